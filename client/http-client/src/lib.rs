@@ -36,6 +36,7 @@
 #![cfg_attr(docsrs, feature(doc_cfg))]
 
 mod client;
+mod pool;
 mod rpc_service;
 
 /// HTTP transport.
@@ -47,6 +48,7 @@ mod tests;
 pub use client::{HttpClient, HttpClientBuilder};
 pub use hyper::http::{HeaderMap, HeaderValue};
 pub use jsonrpsee_types as types;
+pub use pool::{Endpoint, HttpClientPool, HttpClientPoolBuilder, LoadBalancing};
 
 /// This is the default implementation of the [`jsonrpsee_core::middleware::RpcServiceT`] trait used in the [`HttpClient`].
 pub use rpc_service::RpcService;
@@ -0,0 +1,617 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! HTTP client that balances the calls over a pool of endpoints.
+
+use std::fmt;
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+
+use crate::client::{HttpClient, HttpClientBuilder};
+use crate::rpc_service::RpcService;
+use crate::transport::{Error as TransportError, HttpBackend};
+use crate::{HttpRequest, HttpResponse};
+use hyper::body::Bytes;
+use jsonrpsee_core::client::{
+	BatchResponse, ClientT, Error, MiddlewareBatchResponse, MiddlewareMethodResponse, MiddlewareNotifResponse,
+};
+use jsonrpsee_core::middleware::RpcServiceT;
+use jsonrpsee_core::params::BatchRequestBuilder;
+use jsonrpsee_core::traits::ToRpcParams;
+use jsonrpsee_core::{BoxError, JsonRawValue, JsonValue};
+use serde::de::DeserializeOwned;
+use tower::layer::util::Identity;
+use tower::{Layer, Service};
+
+const LOG_TARGET: &str = "jsonrpsee-http-client";
+
+type Logger = tower::layer::util::Stack<jsonrpsee_core::middleware::layer::RpcLoggerLayer, Identity>;
+
+/// Strategy to select which endpoint a call is sent to.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum LoadBalancing {
+	/// Send the calls to the endpoints in turn.
+	#[default]
+	RoundRobin,
+	/// Send the call to the endpoint with the fewest calls in flight.
+	LeastInFlight,
+	/// Send the calls to the endpoints in turn in proportion to their weights.
+	Weighted,
+}
+
+/// An endpoint in the [`HttpClientPool`].
+#[derive(Debug, Clone)]
+pub struct Endpoint {
+	url: String,
+	weight: u32,
+}
+
+impl Endpoint {
+	/// Create a new endpoint with a weight of 1.
+	pub fn new(url: impl Into<String>) -> Self {
+		Self { url: url.into(), weight: 1 }
+	}
+
+	/// Set the weight of the endpoint which is only used by [`LoadBalancing::Weighted`].
+	pub fn weight(mut self, weight: u32) -> Self {
+		self.weight = weight;
+		self
+	}
+}
+
+impl From<&str> for Endpoint {
+	fn from(url: &str) -> Self {
+		Self::new(url)
+	}
+}
+
+impl From<String> for Endpoint {
+	fn from(url: String) -> Self {
+		Self::new(url)
+	}
+}
+
+/// Predicate to decide whether a method may be sent again to another endpoint.
+#[derive(Clone)]
+struct SafeMethods(Arc<dyn Fn(&str) -> bool + Send + Sync>);
+
+impl fmt::Debug for SafeMethods {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str("SafeMethods")
+	}
+}
+
+#[derive(Debug, Clone)]
+struct HealthCheck {
+	method: String,
+	interval: Duration,
+}
+
+/// Builder for [`HttpClientPool`].
+///
+/// # Examples
+///
+/// ```no_run
+/// use jsonrpsee_http_client::{Endpoint, HttpClientPoolBuilder, LoadBalancing};
+/// use std::time::Duration;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let client = HttpClientPoolBuilder::default()
+///         .load_balancing(LoadBalancing::Weighted)
+///         .health_check("system_health", Duration::from_secs(10))
+///         .build([Endpoint::new("http://node-1:9944").weight(3), Endpoint::new("http://node-2:9944")])
+///         .unwrap();
+///
+///     // use client....
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct HttpClientPoolBuilder<HttpMiddleware = Identity, RpcMiddleware = Logger> {
+	client_builder: HttpClientBuilder<HttpMiddleware, RpcMiddleware>,
+	load_balancing: LoadBalancing,
+	max_failures: u32,
+	ejection_duration: Duration,
+	health_check: Option<HealthCheck>,
+	safe_methods: SafeMethods,
+}
+
+impl Default for HttpClientPoolBuilder {
+	fn default() -> Self {
+		Self {
+			client_builder: HttpClientBuilder::default(),
+			load_balancing: LoadBalancing::default(),
+			max_failures: 3,
+			ejection_duration: Duration::from_secs(30),
+			health_check: None,
+			safe_methods: SafeMethods(Arc::new(|_| false)),
+		}
+	}
+}
+
+impl HttpClientPoolBuilder {
+	/// Create a new builder.
+	pub fn new() -> Self {
+		Self::default()
+	}
+}
+
+impl<HttpMiddleware, RpcMiddleware> HttpClientPoolBuilder<HttpMiddleware, RpcMiddleware> {
+	/// Set the [`HttpClientBuilder`] that is used to build the client of each endpoint.
+	pub fn set_client_builder<H, R>(self, client_builder: HttpClientBuilder<H, R>) -> HttpClientPoolBuilder<H, R> {
+		HttpClientPoolBuilder {
+			client_builder,
+			load_balancing: self.load_balancing,
+			max_failures: self.max_failures,
+			ejection_duration: self.ejection_duration,
+			health_check: self.health_check,
+			safe_methods: self.safe_methods,
+		}
+	}
+
+	/// Set the strategy to select the endpoint for each call (default is round-robin).
+	pub fn load_balancing(mut self, load_balancing: LoadBalancing) -> Self {
+		self.load_balancing = load_balancing;
+		self
+	}
+
+	/// Set the number of consecutive transport errors after which an endpoint
+	/// is ejected from the pool (default is 3).
+	///
+	/// `0` disables the passive ejection.
+	pub fn max_failures(mut self, max_failures: u32) -> Self {
+		self.max_failures = max_failures;
+		self
+	}
+
+	/// Set for how long an ejected endpoint is not used (default is 30 seconds).
+	pub fn ejection_duration(mut self, duration: Duration) -> Self {
+		self.ejection_duration = duration;
+		self
+	}
+
+	/// Probe each endpoint periodically by calling the RPC `method` without params (default is disabled).
+	///
+	/// The endpoints are probed concurrently and a probe that doesn't complete within
+	/// the `interval` fails. An endpoint that fails the probe is ejected until it passes
+	/// the probe again.
+	///
+	/// The probes run in a tokio task which requires [`HttpClientPoolBuilder::build`]
+	/// to be called from within a tokio runtime.
+	pub fn health_check(mut self, method: impl Into<String>, interval: Duration) -> Self {
+		self.health_check = Some(HealthCheck { method: method.into(), interval });
+		self
+	}
+
+	/// Set which methods are safe to send again to another endpoint if the call failed
+	/// because of a transport error or timed out (default is no methods).
+	///
+	/// Such a call may already have been executed by the endpoint, thus failover must
+	/// only be enabled for methods which may be executed more than once, such as reads.
+	///
+	/// A batch is only sent again if all methods in the batch are safe.
+	pub fn safe_methods(mut self, f: impl Fn(&str) -> bool + Send + Sync + 'static) -> Self {
+		self.safe_methods = SafeMethods(Arc::new(f));
+		self
+	}
+}
+
+impl<B, S, S2, HttpMiddleware, RpcMiddleware> HttpClientPoolBuilder<HttpMiddleware, RpcMiddleware>
+where
+	RpcMiddleware: Layer<RpcService<S>, Service = S2> + Clone,
+	<RpcMiddleware as Layer<RpcService<S>>>::Service: RpcServiceT,
+	HttpMiddleware: Layer<HttpBackend, Service = S> + Clone,
+	S: Service<HttpRequest, Response = HttpResponse<B>, Error = TransportError> + Clone,
+	B: http_body::Body<Data = Bytes> + Send + Unpin + 'static,
+	B::Data: Send,
+	B::Error: Into<BoxError>,
+	S2: RpcServiceT<
+			MethodResponse = Result<MiddlewareMethodResponse, Error>,
+			BatchResponse = Result<MiddlewareBatchResponse, Error>,
+			NotificationResponse = Result<MiddlewareNotifResponse, Error>,
+		> + Send
+		+ Sync
+		+ 'static,
+{
+	/// Build the client with the endpoints to connect to.
+	///
+	/// Fails if no endpoint was provided or if any of the clients couldn't be built.
+	pub fn build<E: Into<Endpoint>>(self, endpoints: impl IntoIterator<Item = E>) -> Result<HttpClientPool<S2>, Error> {
+		let endpoints = endpoints
+			.into_iter()
+			.map(|e| {
+				let Endpoint { url, weight } = e.into();
+				let client = self.client_builder.clone().build(&url)?;
+				Ok(EndpointState::new(url, weight, client))
+			})
+			.collect::<Result<Vec<_>, Error>>()?;
+
+		if endpoints.is_empty() {
+			return Err(Error::Custom("At least one endpoint must be provided".into()));
+		}
+
+		let inner = Arc::new(Inner {
+			endpoints,
+			load_balancing: self.load_balancing,
+			next: AtomicUsize::new(0),
+			max_failures: self.max_failures,
+			ejection_duration: self.ejection_duration,
+			safe_methods: self.safe_methods,
+		});
+
+		if let Some(health_check) = self.health_check {
+			tokio::spawn(run_health_checks(Arc::downgrade(&inner), health_check));
+		}
+
+		Ok(HttpClientPool { inner })
+	}
+}
+
+/// JSON-RPC HTTP client that balances the calls over a pool of endpoints.
+///
+/// Endpoints which fail with transport errors or time out are ejected from the pool for a while
+/// and such a failed call is transparently sent to another endpoint if the method is regarded
+/// as safe, which no method is by default, see [`HttpClientPoolBuilder::safe_methods`].
+#[derive(Debug)]
+pub struct HttpClientPool<S = jsonrpsee_core::middleware::layer::RpcLogger<RpcService<HttpBackend>>> {
+	inner: Arc<Inner<S>>,
+}
+
+impl<S> Clone for HttpClientPool<S> {
+	fn clone(&self) -> Self {
+		Self { inner: self.inner.clone() }
+	}
+}
+
+impl HttpClientPool<HttpBackend> {
+	/// Create a builder for the HttpClientPool.
+	pub fn builder() -> HttpClientPoolBuilder {
+		HttpClientPoolBuilder::new()
+	}
+}
+
+impl<S> HttpClientPool<S> {
+	/// Returns the URLs of the endpoints that are currently not ejected.
+	pub fn healthy_endpoints(&self) -> Vec<&str> {
+		let now = Instant::now();
+		self.inner.endpoints.iter().filter(|e| !e.is_ejected(now)).map(|e| e.url.as_str()).collect()
+	}
+}
+
+impl<S> ClientT for HttpClientPool<S>
+where
+	S: RpcServiceT<
+			MethodResponse = Result<MiddlewareMethodResponse, Error>,
+			BatchResponse = Result<MiddlewareBatchResponse, Error>,
+			NotificationResponse = Result<MiddlewareNotifResponse, Error>,
+		> + Send
+		+ Sync,
+{
+	fn notification<Params>(&self, method: &str, params: Params) -> impl Future<Output = Result<(), Error>> + Send
+	where
+		Params: ToRpcParams + Send,
+	{
+		async move {
+			let params = params.to_rpc_params()?;
+			let safe = (self.inner.safe_methods.0)(method);
+			let mut tried = Vec::with_capacity(1);
+
+			loop {
+				let endpoint = self.inner.select(&mut tried);
+				let rp = endpoint.track(endpoint.client.notification(method, params.clone())).await;
+
+				if !self.inner.should_failover(endpoint, &rp, safe, tried.len()) {
+					return rp;
+				}
+			}
+		}
+	}
+
+	fn request<R, Params>(&self, method: &str, params: Params) -> impl Future<Output = Result<R, Error>> + Send
+	where
+		R: DeserializeOwned,
+		Params: ToRpcParams + Send,
+	{
+		async move {
+			let params = params.to_rpc_params()?;
+			let safe = (self.inner.safe_methods.0)(method);
+			let mut tried = Vec::with_capacity(1);
+
+			loop {
+				let endpoint = self.inner.select(&mut tried);
+				let rp = endpoint.track(endpoint.client.request(method, params.clone())).await;
+
+				if !self.inner.should_failover(endpoint, &rp, safe, tried.len()) {
+					return rp;
+				}
+			}
+		}
+	}
+
+	fn batch_request<'a, R>(
+		&self,
+		batch: BatchRequestBuilder<'a>,
+	) -> impl Future<Output = Result<BatchResponse<'a, R>, Error>> + Send
+	where
+		R: DeserializeOwned + fmt::Debug + 'a,
+	{
+		async move {
+			let safe = batch.iter().all(|(method, _)| (self.inner.safe_methods.0)(method));
+			let mut tried = Vec::with_capacity(1);
+
+			loop {
+				let endpoint = self.inner.select(&mut tried);
+				let rp = endpoint.track(endpoint.client.batch_request(batch.clone())).await;
+
+				if !self.inner.should_failover(endpoint, &rp, safe, tried.len()) {
+					return rp;
+				}
+			}
+		}
+	}
+}
+
+#[derive(Debug)]
+struct Inner<S> {
+	endpoints: Vec<EndpointState<S>>,
+	load_balancing: LoadBalancing,
+	next: AtomicUsize,
+	max_failures: u32,
+	ejection_duration: Duration,
+	safe_methods: SafeMethods,
+}
+
+impl<S> Inner<S> {
+	/// Select the endpoint for the next attempt of a call among the endpoints
+	/// that haven't been tried yet and adds it to `tried`.
+	///
+	/// Ejected endpoints are only selected if all the other endpoints have been tried.
+	///
+	/// Panics if all endpoints have been tried.
+	fn select(&self, tried: &mut Vec<usize>) -> &EndpointState<S> {
+		let now = Instant::now();
+		let untried = || (0..self.endpoints.len()).filter(|idx| !tried.contains(idx));
+
+		let mut candidates: Vec<usize> = untried().filter(|idx| !self.endpoints[*idx].is_ejected(now)).collect();
+		if candidates.is_empty() {
+			candidates = untried().collect();
+		}
+
+		let next = self.next.fetch_add(1, Ordering::Relaxed);
+
+		let idx = match self.load_balancing {
+			LoadBalancing::RoundRobin => candidates[next % candidates.len()],
+			LoadBalancing::LeastInFlight => {
+				// Start at the round-robin position such that ties are spread over the endpoints.
+				let offset = next % candidates.len();
+				candidates
+					.iter()
+					.cycle()
+					.skip(offset)
+					.take(candidates.len())
+					.copied()
+					.min_by_key(|idx| self.endpoints[*idx].in_flight.load(Ordering::Relaxed))
+					.expect("At least one candidate; qed")
+			}
+			LoadBalancing::Weighted => {
+				let total: u64 = candidates.iter().map(|idx| self.endpoints[*idx].weight as u64).sum();
+
+				if total == 0 {
+					candidates[next % candidates.len()]
+				} else {
+					let mut pos = next as u64 % total;
+					let mut selected = candidates[0];
+					for idx in &candidates {
+						let weight = self.endpoints[*idx].weight as u64;
+						if pos < weight {
+							selected = *idx;
+							break;
+						}
+						pos -= weight;
+					}
+					selected
+				}
+			}
+		};
+
+		tried.push(idx);
+		&self.endpoints[idx]
+	}
+
+	/// Updates the state of the endpoint according to the outcome of a call and
+	/// returns whether the call should be sent to another endpoint.
+	fn should_failover<T>(
+		&self,
+		endpoint: &EndpointState<S>,
+		rp: &Result<T, Error>,
+		safe: bool,
+		attempts: usize,
+	) -> bool {
+		match rp {
+			Err(err @ (Error::Transport(_) | Error::RequestTimeout)) => {
+				tracing::debug!(target: LOG_TARGET, "Endpoint {} failed: {}", endpoint.url, err);
+				endpoint.on_failure(self.max_failures, self.ejection_duration);
+				safe && attempts < self.endpoints.len()
+			}
+			_ => {
+				endpoint.on_success();
+				false
+			}
+		}
+	}
+}
+
+#[derive(Debug)]
+struct EndpointState<S> {
+	url: String,
+	weight: u32,
+	client: HttpClient<S>,
+	in_flight: AtomicUsize,
+	failures: AtomicU32,
+	ejected_until: Mutex<Option<Instant>>,
+}
+
+impl<S> EndpointState<S> {
+	fn new(url: String, weight: u32, client: HttpClient<S>) -> Self {
+		Self {
+			url,
+			weight,
+			client,
+			in_flight: AtomicUsize::new(0),
+			failures: AtomicU32::new(0),
+			ejected_until: Mutex::new(None),
+		}
+	}
+
+	fn is_ejected(&self, now: Instant) -> bool {
+		self.ejected_until.lock().expect("Mutex not poisoned; qed").is_some_and(|until| until > now)
+	}
+
+	fn eject(&self, duration: Duration) {
+		tracing::debug!(target: LOG_TARGET, "Ejecting endpoint {} for {:?}", self.url, duration);
+		*self.ejected_until.lock().expect("Mutex not poisoned; qed") = Some(Instant::now() + duration);
+	}
+
+	fn on_failure(&self, max_failures: u32, ejection_duration: Duration) {
+		let failures = self.failures.fetch_add(1, Ordering::Relaxed) + 1;
+
+		if max_failures > 0 && failures >= max_failures {
+			self.failures.store(0, Ordering::Relaxed);
+			self.eject(ejection_duration);
+		}
+	}
+
+	fn on_success(&self) {
+		self.failures.store(0, Ordering::Relaxed);
+		*self.ejected_until.lock().expect("Mutex not poisoned; qed") = None;
+	}
+
+	/// Keep track of the number of calls in flight, also if the call is cancelled.
+	async fn track<F: Future>(&self, fut: F) -> F::Output {
+		struct InFlightGuard<'a>(&'a AtomicUsize);
+
+		impl Drop for InFlightGuard<'_> {
+			fn drop(&mut self) {
+				self.0.fetch_sub(1, Ordering::Relaxed);
+			}
+		}
+
+		self.in_flight.fetch_add(1, Ordering::Relaxed);
+		let _guard = InFlightGuard(&self.in_flight);
+		fut.await
+	}
+}
+
+async fn run_health_checks<S>(inner: Weak<Inner<S>>, health_check: HealthCheck)
+where
+	S: RpcServiceT<
+			MethodResponse = Result<MiddlewareMethodResponse, Error>,
+			BatchResponse = Result<MiddlewareBatchResponse, Error>,
+			NotificationResponse = Result<MiddlewareNotifResponse, Error>,
+		> + Send
+		+ Sync,
+{
+	let mut interval = tokio::time::interval(health_check.interval);
+
+	loop {
+		interval.tick().await;
+
+		// The client has been dropped.
+		let Some(inner) = inner.upgrade() else {
+			return;
+		};
+
+		let probes = inner.endpoints.iter().map(|endpoint| async {
+			let probe = endpoint.client.request::<JsonValue, _>(&health_check.method, None::<Box<JsonRawValue>>);
+
+			match tokio::time::timeout(health_check.interval, probe).await {
+				Ok(Ok(_)) => endpoint.on_success(),
+				Ok(Err(err)) => {
+					tracing::debug!(target: LOG_TARGET, "Health check of {} failed: {}", endpoint.url, err);
+					endpoint.eject(health_check.interval.max(inner.ejection_duration));
+				}
+				Err(_) => {
+					tracing::debug!(target: LOG_TARGET, "Health check of {} timed out", endpoint.url);
+					endpoint.eject(health_check.interval.max(inner.ejection_duration));
+				}
+			}
+		});
+
+		futures_util::future::join_all(probes).await;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Endpoint, HttpClientPool, HttpClientPoolBuilder, LoadBalancing};
+	use std::sync::atomic::Ordering;
+	use std::time::Duration;
+
+	fn pool(load_balancing: LoadBalancing, endpoints: Vec<Endpoint>) -> HttpClientPool {
+		HttpClientPoolBuilder::default().load_balancing(load_balancing).build(endpoints).unwrap()
+	}
+
+	fn select_n(pool: &HttpClientPool, n: usize) -> Vec<&str> {
+		(0..n).map(|_| pool.inner.select(&mut Vec::new()).url.as_str()).collect()
+	}
+
+	#[test]
+	fn round_robin_works() {
+		let pool = pool(LoadBalancing::RoundRobin, vec!["http://a".into(), "http://b".into()]);
+		assert_eq!(select_n(&pool, 4), vec!["http://a", "http://b", "http://a", "http://b"]);
+	}
+
+	#[test]
+	fn weighted_works() {
+		let pool = pool(
+			LoadBalancing::Weighted,
+			vec![Endpoint::new("http://a").weight(3), Endpoint::new("http://b").weight(1)],
+		);
+		let selected = select_n(&pool, 8);
+		assert_eq!(selected.iter().filter(|url| **url == "http://a").count(), 6);
+		assert_eq!(selected.iter().filter(|url| **url == "http://b").count(), 2);
+	}
+
+	#[test]
+	fn least_in_flight_works() {
+		let pool = pool(LoadBalancing::LeastInFlight, vec!["http://a".into(), "http://b".into()]);
+		pool.inner.endpoints[0].in_flight.store(2, Ordering::Relaxed);
+		pool.inner.endpoints[1].in_flight.store(1, Ordering::Relaxed);
+		assert_eq!(select_n(&pool, 3), vec!["http://b", "http://b", "http://b"]);
+	}
+
+	#[test]
+	fn ejected_endpoints_are_skipped_until_all_tried() {
+		let pool = pool(LoadBalancing::RoundRobin, vec!["http://a".into(), "http://b".into()]);
+		pool.inner.endpoints[1].eject(Duration::from_secs(60));
+		assert_eq!(select_n(&pool, 3), vec!["http://a", "http://a", "http://a"]);
+
+		let mut tried = Vec::new();
+		assert_eq!(pool.inner.select(&mut tried).url, "http://a");
+		assert_eq!(pool.inner.select(&mut tried).url, "http://b");
+	}
+}
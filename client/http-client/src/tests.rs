@@ -26,8 +26,8 @@
 
 use std::time::Duration;
 
-use crate::HttpClientBuilder;
 use crate::types::error::{ErrorCode, ErrorObject};
+use crate::{HttpClientBuilder, HttpClientPoolBuilder};
 use jsonrpsee_core::ClientError;
 use jsonrpsee_core::client::{BatchResponse, ClientT, IdKind};
 use jsonrpsee_core::params::BatchRequestBuilder;
@@ -278,10 +278,192 @@ async fn batch_request_out_of_order_response() {
 	assert_eq!(response, vec!["hello".to_string(), "goodbye".to_string(), "here's your swag".to_string()]);
 }
 
+#[tokio::test]
+async fn pool_fails_over_to_another_endpoint() {
+	init_logger();
+
+	let server_addr = http_server_with_hardcoded_response(ok_response("hello".into(), Id::Num(0)))
+		.with_default_timeout()
+		.await
+		.unwrap();
+	let dead = dead_endpoint().await;
+	let live = format!("http://{server_addr}");
+
+	let client = HttpClientPoolBuilder::default()
+		.max_failures(1)
+		.safe_methods(|method| method == "say_hello")
+		.build([dead.as_str(), live.as_str()])
+		.unwrap();
+	assert_eq!(client.healthy_endpoints(), vec![dead.as_str(), live.as_str()]);
+
+	let response: String = client.request("say_hello", rpc_params![]).with_default_timeout().await.unwrap().unwrap();
+	assert_eq!(&response, "hello");
+	assert_eq!(client.healthy_endpoints(), vec![live.as_str()]);
+}
+
+#[tokio::test]
+async fn pool_doesnt_fail_over_unsafe_methods() {
+	init_logger();
+
+	let server_addr = http_server_with_hardcoded_response(ok_response("hello".into(), Id::Num(0)))
+		.with_default_timeout()
+		.await
+		.unwrap();
+	let dead = dead_endpoint().await;
+	let live = format!("http://{server_addr}");
+
+	// No method is safe by default.
+	let client = HttpClientPoolBuilder::default().build([dead.as_str(), live.as_str()]).unwrap();
+
+	let err = client.request::<String, _>("send_transaction", rpc_params![]).with_default_timeout().await.unwrap();
+	assert!(matches!(err, Err(ClientError::Transport(_))));
+
+	let client = HttpClientPoolBuilder::default()
+		.safe_methods(|method| method != "send_transaction")
+		.build([dead.as_str(), live.as_str()])
+		.unwrap();
+
+	let err = client.request::<String, _>("send_transaction", rpc_params![]).with_default_timeout().await.unwrap();
+	assert!(matches!(err, Err(ClientError::Transport(_))));
+}
+
+#[tokio::test]
+async fn pool_ejects_endpoints_that_time_out() {
+	init_logger();
+
+	let server_addr = http_server_with_hardcoded_response(ok_response("hello".into(), Id::Num(0)))
+		.with_default_timeout()
+		.await
+		.unwrap();
+	let hanging = hanging_endpoint().await;
+	let live = format!("http://{server_addr}");
+
+	let client = HttpClientPoolBuilder::default()
+		.set_client_builder(HttpClientBuilder::default().request_timeout(Duration::from_millis(100)))
+		.max_failures(1)
+		.safe_methods(|method| method == "say_hello")
+		.build([hanging.as_str(), live.as_str()])
+		.unwrap();
+
+	let response: String = client.request("say_hello", rpc_params![]).with_default_timeout().await.unwrap().unwrap();
+	assert_eq!(&response, "hello");
+	assert_eq!(client.healthy_endpoints(), vec![live.as_str()]);
+}
+
+#[tokio::test]
+async fn pool_health_check_ejects_endpoints() {
+	init_logger();
+
+	let server_addr =
+		http_server_with_hardcoded_response(ok_response("ok".into(), Id::Num(0))).with_default_timeout().await.unwrap();
+	let dead = dead_endpoint().await;
+	let live = format!("http://{server_addr}");
+
+	let client = HttpClientPoolBuilder::default()
+		.health_check("system_health", Duration::from_secs(60))
+		.build([dead.as_str(), live.as_str()])
+		.unwrap();
+
+	// The first probe runs immediately.
+	for _ in 0..50 {
+		if client.healthy_endpoints() == vec![live.as_str()] {
+			return;
+		}
+		tokio::time::sleep(Duration::from_millis(100)).await;
+	}
+
+	panic!("The dead endpoint should be ejected by the health check");
+}
+
+#[tokio::test]
+async fn pool_health_check_probes_endpoints_concurrently() {
+	init_logger();
+
+	let hanging = hanging_endpoint().await;
+	let dead = dead_endpoint().await;
+
+	let client = HttpClientPoolBuilder::default()
+		.health_check("system_health", Duration::from_millis(200))
+		.build([hanging.as_str(), dead.as_str()])
+		.unwrap();
+
+	// The hanging endpoint doesn't hold up the probe of the dead endpoint and
+	// its own probe fails once the interval has elapsed.
+	for _ in 0..50 {
+		if client.healthy_endpoints().is_empty() {
+			return;
+		}
+		tokio::time::sleep(Duration::from_millis(100)).await;
+	}
+
+	panic!("Both endpoints should be ejected by the health check");
+}
+
+#[test]
+fn pool_without_endpoints_fails() {
+	let err = HttpClientPoolBuilder::default().build(Vec::<String>::new());
+	assert!(matches!(err, Err(ClientError::Custom(_))));
+}
+
+#[tokio::test]
+async fn circuit_breaker_fails_fast_when_open() {
+	use jsonrpsee_core::middleware::RpcServiceBuilder;
+	use jsonrpsee_core::middleware::layer::{CircuitBreakerLayer, CircuitState};
+	use std::sync::{Arc, Mutex};
+
+	let changes = Arc::new(Mutex::new(Vec::new()));
+	let changes2 = changes.clone();
+
+	let breaker = CircuitBreakerLayer::new()
+		.minimum_calls(2)
+		.open_duration(Duration::from_millis(100))
+		.on_state_change(move |change| changes2.lock().unwrap().push(change.to()));
+	let client = HttpClientBuilder::default()
+		.set_rpc_middleware(RpcServiceBuilder::new().layer(breaker))
+		.build(dead_endpoint().await)
+		.unwrap();
+
+	for _ in 0..2 {
+		let err = client.request::<String, _>("say_hello", rpc_params![]).await.unwrap_err();
+		assert!(matches!(err, ClientError::Transport(_)));
+	}
+
+	let err = client.request::<String, _>("say_hello", rpc_params![]).await.unwrap_err();
+	assert!(matches!(err, ClientError::CircuitOpen(None)));
+	assert_eq!(*changes.lock().unwrap(), vec![CircuitState::Open]);
+
+	// The probe call in the half-open state fails and the circuit is re-opened.
+	tokio::time::sleep(Duration::from_millis(100)).await;
+	let err = client.request::<String, _>("say_hello", rpc_params![]).await.unwrap_err();
+	assert!(matches!(err, ClientError::Transport(_)));
+	assert_eq!(*changes.lock().unwrap(), vec![CircuitState::Open, CircuitState::HalfOpen, CircuitState::Open]);
+}
+
+/// Returns the URL of an endpoint that refuses connections.
+async fn dead_endpoint() -> String {
+	let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+	let addr = listener.local_addr().unwrap();
+	drop(listener);
+	format!("http://{addr}")
+}
+
+/// Returns the URL of an endpoint that accepts connections but never responds.
+async fn hanging_endpoint() -> String {
+	let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+	let addr = listener.local_addr().unwrap();
+	tokio::spawn(async move {
+		let mut connections = Vec::new();
+		while let Ok((socket, _)) = listener.accept().await {
+			connections.push(socket);
+		}
+	});
+	format!("http://{addr}")
+}
+
 async fn run_batch_request_with_response<T: Send + DeserializeOwned + std::fmt::Debug + Clone + 'static>(
 	batch: BatchRequestBuilder<'_>,
 	response: String,
-) -> Result<BatchResponse<T>, ClientError> {
+) -> Result<BatchResponse<'_, T>, ClientError> {
 	let server_addr = http_server_with_hardcoded_response(response).with_default_timeout().await.unwrap();
 	let uri = format!("http://{server_addr}");
 	let client = HttpClientBuilder::default().build(&uri).unwrap();
@@ -0,0 +1,193 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Typed batch requests.
+
+use std::fmt;
+use std::marker::PhantomData;
+
+use crate::client::{ClientT, Error};
+use crate::params::BatchRequestBuilder;
+use crate::traits::ToRpcParams;
+
+use jsonrpsee_types::ErrorObjectOwned;
+use serde::de::DeserializeOwned;
+use serde_json::Value as JsonValue;
+
+/// The maximum number of calls in a [`TypedBatch`].
+pub const MAX_TYPED_BATCH_LEN: usize = 12;
+
+/// Batch request where the response of each call is decoded to its own type,
+/// which is created by [`ClientT::typed_batch`].
+///
+/// The calls are usually inserted by the methods of the `<TRAIT>Batch` traits generated by
+/// `#[rpc(client)]`, `T` is the tuple of the response types of the inserted calls.
+/// Sending the batch returns a tuple of the responses in the same order as the calls were inserted,
+/// see [`BatchResponses`].
+///
+/// A typed batch may contain at most [`MAX_TYPED_BATCH_LEN`] calls.
+///
+/// ```no_run
+/// use jsonrpsee_core::client::{ClientT, TypedBatch};
+/// use jsonrpsee_core::rpc_params;
+///
+/// async fn example(client: impl ClientT) {
+///     let (a, b) = client
+///         .typed_batch()
+///         .insert::<u64, _>("block_number", rpc_params![])
+///         .insert::<String, _>("block_hash", rpc_params![1])
+///         .send()
+///         .await
+///         .unwrap();
+///
+///     let number: u64 = a.unwrap();
+///     let hash: String = b.unwrap();
+/// }
+/// ```
+pub struct TypedBatch<'a, C: ?Sized, T> {
+	client: &'a C,
+	batch: BatchRequestBuilder<'a>,
+	error: Option<serde_json::Error>,
+	_marker: PhantomData<fn() -> T>,
+}
+
+impl<C: ?Sized, T> fmt::Debug for TypedBatch<'_, C, T> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("TypedBatch").field("batch", &self.batch).field("error", &self.error).finish()
+	}
+}
+
+impl<'a, C: ?Sized> TypedBatch<'a, C, ()> {
+	/// Create an empty batch sent by `client`.
+	pub fn new(client: &'a C) -> Self {
+		Self { client, batch: BatchRequestBuilder::new(), error: None, _marker: PhantomData }
+	}
+}
+
+impl<'a, C, T> TypedBatch<'a, C, T>
+where
+	C: ClientT + ?Sized,
+{
+	/// Insert the call of `method` with `params` whose response is decoded to `R`.
+	///
+	/// Failing to serialize the params is reported when the batch is sent.
+	pub fn insert<R, Params>(mut self, method: &'a str, params: Params) -> TypedBatch<'a, C, T::Output>
+	where
+		T: BatchPush<R>,
+		Params: ToRpcParams,
+	{
+		if let Err(err) = self.batch.insert(method, params) {
+			self.error.get_or_insert(err);
+		}
+
+		TypedBatch { client: self.client, batch: self.batch, error: self.error, _marker: PhantomData }
+	}
+
+	/// Send the batch and decode the responses.
+	///
+	/// Returns `Err` if the batch failed as a whole, such as when the network failed or a
+	/// response couldn't be decoded. Otherwise each call has its own result.
+	pub async fn send(self) -> Result<T::Responses, Error>
+	where
+		T: BatchResponses,
+	{
+		if let Some(err) = self.error {
+			return Err(Error::ParseError(err));
+		}
+
+		let responses = self.client.batch_request::<JsonValue>(self.batch).await?;
+		let mut responses = responses.into_iter().map(|entry| entry.map_err(|e| e.into_owned()));
+
+		T::decode(&mut responses)
+	}
+}
+
+/// Append the response type `R` to the response types of a [`TypedBatch`].
+pub trait BatchPush<R> {
+	/// The response types with `R` appended.
+	type Output;
+}
+
+/// Decode the responses of a [`TypedBatch`] into a tuple of typed results.
+pub trait BatchResponses {
+	/// A tuple of `Result<R, ErrorObjectOwned>` for each response type `R`.
+	type Responses;
+
+	/// Decode the responses which are in the same order as the calls.
+	fn decode(
+		responses: &mut dyn Iterator<Item = Result<JsonValue, ErrorObjectOwned>>,
+	) -> Result<Self::Responses, Error>;
+}
+
+fn decode_next<R: DeserializeOwned>(
+	responses: &mut dyn Iterator<Item = Result<JsonValue, ErrorObjectOwned>>,
+) -> Result<Result<R, ErrorObjectOwned>, Error> {
+	match responses.next() {
+		Some(Ok(value)) => Ok(Ok(serde_json::from_value(value)?)),
+		Some(Err(err)) => Ok(Err(err)),
+		None => Err(Error::Custom("Missing response in batch".to_string())),
+	}
+}
+
+macro_rules! impl_batch_responses {
+	($($ty:ident),*) => {
+		impl<$($ty: DeserializeOwned),*> BatchResponses for ($($ty,)*) {
+			type Responses = ($(Result<$ty, ErrorObjectOwned>,)*);
+
+			#[allow(unused_variables)]
+			fn decode(
+				responses: &mut dyn Iterator<Item = Result<JsonValue, ErrorObjectOwned>>,
+			) -> Result<Self::Responses, Error> {
+				Ok(($(decode_next::<$ty>(responses)?,)*))
+			}
+		}
+	};
+}
+
+macro_rules! impl_batch_tuple {
+	($($ty:ident),*) => {
+		impl<$($ty,)* R> BatchPush<R> for ($($ty,)*) {
+			type Output = ($($ty,)* R,);
+		}
+
+		impl_batch_responses!($($ty),*);
+	};
+}
+
+impl_batch_tuple!();
+impl_batch_tuple!(T1);
+impl_batch_tuple!(T1, T2);
+impl_batch_tuple!(T1, T2, T3);
+impl_batch_tuple!(T1, T2, T3, T4);
+impl_batch_tuple!(T1, T2, T3, T4, T5);
+impl_batch_tuple!(T1, T2, T3, T4, T5, T6);
+impl_batch_tuple!(T1, T2, T3, T4, T5, T6, T7);
+impl_batch_tuple!(T1, T2, T3, T4, T5, T6, T7, T8);
+impl_batch_tuple!(T1, T2, T3, T4, T5, T6, T7, T8, T9);
+impl_batch_tuple!(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10);
+impl_batch_tuple!(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11);
+// The largest tuple doesn't implement `BatchPush` such that at most `MAX_TYPED_BATCH_LEN` calls can be inserted.
+impl_batch_responses!(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12);
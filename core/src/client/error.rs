@@ -63,6 +63,12 @@ pub enum Error {
 	/// Empty batch request.
 	#[error(transparent)]
 	EmptyBatchRequest(#[from] EmptyBatchRequest),
+	/// The call was rejected because the circuit breaker is open.
+	///
+	/// Contains the method name if the circuit of the method is open
+	/// or `None` if the circuit of the whole endpoint is open.
+	#[error("Circuit breaker is open")]
+	CircuitOpen(Option<String>),
 	/// The error returned when registering a method or subscription failed.
 	#[error(transparent)]
 	RegisterMethod(#[from] RegisterMethodError),
@@ -78,3 +84,51 @@ pub enum Error {
 	#[doc(hidden)]
 	ServiceDisconnect,
 }
+
+/// Error of a call to an RPC API which declares its own error type `E`,
+/// such as the clients generated by `#[rpc(client, error = E)]`.
+///
+/// JSON-RPC errors which can be decoded as `E` are returned as [`ClientError::Rpc`] and
+/// all other errors, including JSON-RPC errors that aren't part of the API, as [`ClientError::Client`].
+#[derive(Debug, thiserror::Error)]
+pub enum ClientError<E> {
+	/// The server responded with an error of the RPC API.
+	#[error("{0}")]
+	Rpc(E),
+	/// The call failed for another reason.
+	#[error(transparent)]
+	Client(Error),
+}
+
+impl<E> ClientError<E> {
+	/// Returns the error of the RPC API, if any.
+	pub fn rpc(&self) -> Option<&E> {
+		match self {
+			Self::Rpc(e) => Some(e),
+			Self::Client(_) => None,
+		}
+	}
+
+	/// Returns the client error, if any.
+	pub fn client(&self) -> Option<&Error> {
+		match self {
+			Self::Rpc(_) => None,
+			Self::Client(e) => Some(e),
+		}
+	}
+}
+
+impl<E> From<Error> for ClientError<E>
+where
+	E: TryFrom<ErrorObjectOwned, Error = ErrorObjectOwned>,
+{
+	fn from(err: Error) -> Self {
+		match err {
+			Error::Call(err) => match E::try_from(err) {
+				Ok(e) => Self::Rpc(e),
+				Err(err) => Self::Client(Error::Call(err)),
+			},
+			err => Self::Client(err),
+		}
+	}
+}
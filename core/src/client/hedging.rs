@@ -0,0 +1,352 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Client that hedges calls over several clients to cut tail latency.
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::client::{BatchResponse, ClientT, Error};
+use crate::params::BatchRequestBuilder;
+use crate::traits::ToRpcParams;
+
+use futures_util::future::{Either, select};
+use futures_util::stream::{FuturesUnordered, StreamExt};
+use serde::de::DeserializeOwned;
+
+/// The number of latency samples kept per method.
+const MAX_SAMPLES: usize = 128;
+/// The number of latency samples required before the percentile is used.
+const MIN_SAMPLES: usize = 16;
+
+/// When to send a duplicate of a call to another client.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum HedgeDelay {
+	/// Never hedge the call.
+	Disabled,
+	/// Hedge the call if it hasn't completed after a fixed delay.
+	Fixed(Duration),
+	/// Hedge the call if it hasn't completed after the given percentile, between `0.0` and `1.0`,
+	/// of the latency of recent successful calls to the same method.
+	Percentile(f64),
+}
+
+/// Builder for [`HedgedClient`].
+#[derive(Debug, Clone)]
+pub struct HedgedClientBuilder {
+	default_delay: HedgeDelay,
+	method_delays: HashMap<String, HedgeDelay>,
+	initial_delay: Duration,
+	max_hedged_requests: usize,
+}
+
+impl Default for HedgedClientBuilder {
+	fn default() -> Self {
+		Self {
+			default_delay: HedgeDelay::Disabled,
+			method_delays: HashMap::new(),
+			initial_delay: Duration::from_millis(100),
+			max_hedged_requests: 1,
+		}
+	}
+}
+
+impl HedgedClientBuilder {
+	/// Create a new builder.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Set the hedge delay of all methods without a method specific delay.
+	///
+	/// Only enable hedging for all methods if all of them are safe to execute more than once.
+	///
+	/// Default: [`HedgeDelay::Disabled`].
+	pub fn default_delay(mut self, delay: HedgeDelay) -> Self {
+		self.default_delay = delay;
+		self
+	}
+
+	/// Set the hedge delay of a specific method, for example `HedgeDelay::Percentile(0.95)`
+	/// to hedge the calls to the method that are slower than 95% of the recent calls.
+	pub fn method_delay(mut self, method: impl Into<String>, delay: HedgeDelay) -> Self {
+		self.method_delays.insert(method.into(), delay);
+		self
+	}
+
+	/// Set the hedge delay used by [`HedgeDelay::Percentile`] until enough calls
+	/// to the method have been made to compute the percentile.
+	///
+	/// Default: 100ms.
+	pub fn initial_delay(mut self, delay: Duration) -> Self {
+		self.initial_delay = delay;
+		self
+	}
+
+	/// Set the maximum number of duplicates that are sent for a call.
+	///
+	/// Default: 1.
+	pub fn max_hedged_requests(mut self, max: usize) -> Self {
+		self.max_hedged_requests = max;
+		self
+	}
+
+	/// Build the client over the given clients.
+	///
+	/// Calls are spread over the clients in a round-robin fashion and
+	/// duplicates are sent to the clients following the one that got the call.
+	pub fn build<C>(self, clients: impl IntoIterator<Item = C>) -> Result<HedgedClient<C>, Error> {
+		let clients: Vec<C> = clients.into_iter().collect();
+
+		if clients.is_empty() {
+			return Err(Error::Custom("At least one client must be provided".into()));
+		}
+
+		Ok(HedgedClient {
+			inner: Arc::new(Inner {
+				clients,
+				next: AtomicUsize::new(0),
+				config: self,
+				latencies: Mutex::new(HashMap::new()),
+			}),
+		})
+	}
+}
+
+/// A client which sends duplicates of slow calls to other clients and takes the first success.
+///
+/// If a call hasn't completed within the [hedge delay](HedgeDelay) of the method,
+/// the same call is sent to another client. Whichever call succeeds first is returned
+/// and the other calls are cancelled. Calls that fail with a transport error or timeout are
+/// hedged right away, whereas JSON-RPC errors are returned as is.
+///
+/// Hedging is intended for read calls against replicated nodes, thus it's disabled by default
+/// and must be enabled per method with [`HedgedClientBuilder::method_delay`] for the methods
+/// which are safe to execute more than once. Notifications and batch requests are not hedged.
+///
+/// A call which fails with a transport error or a timeout is sent to the next client right away,
+/// any other error is returned as is.
+#[derive(Debug)]
+pub struct HedgedClient<C> {
+	inner: Arc<Inner<C>>,
+}
+
+impl<C> Clone for HedgedClient<C> {
+	fn clone(&self) -> Self {
+		Self { inner: self.inner.clone() }
+	}
+}
+
+impl<C> HedgedClient<C> {
+	/// Get the clients.
+	pub fn clients(&self) -> &[C] {
+		&self.inner.clients
+	}
+}
+
+impl<C> ClientT for HedgedClient<C>
+where
+	C: ClientT + Send + Sync,
+{
+	fn notification<Params>(&self, method: &str, params: Params) -> impl Future<Output = Result<(), Error>> + Send
+	where
+		Params: ToRpcParams + Send,
+	{
+		self.inner.next_client().notification(method, params)
+	}
+
+	fn request<R, Params>(&self, method: &str, params: Params) -> impl Future<Output = Result<R, Error>> + Send
+	where
+		R: DeserializeOwned,
+		Params: ToRpcParams + Send,
+	{
+		async move {
+			let params = params.to_rpc_params()?;
+			let inner = &self.inner;
+			let first = inner.next.fetch_add(1, Ordering::Relaxed);
+			let delay = inner.delay(method);
+			let max_requests =
+				if delay.is_some() { (inner.config.max_hedged_requests + 1).min(inner.clients.len()) } else { 1 };
+
+			let send = |n: usize| {
+				let client = &inner.clients[(first + n) % inner.clients.len()];
+				let params = params.clone();
+				async move {
+					let started = Instant::now();
+					let rp = client.request::<R, _>(method, params).await;
+					(rp, started.elapsed())
+				}
+			};
+
+			let mut in_flight = FuturesUnordered::new();
+			in_flight.push(send(0));
+			let mut sent = 1;
+
+			loop {
+				let hedge = async {
+					match delay {
+						Some(delay) if sent < max_requests => futures_timer::Delay::new(delay).await,
+						_ => futures_util::future::pending().await,
+					}
+				};
+
+				let completed = match select(in_flight.next(), std::pin::pin!(hedge)).await {
+					Either::Left((completed, _)) => completed,
+					Either::Right(_) => None,
+				};
+
+				match completed {
+					Some((Ok(rp), elapsed)) => {
+						inner.record(method, elapsed);
+						return Ok(rp);
+					}
+					Some((Err(err), _)) if !should_hedge(&err) || (sent == max_requests && in_flight.is_empty()) => {
+						return Err(err);
+					}
+					// Wait for the other calls in flight.
+					Some((Err(_), _)) if sent == max_requests => (),
+					// The hedge delay elapsed or the call failed, send it to the next client.
+					_ => {
+						in_flight.push(send(sent));
+						sent += 1;
+					}
+				}
+			}
+		}
+	}
+
+	fn batch_request<'a, R>(
+		&self,
+		batch: BatchRequestBuilder<'a>,
+	) -> impl Future<Output = Result<BatchResponse<'a, R>, Error>> + Send
+	where
+		R: DeserializeOwned + fmt::Debug + 'a,
+	{
+		self.inner.next_client().batch_request(batch)
+	}
+}
+
+#[derive(Debug)]
+struct Inner<C> {
+	clients: Vec<C>,
+	next: AtomicUsize,
+	config: HedgedClientBuilder,
+	latencies: Mutex<HashMap<String, VecDeque<Duration>>>,
+}
+
+impl<C> Inner<C> {
+	fn next_client(&self) -> &C {
+		&self.clients[self.next.fetch_add(1, Ordering::Relaxed) % self.clients.len()]
+	}
+
+	/// Get the hedge delay of the method or `None` if the method is not hedged.
+	fn delay(&self, method: &str) -> Option<Duration> {
+		if self.clients.len() < 2 {
+			return None;
+		}
+
+		match self.config.method_delays.get(method).unwrap_or(&self.config.default_delay) {
+			HedgeDelay::Disabled => None,
+			HedgeDelay::Fixed(delay) => Some(*delay),
+			HedgeDelay::Percentile(p) => {
+				let latencies = self.latencies.lock().expect("Mutex not poisoned; qed");
+				match latencies.get(method) {
+					Some(samples) if samples.len() >= MIN_SAMPLES => Some(percentile(samples, *p)),
+					_ => Some(self.config.initial_delay),
+				}
+			}
+		}
+	}
+
+	fn record(&self, method: &str, latency: Duration) {
+		let mut latencies = self.latencies.lock().expect("Mutex not poisoned; qed");
+		let samples = latencies.entry(method.to_owned()).or_default();
+
+		if samples.len() == MAX_SAMPLES {
+			samples.pop_front();
+		}
+		samples.push_back(latency);
+	}
+}
+
+fn percentile(samples: &VecDeque<Duration>, p: f64) -> Duration {
+	let mut sorted: Vec<_> = samples.iter().copied().collect();
+	sorted.sort_unstable();
+	let idx = ((sorted.len() as f64 * p.clamp(0.0, 1.0)).ceil() as usize).saturating_sub(1);
+	sorted[idx.min(sorted.len() - 1)]
+}
+
+/// Whether the call may succeed on another client after it failed with `err`.
+fn should_hedge(err: &Error) -> bool {
+	matches!(err, Error::Transport(_) | Error::RequestTimeout | Error::RestartNeeded(_))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn percentile_works() {
+		let samples: VecDeque<_> = (1..=100).map(Duration::from_millis).collect();
+
+		assert_eq!(percentile(&samples, 0.95), Duration::from_millis(95));
+		assert_eq!(percentile(&samples, 0.5), Duration::from_millis(50));
+		assert_eq!(percentile(&samples, 1.0), Duration::from_millis(100));
+		assert_eq!(percentile(&samples, 0.0), Duration::from_millis(1));
+	}
+
+	#[test]
+	fn delay_falls_back_to_initial_delay() {
+		let client = HedgedClientBuilder::new()
+			.initial_delay(Duration::from_millis(7))
+			.method_delay("read", HedgeDelay::Percentile(0.95))
+			.method_delay("fixed", HedgeDelay::Fixed(Duration::from_millis(3)))
+			.build([(), ()])
+			.unwrap();
+		let inner = &client.inner;
+
+		assert_eq!(inner.delay("read"), Some(Duration::from_millis(7)));
+		assert_eq!(inner.delay("write"), None);
+		assert_eq!(inner.delay("fixed"), Some(Duration::from_millis(3)));
+
+		for _ in 0..MIN_SAMPLES {
+			inner.record("read", Duration::from_millis(20));
+		}
+		assert_eq!(inner.delay("read"), Some(Duration::from_millis(20)));
+	}
+
+	#[test]
+	fn only_transport_errors_are_hedged() {
+		assert!(should_hedge(&Error::RequestTimeout));
+		assert!(should_hedge(&Error::Transport("connection reset".into())));
+		assert!(!should_hedge(&Error::ParseError(serde_json::from_str::<()>("x").unwrap_err())));
+		assert!(!should_hedge(&Error::Call(jsonrpsee_types::ErrorObject::owned(-32000, "failed", None::<()>))));
+	}
+}
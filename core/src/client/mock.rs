@@ -0,0 +1,123 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Building blocks of the mocks generated by `#[rpc(mock)]`.
+
+use std::fmt;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use crate::client::{Subscription, SubscriptionKind, subscription_channel};
+
+use jsonrpsee_types::ErrorObjectOwned;
+use serde_json::value::RawValue;
+use tokio::sync::mpsc;
+
+type Handler<Args, R> = Box<dyn FnMut(Args) -> Result<R, ErrorObjectOwned> + Send>;
+
+/// Expectation of a mocked RPC method or subscription.
+///
+/// It records the arguments of every call and answers them with the handler
+/// set by [`MockMethod::expect`].
+pub struct MockMethod<Args, R> {
+	name: &'static str,
+	handler: Mutex<Option<Handler<Args, R>>>,
+	calls: Mutex<Vec<Args>>,
+}
+
+impl<Args, R> fmt::Debug for MockMethod<Args, R> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("MockMethod")
+			.field("name", &self.name)
+			.field("expected", &lock(&self.handler).is_some())
+			.field("calls", &lock(&self.calls).len())
+			.finish()
+	}
+}
+
+impl<Args: Clone, R> MockMethod<Args, R> {
+	/// Create a new expectation of the RPC method `name` which isn't expected to be called.
+	pub fn new(name: &'static str) -> Self {
+		Self { name, handler: Mutex::new(None), calls: Mutex::new(Vec::new()) }
+	}
+
+	/// Name of the RPC method.
+	pub fn name(&self) -> &'static str {
+		self.name
+	}
+
+	/// Expect calls of the method, which are answered by `handler`.
+	///
+	/// Replaces the previous expectation of the method.
+	pub fn expect(&self, handler: impl FnMut(Args) -> Result<R, ErrorObjectOwned> + Send + 'static) {
+		*lock(&self.handler) = Some(Box::new(handler));
+	}
+
+	/// Record the call and answer it.
+	///
+	/// # Panics
+	///
+	/// Panics if the method isn't expected to be called.
+	pub fn call(&self, args: Args) -> Result<R, ErrorObjectOwned> {
+		lock(&self.calls).push(args.clone());
+		match lock(&self.handler).as_mut() {
+			Some(handler) => handler(args),
+			None => panic!("Unexpected call of RPC method `{}`: no expectation was set", self.name),
+		}
+	}
+
+	/// The arguments of the calls made so far.
+	pub fn calls(&self) -> Vec<Args> {
+		lock(&self.calls).clone()
+	}
+
+	/// Check that the method was called if it was expected to be called.
+	///
+	/// # Panics
+	///
+	/// Panics if an expectation was set but the method was never called.
+	pub fn checkpoint(&self) {
+		if lock(&self.handler).is_some() && lock(&self.calls).is_empty() {
+			panic!("Expected a call of RPC method `{}` which was never made", self.name);
+		}
+	}
+}
+
+/// Create a subscription which yields the `notifications` and is then closed.
+pub fn mock_subscription<Notif>(method: &str, notifications: Vec<Box<RawValue>>) -> Subscription<Notif> {
+	let (tx, rx) = subscription_channel(notifications.len().max(1));
+	for notif in notifications {
+		tx.send(notif).expect("The channel has capacity for all the notifications; qed");
+	}
+	// Nothing listens to the unsubscribe message of the subscription.
+	let (to_back, _) = mpsc::channel(1);
+
+	Subscription::new(to_back, rx, SubscriptionKind::Method(method.to_owned()))
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+	// The handler of a mock may panic, the state remains consistent anyway.
+	mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
@@ -31,9 +31,14 @@ cfg_async_client! {
 	pub use async_client::{Client, ClientBuilder};
 }
 
+mod batch;
 pub mod error;
+mod hedging;
+pub mod mock;
 
-pub use error::Error;
+pub use batch::{BatchPush, BatchResponses, MAX_TYPED_BATCH_LEN, TypedBatch};
+pub use error::{ClientError, Error};
+pub use hedging::{HedgeDelay, HedgedClient, HedgedClientBuilder};
 
 use std::fmt;
 use std::future::Future;
@@ -115,6 +120,11 @@ pub trait ClientT {
 	) -> impl Future<Output = Result<BatchResponse<'a, R>, Error>> + Send
 	where
 		R: DeserializeOwned + fmt::Debug + 'a;
+
+	/// Create a [typed batch request](TypedBatch) where the response of each call is decoded to its own type.
+	fn typed_batch(&self) -> TypedBatch<'_, Self, ()> {
+		TypedBatch::new(self)
+	}
 }
 
 /// [JSON-RPC](https://www.jsonrpc.org/specification) client interface that can make requests, notifications and subscriptions.
@@ -77,6 +77,7 @@ mod proc_macros_support;
 #[doc(hidden)]
 pub mod __reexports {
 	pub use async_trait::async_trait;
+	pub use jsonrpsee_types;
 	pub use serde;
 	pub use serde_json;
 
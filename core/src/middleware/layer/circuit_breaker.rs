@@ -0,0 +1,642 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Client-side circuit breaker layer.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::client::{Error, MiddlewareBatchResponse, MiddlewareMethodResponse, MiddlewareNotifResponse};
+use crate::middleware::{Batch, Notification, RpcServiceT};
+
+use futures_util::Future;
+use futures_util::future::{Either, select};
+use jsonrpsee_types::Request;
+
+const LOG_TARGET: &str = "jsonrpsee-client";
+
+/// The number of buckets the rolling window is split into.
+const WINDOW_BUCKETS: u32 = 10;
+
+type StateChangeFn = dyn Fn(&CircuitStateChange) + Send + Sync;
+
+/// State of a circuit.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CircuitState {
+	/// Calls are let through and their outcome is tracked.
+	Closed,
+	/// Calls fail fast with [`Error::CircuitOpen`].
+	Open,
+	/// A limited number of calls are let through to probe whether the backend has recovered.
+	HalfOpen,
+}
+
+/// A state change of a circuit.
+#[derive(Debug, Clone)]
+pub struct CircuitStateChange {
+	method: Option<String>,
+	from: CircuitState,
+	to: CircuitState,
+}
+
+impl CircuitStateChange {
+	/// The method of the circuit or `None` if it's the circuit of the whole endpoint.
+	pub fn method(&self) -> Option<&str> {
+		self.method.as_deref()
+	}
+
+	/// The previous state.
+	pub fn from(&self) -> CircuitState {
+		self.from
+	}
+
+	/// The new state.
+	pub fn to(&self) -> CircuitState {
+		self.to
+	}
+}
+
+/// Layer that stops calling an endpoint while it's failing.
+///
+/// Each service created by the layer, i.e. each client, tracks the outcome of its calls in a
+/// rolling window. Transport errors and timeouts count as failures, any response from
+/// the server (including JSON-RPC errors) counts as a success.
+///
+/// Once the failure rate exceeds the threshold the circuit opens and all calls fail fast
+/// with [`Error::CircuitOpen`]. After the open duration has elapsed the circuit becomes half-open
+/// and lets a limited number of probe calls through. If they succeed the circuit is closed again,
+/// otherwise it's re-opened.
+///
+/// By default there is one circuit per endpoint. With [`CircuitBreakerLayer::per_method`]
+/// every method gets a circuit of its own in addition.
+#[derive(Clone)]
+pub struct CircuitBreakerLayer {
+	config: Arc<Config>,
+}
+
+impl std::fmt::Debug for CircuitBreakerLayer {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("CircuitBreakerLayer")
+			.field("failure_rate", &self.config.failure_rate)
+			.field("minimum_calls", &self.config.minimum_calls)
+			.field("window", &self.config.window)
+			.field("open_duration", &self.config.open_duration)
+			.field("half_open_calls", &self.config.half_open_calls)
+			.field("per_method", &self.config.per_method)
+			.field("call_timeout", &self.config.call_timeout)
+			.finish()
+	}
+}
+
+impl Default for CircuitBreakerLayer {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl CircuitBreakerLayer {
+	/// Create a new circuit breaker layer.
+	///
+	/// Defaults to open the circuit if more than 50% of at least 20 calls in the last 30s failed,
+	/// keep it open for 30s and let one probe call through when it's half-open.
+	pub fn new() -> Self {
+		Self {
+			config: Arc::new(Config {
+				failure_rate: 0.5,
+				minimum_calls: 20,
+				window: Duration::from_secs(30),
+				open_duration: Duration::from_secs(30),
+				half_open_calls: 1,
+				per_method: false,
+				call_timeout: None,
+				on_state_change: None,
+			}),
+		}
+	}
+
+	/// Set the failure rate which must be exceeded for the circuit to open.
+	///
+	/// The rate is clamped to `(0.0, 1.0]`, a failure rate of `1.0` never opens the circuit.
+	pub fn failure_rate(mut self, rate: f32) -> Self {
+		let rate = if rate.is_nan() { f32::MIN_POSITIVE } else { rate.clamp(f32::MIN_POSITIVE, 1.0) };
+		self.config_mut().failure_rate = rate;
+		self
+	}
+
+	/// Set the minimum number of calls in the window before the failure rate is considered.
+	pub fn minimum_calls(mut self, calls: u32) -> Self {
+		self.config_mut().minimum_calls = calls;
+		self
+	}
+
+	/// Set the duration of the rolling window in which the outcome of calls is tracked.
+	pub fn window(mut self, window: Duration) -> Self {
+		self.config_mut().window = window;
+		self
+	}
+
+	/// Set for how long the circuit stays open before it becomes half-open.
+	pub fn open_duration(mut self, duration: Duration) -> Self {
+		self.config_mut().open_duration = duration;
+		self
+	}
+
+	/// Set the number of probe calls that must succeed in the half-open state to close the circuit.
+	pub fn half_open_calls(mut self, calls: u32) -> Self {
+		self.config_mut().half_open_calls = calls.max(1);
+		self
+	}
+
+	/// Track a circuit per method in addition to the circuit of the endpoint.
+	pub fn per_method(mut self, enabled: bool) -> Self {
+		self.config_mut().per_method = enabled;
+		self
+	}
+
+	/// Fail calls with [`Error::RequestTimeout`] if they take longer than `timeout`.
+	///
+	/// This is useful for clients which enforce the request timeout outside of
+	/// the RPC middleware such as the HTTP client, as otherwise slow calls are
+	/// cancelled before the circuit breaker can observe them.
+	pub fn call_timeout(mut self, timeout: Duration) -> Self {
+		self.config_mut().call_timeout = Some(timeout);
+		self
+	}
+
+	/// Register a callback which is invoked on every state change of a circuit.
+	pub fn on_state_change(mut self, f: impl Fn(&CircuitStateChange) + Send + Sync + 'static) -> Self {
+		self.config_mut().on_state_change = Some(Box::new(f));
+		self
+	}
+
+	fn config_mut(&mut self) -> &mut Config {
+		Arc::get_mut(&mut self.config).expect("CircuitBreakerLayer is not shared while it's configured; qed")
+	}
+}
+
+impl<S> tower::Layer<S> for CircuitBreakerLayer {
+	type Service = CircuitBreaker<S>;
+
+	fn layer(&self, service: S) -> Self::Service {
+		CircuitBreaker {
+			service,
+			circuits: Arc::new(Circuits {
+				config: self.config.clone(),
+				endpoint: Mutex::new(Circuit::default()),
+				methods: Mutex::new(HashMap::new()),
+			}),
+		}
+	}
+}
+
+/// A middleware that fails fast while the endpoint or method is failing.
+#[derive(Clone)]
+pub struct CircuitBreaker<S> {
+	service: S,
+	circuits: Arc<Circuits>,
+}
+
+impl<S: std::fmt::Debug> std::fmt::Debug for CircuitBreaker<S> {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("CircuitBreaker").field("service", &self.service).finish()
+	}
+}
+
+impl<S> CircuitBreaker<S> {
+	/// Get the state of the circuit of the endpoint.
+	pub fn state(&self) -> CircuitState {
+		self.circuits.endpoint.lock().expect("Mutex not poisoned; qed").state
+	}
+}
+
+impl<S> RpcServiceT for CircuitBreaker<S>
+where
+	S: RpcServiceT<
+			MethodResponse = Result<MiddlewareMethodResponse, Error>,
+			BatchResponse = Result<MiddlewareBatchResponse, Error>,
+			NotificationResponse = Result<MiddlewareNotifResponse, Error>,
+		> + Send
+		+ Sync
+		+ Clone
+		+ 'static,
+{
+	type MethodResponse = S::MethodResponse;
+	type NotificationResponse = S::NotificationResponse;
+	type BatchResponse = S::BatchResponse;
+
+	fn call<'a>(&self, request: Request<'a>) -> impl Future<Output = Self::MethodResponse> + Send + 'a {
+		let service = self.service.clone();
+		let circuits = self.circuits.clone();
+
+		async move {
+			let endpoint = circuits.acquire(None)?;
+			let method =
+				if circuits.config.per_method { Some(circuits.acquire(Some(request.method_name()))?) } else { None };
+
+			let rp = circuits.with_timeout(service.call(request)).await;
+			let failed = matches!(rp, Err(ref e) if is_failure(e));
+
+			endpoint.complete(failed);
+			if let Some(method) = method {
+				method.complete(failed);
+			}
+
+			rp
+		}
+	}
+
+	fn batch<'a>(&self, batch: Batch<'a>) -> impl Future<Output = Self::BatchResponse> + Send + 'a {
+		let service = self.service.clone();
+		let circuits = self.circuits.clone();
+
+		async move {
+			let permit = circuits.acquire(None)?;
+			let rp = circuits.with_timeout(service.batch(batch)).await;
+			permit.complete(matches!(rp, Err(ref e) if is_failure(e)));
+			rp
+		}
+	}
+
+	fn notification<'a>(&self, n: Notification<'a>) -> impl Future<Output = Self::NotificationResponse> + Send + 'a {
+		let service = self.service.clone();
+		let circuits = self.circuits.clone();
+
+		async move {
+			let permit = circuits.acquire(None)?;
+			let rp = circuits.with_timeout(service.notification(n)).await;
+			permit.complete(matches!(rp, Err(ref e) if is_failure(e)));
+			rp
+		}
+	}
+}
+
+fn is_failure(err: &Error) -> bool {
+	matches!(err, Error::Transport(_) | Error::RequestTimeout)
+}
+
+struct Config {
+	failure_rate: f32,
+	minimum_calls: u32,
+	window: Duration,
+	open_duration: Duration,
+	half_open_calls: u32,
+	per_method: bool,
+	call_timeout: Option<Duration>,
+	on_state_change: Option<Box<StateChangeFn>>,
+}
+
+/// The circuits of one service.
+struct Circuits {
+	config: Arc<Config>,
+	endpoint: Mutex<Circuit>,
+	methods: Mutex<HashMap<String, Circuit>>,
+}
+
+impl Circuits {
+	fn with_circuit<R>(&self, method: Option<&str>, f: impl FnOnce(&mut Circuit) -> R) -> R {
+		match method {
+			None => f(&mut self.endpoint.lock().expect("Mutex not poisoned; qed")),
+			Some(method) => {
+				let mut methods = self.methods.lock().expect("Mutex not poisoned; qed");
+				f(methods.entry(method.to_owned()).or_default())
+			}
+		}
+	}
+
+	fn acquire(self: &Arc<Self>, method: Option<&str>) -> Result<Permit, Error> {
+		let (res, change) = self.with_circuit(method, |c| c.try_acquire(Instant::now(), &self.config));
+		self.notify(method, change);
+
+		match res {
+			Some(probe) => {
+				Ok(Permit { circuits: self.clone(), method: method.map(ToOwned::to_owned), probe, done: false })
+			}
+			None => Err(Error::CircuitOpen(method.map(ToOwned::to_owned))),
+		}
+	}
+
+	fn notify(&self, method: Option<&str>, change: Option<(CircuitState, CircuitState)>) {
+		let Some((from, to)) = change else {
+			return;
+		};
+
+		tracing::debug!(target: LOG_TARGET, "Circuit breaker of {} changed from {from:?} to {to:?}", method.unwrap_or("endpoint"));
+
+		if let Some(f) = &self.config.on_state_change {
+			f(&CircuitStateChange { method: method.map(ToOwned::to_owned), from, to });
+		}
+	}
+
+	async fn with_timeout<F, T>(&self, fut: F) -> Result<T, Error>
+	where
+		F: Future<Output = Result<T, Error>>,
+	{
+		let Some(timeout) = self.config.call_timeout else {
+			return fut.await;
+		};
+
+		match select(std::pin::pin!(fut), futures_timer::Delay::new(timeout)).await {
+			Either::Left((rp, _)) => rp,
+			Either::Right(_) => Err(Error::RequestTimeout),
+		}
+	}
+}
+
+/// Permission to make a call which must be completed with the outcome of the call.
+///
+/// If the call is cancelled, the permit is released without affecting the circuit.
+struct Permit {
+	circuits: Arc<Circuits>,
+	method: Option<String>,
+	probe: bool,
+	done: bool,
+}
+
+impl Permit {
+	fn complete(mut self, failed: bool) {
+		self.done = true;
+		let change = self.circuits.with_circuit(self.method.as_deref(), |c| {
+			c.record(Instant::now(), failed, self.probe, &self.circuits.config)
+		});
+		self.circuits.notify(self.method.as_deref(), change);
+	}
+}
+
+impl Drop for Permit {
+	fn drop(&mut self) {
+		if !self.done && self.probe {
+			self.circuits.with_circuit(self.method.as_deref(), |c| c.release_probe());
+		}
+	}
+}
+
+#[derive(Debug)]
+struct Circuit {
+	state: CircuitState,
+	window: RollingWindow,
+	opened_at: Option<Instant>,
+	/// Probe calls in flight in the half-open state.
+	probes: u32,
+	/// Successful probe calls in the half-open state.
+	probe_successes: u32,
+}
+
+impl Default for Circuit {
+	fn default() -> Self {
+		Self {
+			state: CircuitState::Closed,
+			window: RollingWindow::default(),
+			opened_at: None,
+			probes: 0,
+			probe_successes: 0,
+		}
+	}
+}
+
+impl Circuit {
+	/// Returns whether the call is let through and if so whether it's a probe call.
+	fn try_acquire(&mut self, now: Instant, config: &Config) -> (Option<bool>, Option<(CircuitState, CircuitState)>) {
+		match self.state {
+			CircuitState::Closed => (Some(false), None),
+			CircuitState::Open => {
+				let opened_at = self.opened_at.expect("Open circuit has an open timestamp; qed");
+				if now.saturating_duration_since(opened_at) < config.open_duration {
+					return (None, None);
+				}
+				let change = self.transition(CircuitState::HalfOpen);
+				self.probes = 1;
+				(Some(true), change)
+			}
+			CircuitState::HalfOpen => {
+				if self.probes + self.probe_successes >= config.half_open_calls {
+					return (None, None);
+				}
+				self.probes += 1;
+				(Some(true), None)
+			}
+		}
+	}
+
+	fn record(
+		&mut self,
+		now: Instant,
+		failed: bool,
+		probe: bool,
+		config: &Config,
+	) -> Option<(CircuitState, CircuitState)> {
+		match self.state {
+			CircuitState::Closed => {
+				self.window.record(now, failed, config.window);
+				let (calls, failures) = self.window.counts();
+
+				if calls >= config.minimum_calls && failures as f32 > calls as f32 * config.failure_rate {
+					self.opened_at = Some(now);
+					return self.transition(CircuitState::Open);
+				}
+
+				None
+			}
+			CircuitState::HalfOpen if probe => {
+				self.probes = self.probes.saturating_sub(1);
+
+				if failed {
+					self.opened_at = Some(now);
+					return self.transition(CircuitState::Open);
+				}
+
+				self.probe_successes += 1;
+				if self.probe_successes >= config.half_open_calls {
+					self.window = RollingWindow::default();
+					return self.transition(CircuitState::Closed);
+				}
+
+				None
+			}
+			// Outcome of calls that were started before the state changed.
+			CircuitState::HalfOpen | CircuitState::Open => None,
+		}
+	}
+
+	fn release_probe(&mut self) {
+		if self.state == CircuitState::HalfOpen {
+			self.probes = self.probes.saturating_sub(1);
+		}
+	}
+
+	fn transition(&mut self, to: CircuitState) -> Option<(CircuitState, CircuitState)> {
+		let from = std::mem::replace(&mut self.state, to);
+		self.probes = 0;
+		self.probe_successes = 0;
+		(from != to).then_some((from, to))
+	}
+}
+
+/// Outcome of calls in a rolling time window split into buckets.
+#[derive(Debug, Default)]
+struct RollingWindow {
+	buckets: VecDeque<Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+	start: Instant,
+	calls: u32,
+	failures: u32,
+}
+
+impl RollingWindow {
+	fn record(&mut self, now: Instant, failed: bool, window: Duration) {
+		while self.buckets.front().is_some_and(|b| now.saturating_duration_since(b.start) >= window) {
+			self.buckets.pop_front();
+		}
+
+		let bucket_len = window / WINDOW_BUCKETS;
+		let bucket = match self.buckets.back_mut() {
+			Some(b) if now.saturating_duration_since(b.start) < bucket_len => b,
+			_ => {
+				self.buckets.push_back(Bucket { start: now, calls: 0, failures: 0 });
+				self.buckets.back_mut().expect("Bucket inserted above; qed")
+			}
+		};
+
+		bucket.calls += 1;
+		if failed {
+			bucket.failures += 1;
+		}
+	}
+
+	fn counts(&self) -> (u32, u32) {
+		self.buckets.iter().fold((0, 0), |(calls, failures), b| (calls + b.calls, failures + b.failures))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn config() -> Config {
+		Config {
+			failure_rate: 0.5,
+			minimum_calls: 4,
+			window: Duration::from_secs(10),
+			open_duration: Duration::from_secs(5),
+			half_open_calls: 2,
+			per_method: false,
+			call_timeout: None,
+			on_state_change: None,
+		}
+	}
+
+	#[test]
+	fn opens_when_failure_rate_is_exceeded() {
+		let config = config();
+		let mut circuit = Circuit::default();
+		let now = Instant::now();
+
+		assert_eq!(circuit.record(now, true, false, &config), None);
+		assert_eq!(circuit.record(now, false, false, &config), None);
+		assert_eq!(circuit.record(now, true, false, &config), None);
+		assert_eq!(circuit.record(now, true, false, &config), Some((CircuitState::Closed, CircuitState::Open)));
+		assert_eq!(circuit.try_acquire(now + Duration::from_secs(1), &config), (None, None));
+	}
+
+	#[test]
+	fn stays_closed_at_failure_rate() {
+		let config = config();
+		let mut circuit = Circuit::default();
+		let now = Instant::now();
+
+		for failed in [true, false, true, false] {
+			assert_eq!(circuit.record(now, failed, false, &config), None);
+		}
+		assert_eq!(circuit.state, CircuitState::Closed);
+	}
+
+	#[test]
+	fn failure_rate_is_clamped() {
+		assert_eq!(CircuitBreakerLayer::new().failure_rate(1.5).config.failure_rate, 1.0);
+		assert_eq!(CircuitBreakerLayer::new().failure_rate(0.0).config.failure_rate, f32::MIN_POSITIVE);
+		assert_eq!(CircuitBreakerLayer::new().failure_rate(f32::NAN).config.failure_rate, f32::MIN_POSITIVE);
+		assert_eq!(CircuitBreakerLayer::new().failure_rate(0.25).config.failure_rate, 0.25);
+	}
+
+	#[test]
+	fn old_calls_are_evicted_from_window() {
+		let config = config();
+		let mut circuit = Circuit::default();
+		let now = Instant::now();
+
+		for _ in 0..3 {
+			circuit.record(now, true, false, &config);
+		}
+
+		let later = now + Duration::from_secs(11);
+		assert_eq!(circuit.record(later, false, false, &config), None);
+		assert_eq!(circuit.window.counts(), (1, 0));
+	}
+
+	#[test]
+	fn half_open_closes_after_successful_probes() {
+		let config = config();
+		let mut circuit = Circuit::default();
+		let now = Instant::now();
+
+		for _ in 0..4 {
+			circuit.record(now, true, false, &config);
+		}
+		assert_eq!(circuit.state, CircuitState::Open);
+
+		let later = now + Duration::from_secs(5);
+		assert_eq!(
+			circuit.try_acquire(later, &config),
+			(Some(true), Some((CircuitState::Open, CircuitState::HalfOpen)))
+		);
+		assert_eq!(circuit.try_acquire(later, &config), (Some(true), None));
+		// Only two probes are allowed.
+		assert_eq!(circuit.try_acquire(later, &config), (None, None));
+
+		assert_eq!(circuit.record(later, false, true, &config), None);
+		assert_eq!(circuit.record(later, false, true, &config), Some((CircuitState::HalfOpen, CircuitState::Closed)));
+		assert_eq!(circuit.window.counts(), (0, 0));
+	}
+
+	#[test]
+	fn half_open_reopens_after_failed_probe() {
+		let config = config();
+		let mut circuit = Circuit::default();
+		let now = Instant::now();
+
+		for _ in 0..4 {
+			circuit.record(now, true, false, &config);
+		}
+
+		let later = now + Duration::from_secs(5);
+		assert_eq!(circuit.try_acquire(later, &config).0, Some(true));
+		assert_eq!(circuit.record(later, true, true, &config), Some((CircuitState::HalfOpen, CircuitState::Open)));
+		assert_eq!(circuit.try_acquire(later, &config), (None, None));
+	}
+}
@@ -0,0 +1,223 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Single-flight layer that coalesces identical concurrent method calls.
+
+use std::collections::hash_map::Entry;
+use std::sync::Arc;
+
+use crate::TEN_MB_SIZE_BYTES;
+use crate::middleware::{Batch, Notification, RpcServiceT};
+use crate::server::{MethodResponse, ResponsePayload};
+
+use futures_util::Future;
+use jsonrpsee_types::{ErrorObjectOwned, Id, Request, Response};
+use parking_lot::Mutex;
+use rustc_hash::{FxHashMap, FxHashSet};
+use serde_json::value::RawValue;
+use tokio::sync::watch;
+
+/// The method name and the raw params of a call.
+type CallKey = (String, Option<String>);
+type InFlight = Arc<Mutex<FxHashMap<CallKey, watch::Receiver<Option<Coalesced>>>>>;
+
+/// Layer that joins identical concurrent method calls onto one handler execution.
+///
+/// Two calls are regarded as identical if they have the same method name and
+/// byte-identical params. While a call is in flight, every other identical call
+/// waits for it to complete and gets the same result under its own request `Id`.
+///
+/// Coalescing is opt-in and only applies to the methods provided to [`CoalesceLayer::new`].
+/// It should only be enabled for methods without side-effects where it doesn't matter
+/// which caller actually executed the method.
+///
+/// The state is shared by all services created from the same layer such that
+/// calls are coalesced across connections. Batch requests and notifications are not coalesced.
+///
+/// The responses of the coalesced calls are limited to [`CoalesceLayer::max_response_body_size`]
+/// which should be the same as the limit of the server.
+#[derive(Debug, Clone)]
+pub struct CoalesceLayer {
+	methods: Arc<FxHashSet<String>>,
+	in_flight: InFlight,
+	max_response_body_size: usize,
+}
+
+impl CoalesceLayer {
+	/// Create a new coalescing layer for the given methods.
+	pub fn new<I, M>(methods: I) -> Self
+	where
+		I: IntoIterator<Item = M>,
+		M: Into<String>,
+	{
+		Self {
+			methods: Arc::new(methods.into_iter().map(Into::into).collect()),
+			in_flight: Default::default(),
+			max_response_body_size: TEN_MB_SIZE_BYTES as usize,
+		}
+	}
+
+	/// Set the maximum size of the responses of the coalesced calls (default is 10 MiB).
+	///
+	/// The response of a coalesced call is rebuilt with its own request `Id` and
+	/// is replaced by an error if it exceeds this limit.
+	pub fn max_response_body_size(mut self, max: u32) -> Self {
+		self.max_response_body_size = max as usize;
+		self
+	}
+}
+
+impl<S> tower::Layer<S> for CoalesceLayer {
+	type Service = Coalesce<S>;
+
+	fn layer(&self, service: S) -> Self::Service {
+		Coalesce {
+			service,
+			methods: self.methods.clone(),
+			in_flight: self.in_flight.clone(),
+			max_response_body_size: self.max_response_body_size,
+		}
+	}
+}
+
+/// A middleware that coalesces identical concurrent method calls.
+#[derive(Debug, Clone)]
+pub struct Coalesce<S> {
+	service: S,
+	methods: Arc<FxHashSet<String>>,
+	in_flight: InFlight,
+	max_response_body_size: usize,
+}
+
+impl<S> RpcServiceT for Coalesce<S>
+where
+	S: RpcServiceT<MethodResponse = MethodResponse> + Send + Sync + Clone + 'static,
+{
+	type MethodResponse = S::MethodResponse;
+	type NotificationResponse = S::NotificationResponse;
+	type BatchResponse = S::BatchResponse;
+
+	fn call<'a>(&self, request: Request<'a>) -> impl Future<Output = Self::MethodResponse> + Send + 'a {
+		let service = self.service.clone();
+		let in_flight = self.in_flight.clone();
+		let coalesce = self.methods.contains(request.method_name());
+		let max_response_body_size = self.max_response_body_size;
+
+		async move {
+			if !coalesce {
+				return service.call(request).await;
+			}
+
+			let key = (request.method_name().to_owned(), request.params.as_ref().map(|p| p.get().to_owned()));
+
+			let tx = match in_flight.lock().entry(key.clone()) {
+				Entry::Occupied(entry) => Err(entry.get().clone()),
+				Entry::Vacant(entry) => {
+					let (tx, rx) = watch::channel(None);
+					entry.insert(rx);
+					Ok(tx)
+				}
+			};
+
+			match tx {
+				Ok(tx) => {
+					let _guard = InFlightGuard { in_flight, key };
+					let rp = service.call(request).await;
+
+					if let Some(outcome) = Coalesced::from_response(&rp) {
+						let _ = tx.send(Some(outcome));
+					}
+
+					rp
+				}
+				Err(mut rx) => {
+					let outcome = rx.wait_for(Option::is_some).await.ok().and_then(|outcome| outcome.clone());
+
+					match outcome {
+						Some(outcome) => outcome.into_response(request.id, request.extensions, max_response_body_size),
+						// The call that was in flight was cancelled or its response couldn't
+						// be shared, execute the call instead.
+						None => service.call(request).await,
+					}
+				}
+			}
+		}
+	}
+
+	fn batch<'a>(&self, batch: Batch<'a>) -> impl Future<Output = Self::BatchResponse> + Send + 'a {
+		self.service.batch(batch)
+	}
+
+	fn notification<'a>(&self, n: Notification<'a>) -> impl Future<Output = Self::NotificationResponse> + Send + 'a {
+		self.service.notification(n)
+	}
+}
+
+/// Outcome of a method call that is shared with the coalesced calls.
+#[derive(Debug, Clone)]
+enum Coalesced {
+	Success(Box<RawValue>),
+	Error(ErrorObjectOwned),
+}
+
+impl Coalesced {
+	fn from_response(rp: &MethodResponse) -> Option<Self> {
+		if !rp.is_method_call() {
+			return None;
+		}
+
+		let rp = serde_json::from_str::<Response<&RawValue>>(rp.as_json().get()).ok()?;
+
+		match rp.payload {
+			jsonrpsee_types::ResponsePayload::Success(result) => Some(Self::Success(result.into_owned().to_owned())),
+			jsonrpsee_types::ResponsePayload::Error(err) => Some(Self::Error(err.into_owned())),
+		}
+	}
+
+	fn into_response(self, id: Id, extensions: http::Extensions, max_response_body_size: usize) -> MethodResponse {
+		let rp = match self {
+			Self::Success(result) => {
+				MethodResponse::response(id, ResponsePayload::success(result), max_response_body_size)
+			}
+			Self::Error(err) => MethodResponse::error(id, err),
+		};
+
+		rp.with_extensions(extensions)
+	}
+}
+
+/// Removes the call from the in-flight calls when the call
+/// completes or is cancelled.
+struct InFlightGuard {
+	in_flight: InFlight,
+	key: CallKey,
+}
+
+impl Drop for InFlightGuard {
+	fn drop(&mut self) {
+		self.in_flight.lock().remove(&self.key);
+	}
+}
@@ -31,3 +31,16 @@ mod logger;
 
 pub use either::*;
 pub use logger::*;
+
+cfg_server! {
+	mod coalesce;
+	pub use coalesce::*;
+}
+
+cfg_client! {
+	mod circuit_breaker;
+	mod retry;
+
+	pub use circuit_breaker::*;
+	pub use retry::*;
+}
@@ -0,0 +1,430 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Client-side layer that retries failed idempotent calls.
+
+use std::collections::HashSet;
+use std::hash::{BuildHasher, RandomState};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use crate::client::{Error, MiddlewareBatchResponse, MiddlewareMethodResponse, MiddlewareNotifResponse};
+use crate::middleware::{Batch, BatchEntry, IsSubscription, Notification, RpcServiceT};
+
+use futures_util::Future;
+use futures_util::future::join_all;
+use jsonrpsee_types::error::SERVER_IS_BUSY_CODE;
+use jsonrpsee_types::{Request, Response, ResponsePayload};
+
+const LOG_TARGET: &str = "jsonrpsee-client";
+
+type IdempotentFn = dyn Fn(&str) -> bool + Send + Sync;
+
+/// Layer that retries failed calls to idempotent methods with exponential backoff.
+///
+/// A call is retried when the transport failed, the request timed out or the server
+/// responded with one of the [retryable error codes](RetryLayer::retry_on_codes).
+///
+/// Only methods that are known to be idempotent are retried, which is none by default.
+/// Methods can be marked as idempotent by name via [`RetryLayer::idempotent_methods`],
+/// for instance with the constant generated by the `#[rpc(client)]` macro for methods
+/// annotated with `#[method(idempotent)]`, or by a predicate via [`RetryLayer::idempotent_if`].
+///
+/// For batch requests, the whole batch is retried if the transport failed and
+/// all calls in the batch are idempotent. Otherwise, the individual calls of
+/// the batch that failed with a retryable error code are retried on their own.
+///
+/// Subscriptions and notifications are never retried.
+///
+/// To avoid retry storms when the server is overloaded, the number of retries in flight is
+/// limited by a [retry budget](RetryLayer::retry_budget) which is shared by all services
+/// created from the same layer.
+#[derive(Clone)]
+pub struct RetryLayer {
+	policy: Arc<RetryPolicy>,
+	budget: Arc<RetryBudget>,
+}
+
+impl std::fmt::Debug for RetryLayer {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("RetryLayer")
+			.field("max_retries", &self.policy.max_retries)
+			.field("initial_backoff", &self.policy.initial_backoff)
+			.field("max_backoff", &self.policy.max_backoff)
+			.field("idempotent_methods", &self.policy.idempotent_methods)
+			.field("retry_on_codes", &self.policy.retry_on_codes)
+			.field("budget", &self.budget)
+			.finish()
+	}
+}
+
+impl Default for RetryLayer {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl RetryLayer {
+	/// Create a new retry layer.
+	///
+	/// Defaults to at most 3 retries, backoff from 100ms up to 5s and a retry budget
+	/// of 20% of the calls in flight with at least 3 concurrent retries.
+	pub fn new() -> Self {
+		Self {
+			policy: Arc::new(RetryPolicy {
+				max_retries: 3,
+				initial_backoff: Duration::from_millis(100),
+				max_backoff: Duration::from_secs(5),
+				idempotent_methods: HashSet::new(),
+				idempotent_if: None,
+				retry_on_codes: vec![SERVER_IS_BUSY_CODE],
+			}),
+			budget: Arc::new(RetryBudget::new(0.2, 3)),
+		}
+	}
+
+	/// Set the maximum number of retries per call.
+	pub fn max_retries(mut self, max: u32) -> Self {
+		self.policy_mut().max_retries = max;
+		self
+	}
+
+	/// Set the backoff between retries.
+	///
+	/// The backoff starts at `initial` and is doubled for every retry up to `max`.
+	/// The actual delay is picked at random between zero and the backoff (full jitter).
+	pub fn backoff(mut self, initial: Duration, max: Duration) -> Self {
+		let policy = self.policy_mut();
+		policy.initial_backoff = initial;
+		policy.max_backoff = max;
+		self
+	}
+
+	/// Mark the given methods as idempotent.
+	pub fn idempotent_methods<I, M>(mut self, methods: I) -> Self
+	where
+		I: IntoIterator<Item = M>,
+		M: Into<String>,
+	{
+		self.policy_mut().idempotent_methods.extend(methods.into_iter().map(Into::into));
+		self
+	}
+
+	/// Mark the methods for which the predicate returns `true` as idempotent.
+	///
+	/// This is used in addition to the methods provided via [`RetryLayer::idempotent_methods`].
+	pub fn idempotent_if(mut self, f: impl Fn(&str) -> bool + Send + Sync + 'static) -> Self {
+		self.policy_mut().idempotent_if = Some(Box::new(f));
+		self
+	}
+
+	/// Set the JSON-RPC error codes which are retried.
+	///
+	/// Default: [`SERVER_IS_BUSY_CODE`].
+	pub fn retry_on_codes(mut self, codes: impl IntoIterator<Item = i32>) -> Self {
+		self.policy_mut().retry_on_codes = codes.into_iter().collect();
+		self
+	}
+
+	/// Set the retry budget.
+	///
+	/// A retry is only made if the number of retries in flight is less than
+	/// `ratio` times the number of calls in flight or less than `min_retries`.
+	pub fn retry_budget(mut self, ratio: f32, min_retries: usize) -> Self {
+		self.budget = Arc::new(RetryBudget::new(ratio, min_retries));
+		self
+	}
+
+	fn policy_mut(&mut self) -> &mut RetryPolicy {
+		Arc::get_mut(&mut self.policy).expect("RetryLayer is not shared while it's configured; qed")
+	}
+}
+
+impl<S> tower::Layer<S> for RetryLayer {
+	type Service = Retry<S>;
+
+	fn layer(&self, service: S) -> Self::Service {
+		Retry { service, policy: self.policy.clone(), budget: self.budget.clone() }
+	}
+}
+
+/// A middleware that retries failed calls to idempotent methods.
+#[derive(Clone)]
+pub struct Retry<S> {
+	service: S,
+	policy: Arc<RetryPolicy>,
+	budget: Arc<RetryBudget>,
+}
+
+impl<S: std::fmt::Debug> std::fmt::Debug for Retry<S> {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("Retry").field("service", &self.service).field("budget", &self.budget).finish()
+	}
+}
+
+impl<S> RpcServiceT for Retry<S>
+where
+	S: RpcServiceT<
+			MethodResponse = Result<MiddlewareMethodResponse, Error>,
+			BatchResponse = Result<MiddlewareBatchResponse, Error>,
+			NotificationResponse = Result<MiddlewareNotifResponse, Error>,
+		> + Send
+		+ Sync
+		+ Clone
+		+ 'static,
+{
+	type MethodResponse = S::MethodResponse;
+	type NotificationResponse = S::NotificationResponse;
+	type BatchResponse = S::BatchResponse;
+
+	fn call<'a>(&self, request: Request<'a>) -> impl Future<Output = Self::MethodResponse> + Send + 'a {
+		let service = self.service.clone();
+		let policy = self.policy.clone();
+		let budget = self.budget.clone();
+
+		async move {
+			if request.extensions.get::<IsSubscription>().is_some() || !policy.is_idempotent(request.method_name()) {
+				return service.call(request).await;
+			}
+
+			let _call = budget.track_call();
+			call_with_retries(&service, &policy, &budget, request, None).await
+		}
+	}
+
+	fn batch<'a>(&self, mut batch: Batch<'a>) -> impl Future<Output = Self::BatchResponse> + Send + 'a {
+		let service = self.service.clone();
+		let policy = self.policy.clone();
+		let budget = self.budget.clone();
+
+		async move {
+			let mut calls = Vec::new();
+			let mut all_idempotent = true;
+
+			for entry in batch.iter() {
+				match entry {
+					Ok(BatchEntry::Call(req)) if policy.is_idempotent(req.method_name()) => calls.push(req.clone()),
+					Ok(BatchEntry::Call(_)) | Ok(BatchEntry::Notification(_)) | Err(_) => all_idempotent = false,
+				}
+			}
+
+			if calls.is_empty() {
+				return service.batch(batch).await;
+			}
+
+			let _call = budget.track_call();
+			let extensions = batch.extensions().clone();
+			let mut attempt = 0;
+			let mut _retry = None;
+
+			let rps = loop {
+				let retry = all_idempotent.then(|| {
+					let mut b = Batch::from(calls.iter().cloned().map(|req| Ok(BatchEntry::Call(req))).collect());
+					*b.extensions_mut() = extensions.clone();
+					b
+				});
+
+				match service.batch(batch).await {
+					Err(e) if policy.is_retryable_error(&e) && attempt < policy.max_retries => {
+						let (Some(retry), Some(guard)) = (retry, budget.try_retry()) else {
+							return Err(e);
+						};
+						tracing::debug!(target: LOG_TARGET, "Retrying batch request after error: {e}");
+						attempt += 1;
+						_retry = Some(guard);
+						futures_timer::Delay::new(policy.backoff(attempt)).await;
+						batch = retry;
+					}
+					rps => break rps?,
+				}
+			};
+
+			// Retry the individual calls which failed with a retryable error code.
+			let rps = join_all(rps.into_iter().map(|rp| {
+				let retry = rp
+					.as_error()
+					.filter(|err| policy.retry_on_codes.contains(&err.code()))
+					.and_then(|_| calls.iter().find(|req| req.id == *rp.id()).cloned());
+				let (service, policy, budget) = (&service, &*policy, &*budget);
+
+				async move {
+					let Some(req) = retry else {
+						return rp;
+					};
+
+					let id = rp.id().clone();
+					let err = rp.as_error().expect("Only error responses are retried; qed").clone().into_owned();
+
+					match call_with_retries(
+						service,
+						policy,
+						budget,
+						req,
+						Some(Ok(MiddlewareMethodResponse::response(rp))),
+					)
+					.await
+					{
+						Ok(rp) => rp.into_response(),
+						Err(e) => {
+							tracing::debug!(target: LOG_TARGET, "Retrying batch entry `{id}` failed: {e}");
+							Response::new(ResponsePayload::error(err), id).into()
+						}
+					}
+				}
+			}))
+			.await;
+
+			Ok(rps)
+		}
+	}
+
+	fn notification<'a>(&self, n: Notification<'a>) -> impl Future<Output = Self::NotificationResponse> + Send + 'a {
+		self.service.notification(n)
+	}
+}
+
+/// Make the call and retry it according to the policy.
+///
+/// If `rp` is provided it's regarded as the response of the first attempt.
+async fn call_with_retries<S>(
+	service: &S,
+	policy: &RetryPolicy,
+	budget: &RetryBudget,
+	request: Request<'_>,
+	rp: Option<Result<MiddlewareMethodResponse, Error>>,
+) -> Result<MiddlewareMethodResponse, Error>
+where
+	S: RpcServiceT<MethodResponse = Result<MiddlewareMethodResponse, Error>>,
+{
+	let mut rp = match rp {
+		Some(rp) => rp,
+		None => service.call(request.clone()).await,
+	};
+	let mut attempt = 0;
+	let mut _retry = None;
+
+	loop {
+		if attempt >= policy.max_retries || !policy.is_retryable(&rp) {
+			return rp;
+		}
+
+		let Some(guard) = budget.try_retry() else {
+			tracing::debug!(target: LOG_TARGET, "Retry budget exhausted, not retrying `{}`", request.method_name());
+			return rp;
+		};
+
+		attempt += 1;
+		_retry = Some(guard);
+		tracing::debug!(target: LOG_TARGET, "Retrying `{}`, attempt {attempt}", request.method_name());
+		futures_timer::Delay::new(policy.backoff(attempt)).await;
+		rp = service.call(request.clone()).await;
+	}
+}
+
+struct RetryPolicy {
+	max_retries: u32,
+	initial_backoff: Duration,
+	max_backoff: Duration,
+	idempotent_methods: HashSet<String>,
+	idempotent_if: Option<Box<IdempotentFn>>,
+	retry_on_codes: Vec<i32>,
+}
+
+impl RetryPolicy {
+	fn is_idempotent(&self, method: &str) -> bool {
+		self.idempotent_methods.contains(method) || self.idempotent_if.as_ref().is_some_and(|f| f(method))
+	}
+
+	fn is_retryable_error(&self, err: &Error) -> bool {
+		matches!(err, Error::Transport(_) | Error::RequestTimeout)
+	}
+
+	fn is_retryable(&self, rp: &Result<MiddlewareMethodResponse, Error>) -> bool {
+		match rp {
+			Ok(rp) => rp.as_error().is_some_and(|err| self.retry_on_codes.contains(&err.code())),
+			Err(err) => self.is_retryable_error(err),
+		}
+	}
+
+	/// Backoff before the retry `attempt` with full jitter.
+	fn backoff(&self, attempt: u32) -> Duration {
+		let backoff =
+			self.initial_backoff.saturating_mul(2_u32.saturating_pow(attempt.saturating_sub(1))).min(self.max_backoff);
+		let nanos = u64::try_from(backoff.as_nanos()).unwrap_or(u64::MAX);
+
+		if nanos == 0 {
+			return backoff;
+		}
+
+		// The jitter only has to spread out the retries of different calls, it doesn't need to be
+		// unpredictable. Every `RandomState` is created with different keys (seeded randomly once
+		// per thread and then incremented) thus hashing with it gives a new pseudo-random value
+		// on every call. It's used instead of `rand` because the client is built for
+		// `wasm32-unknown-unknown` where `rand` requires extra configuration of `getrandom`.
+		let jitter = RandomState::new().hash_one(attempt) % nanos.saturating_add(1);
+		Duration::from_nanos(jitter)
+	}
+}
+
+/// Limits the number of concurrent retries relative to the number of calls in flight.
+#[derive(Debug)]
+struct RetryBudget {
+	ratio: f32,
+	min_retries: usize,
+	calls: AtomicUsize,
+	retries: AtomicUsize,
+}
+
+impl RetryBudget {
+	fn new(ratio: f32, min_retries: usize) -> Self {
+		Self { ratio, min_retries, calls: AtomicUsize::new(0), retries: AtomicUsize::new(0) }
+	}
+
+	fn track_call(&self) -> BudgetGuard<'_> {
+		self.calls.fetch_add(1, Ordering::Relaxed);
+		BudgetGuard(&self.calls)
+	}
+
+	fn try_retry(&self) -> Option<BudgetGuard<'_>> {
+		let calls = self.calls.load(Ordering::Relaxed);
+		let max = ((calls as f32 * self.ratio) as usize).max(self.min_retries);
+
+		self.retries
+			.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| (n < max).then_some(n + 1))
+			.ok()
+			.map(|_| BudgetGuard(&self.retries))
+	}
+}
+
+/// Decrements the counter when dropped.
+struct BudgetGuard<'a>(&'a AtomicUsize);
+
+impl Drop for BudgetGuard<'_> {
+	fn drop(&mut self) {
+		self.0.fetch_sub(1, Ordering::Relaxed);
+	}
+}
@@ -19,6 +19,11 @@ pub fn panic_fail_serialize(param: &str, err: serde_json::Error) -> ! {
 	panic!("Parameter `{param}` cannot be serialized: {err}");
 }
 
+#[cold]
+pub fn panic_fail_serialize_error_data(field: &str, err: serde_json::Error) -> ! {
+	panic!("Error data `{field}` cannot be serialized: {err}");
+}
+
 #[cfg(debug_assertions)]
 #[cold]
 pub fn panic_fail_register() -> ! {
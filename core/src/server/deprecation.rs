@@ -0,0 +1,80 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Deprecation of a method, similar to `#[deprecated]`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Deprecation {
+	/// The version since the method is deprecated.
+	pub since: Option<String>,
+	/// The reason of the deprecation, such as the method which should be used instead.
+	pub note: Option<String>,
+}
+
+/// A deprecated method and the number of times it was called.
+///
+/// The counter is shared by all clones of the [`super::Methods`] which the method is registered in,
+/// such that it tracks the calls of all connections of a server.
+#[derive(Debug, Clone)]
+pub struct DeprecatedMethod {
+	deprecation: Deprecation,
+	calls: Arc<AtomicU64>,
+}
+
+impl DeprecatedMethod {
+	pub(crate) fn new(deprecation: Deprecation) -> Self {
+		Self { deprecation, calls: Arc::new(AtomicU64::new(0)) }
+	}
+
+	/// Returns the deprecation of the method.
+	pub fn deprecation(&self) -> &Deprecation {
+		&self.deprecation
+	}
+
+	/// Returns the number of times the method was called.
+	pub fn calls(&self) -> u64 {
+		self.calls.load(Ordering::Relaxed)
+	}
+
+	/// Record a call of the method.
+	pub fn record_call(&self) {
+		self.calls.fetch_add(1, Ordering::Relaxed);
+	}
+}
+
+/// Notice that a deprecated method was called.
+///
+/// The server inserts it into the extensions of the response of a call to a deprecated method
+/// if the deprecation notices are enabled, such that middleware can report it to the client or log it.
+#[derive(Debug, Clone)]
+pub struct DeprecationNotice {
+	/// The name of the deprecated method.
+	pub method: &'static str,
+	/// The deprecation of the method.
+	pub deprecation: Deprecation,
+}
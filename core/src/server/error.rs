@@ -39,6 +39,15 @@ pub enum TrySendError {
 	Full(SubscriptionMessage),
 }
 
+impl TrySendError {
+	/// Get back the message that couldn't be sent.
+	pub(crate) fn into_message(self) -> SubscriptionMessage {
+		match self {
+			Self::Closed(msg) | Self::Full(msg) => msg,
+		}
+	}
+}
+
 /// Error that may occur during [`crate::server::MethodSink::send`] or [`crate::server::SubscriptionSink::send`].
 #[derive(Debug, thiserror::Error)]
 #[error("The connection channel is closed")]
@@ -56,6 +65,18 @@ pub enum SendTimeoutError {
 	Closed(SubscriptionMessage),
 }
 
+/// Error that may occur when publishing or subscribing via [`crate::server::PubSubHub`]
+/// or a [`crate::server::PubSubBackend`].
+#[derive(Debug, thiserror::Error)]
+pub enum PubSubError {
+	/// The message couldn't be serialized or deserialized.
+	#[error("Invalid pub/sub message: {0}")]
+	Serde(#[from] serde_json::Error),
+	/// The backend failed.
+	#[error("Pub/sub backend error: {0}")]
+	Backend(String),
+}
+
 /// The error returned while accepting a subscription.
 #[derive(Debug, Copy, Clone, thiserror::Error)]
 #[error("The remote peer closed the connection")]
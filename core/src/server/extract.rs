@@ -0,0 +1,257 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Extractors which let method handlers declare the parts of a call they need
+//! as typed arguments, see [`RpcModule::register_handler`](crate::server::RpcModule::register_handler).
+
+use std::any::type_name;
+use std::future::Future;
+use std::sync::Arc;
+
+use futures_util::future::BoxFuture;
+use http::Extensions;
+use jsonrpsee_types::error::{ErrorCode, INTERNAL_ERROR_MSG, INVALID_PARAMS_MSG};
+use jsonrpsee_types::{ErrorObject, ErrorObjectOwned, Params};
+use serde::de::DeserializeOwned;
+
+use crate::server::{ConnectionId, IntoResponse, Session};
+
+/// The parts of a method call which are available to the extractors.
+#[derive(Debug)]
+pub struct RequestParts<'a, Context> {
+	pub(crate) params: &'a Params<'a>,
+	pub(crate) ctx: &'a Arc<Context>,
+	pub(crate) conn_id: ConnectionId,
+	pub(crate) extensions: &'a Extensions,
+}
+
+impl<'a, Context> RequestParts<'a, Context> {
+	/// Parameters of the call.
+	pub fn params(&self) -> &'a Params<'a> {
+		self.params
+	}
+
+	/// Context of the module.
+	pub fn context(&self) -> &'a Arc<Context> {
+		self.ctx
+	}
+
+	/// Connection ID of the call.
+	pub fn conn_id(&self) -> ConnectionId {
+		self.conn_id
+	}
+
+	/// Extensions of the call.
+	pub fn extensions(&self) -> &'a Extensions {
+		self.extensions
+	}
+}
+
+/// Something that can be extracted from a method call and passed as argument to a [`Handler`].
+///
+/// Params that can't be parsed are rejected with `-32602 Invalid params` and missing
+/// extensions with `-32603 Internal error`. Wrap the extractor in an [`Option`] to make it optional.
+///
+/// ```
+/// use jsonrpsee_core::server::{FromRequest, RequestParts};
+/// use jsonrpsee_types::{ErrorObject, ErrorObjectOwned};
+///
+/// struct ApiVersion(u32);
+///
+/// impl<Context> FromRequest<Context> for ApiVersion {
+///     fn from_request(parts: &RequestParts<'_, Context>) -> Result<Self, ErrorObjectOwned> {
+///         match parts.extensions().get::<u32>() {
+///             Some(v) => Ok(ApiVersion(*v)),
+///             None => Err(ErrorObject::owned(-32000, "Unknown API version", None::<()>)),
+///         }
+///     }
+/// }
+/// ```
+pub trait FromRequest<Context>: Sized {
+	/// Extract the value from the call.
+	fn from_request(parts: &RequestParts<'_, Context>) -> Result<Self, ErrorObjectOwned>;
+}
+
+/// Positional params, parsed from the params array as `T` which is usually a tuple.
+///
+/// Missing params are parsed as an empty array.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Positional<T>(pub T);
+
+impl<Context, T: DeserializeOwned> FromRequest<Context> for Positional<T> {
+	fn from_request(parts: &RequestParts<'_, Context>) -> Result<Self, ErrorObjectOwned> {
+		if parts.params.is_object() {
+			return Err(invalid_params("expected positional params"));
+		}
+		let params = parts.params.as_str().unwrap_or("[]");
+		serde_json::from_str(params).map(Positional).map_err(invalid_params)
+	}
+}
+
+/// Named params, parsed from the params object as `T`.
+///
+/// Missing params are parsed as an empty object.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Named<T>(pub T);
+
+impl<Context, T: DeserializeOwned> FromRequest<Context> for Named<T> {
+	fn from_request(parts: &RequestParts<'_, Context>) -> Result<Self, ErrorObjectOwned> {
+		let params = match parts.params.as_str() {
+			Some(params) if parts.params.is_object() => params,
+			Some(_) => return Err(invalid_params("expected named params")),
+			None => "{}",
+		};
+		serde_json::from_str(params).map(Named).map_err(invalid_params)
+	}
+}
+
+/// The context of the module.
+#[derive(Debug)]
+pub struct Ctx<Context>(pub Arc<Context>);
+
+impl<Context> Clone for Ctx<Context> {
+	fn clone(&self) -> Self {
+		Self(self.0.clone())
+	}
+}
+
+impl<Context> FromRequest<Context> for Ctx<Context> {
+	fn from_request(parts: &RequestParts<'_, Context>) -> Result<Self, ErrorObjectOwned> {
+		Ok(Ctx(parts.ctx.clone()))
+	}
+}
+
+/// A value of type `T` from the extensions of the call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Extension<T>(pub T);
+
+impl<Context, T: Clone + Send + Sync + 'static> FromRequest<Context> for Extension<T> {
+	fn from_request(parts: &RequestParts<'_, Context>) -> Result<Self, ErrorObjectOwned> {
+		match parts.extensions.get::<T>() {
+			Some(value) => Ok(Extension(value.clone())),
+			None => Err(internal_error(format!("missing extension `{}`", type_name::<T>()))),
+		}
+	}
+}
+
+/// A value of type `T` from the [`Session`] of the connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SessionData<T>(pub T);
+
+impl<Context, T: Clone + Send + Sync + 'static> FromRequest<Context> for SessionData<T> {
+	fn from_request(parts: &RequestParts<'_, Context>) -> Result<Self, ErrorObjectOwned> {
+		match Session::from_extensions(parts.extensions).get::<T>() {
+			Some(value) => Ok(SessionData(value)),
+			None => Err(internal_error(format!("missing session data `{}`", type_name::<T>()))),
+		}
+	}
+}
+
+impl<Context> FromRequest<Context> for Params<'static> {
+	fn from_request(parts: &RequestParts<'_, Context>) -> Result<Self, ErrorObjectOwned> {
+		Ok(parts.params.clone().into_owned())
+	}
+}
+
+impl<Context> FromRequest<Context> for ConnectionId {
+	fn from_request(parts: &RequestParts<'_, Context>) -> Result<Self, ErrorObjectOwned> {
+		Ok(parts.conn_id)
+	}
+}
+
+impl<Context> FromRequest<Context> for Extensions {
+	fn from_request(parts: &RequestParts<'_, Context>) -> Result<Self, ErrorObjectOwned> {
+		Ok(parts.extensions.clone())
+	}
+}
+
+impl<Context> FromRequest<Context> for Session {
+	fn from_request(parts: &RequestParts<'_, Context>) -> Result<Self, ErrorObjectOwned> {
+		Ok(Session::from_extensions(parts.extensions))
+	}
+}
+
+impl<Context, T: FromRequest<Context>> FromRequest<Context> for Option<T> {
+	fn from_request(parts: &RequestParts<'_, Context>) -> Result<Self, ErrorObjectOwned> {
+		Ok(T::from_request(parts).ok())
+	}
+}
+
+impl<Context, T: FromRequest<Context>> FromRequest<Context> for Result<T, ErrorObjectOwned> {
+	fn from_request(parts: &RequestParts<'_, Context>) -> Result<Self, ErrorObjectOwned> {
+		Ok(T::from_request(parts))
+	}
+}
+
+/// An async function whose arguments are [`FromRequest`] extractors and which is registered
+/// with [`RpcModule::register_handler`](crate::server::RpcModule::register_handler).
+///
+/// It's implemented for functions with up to 8 arguments.
+pub trait Handler<Context, Args>: Clone + Send + Sync + 'static {
+	/// Response of the handler.
+	type Response: IntoResponse + 'static;
+
+	/// Extract the arguments and call the handler.
+	fn call(&self, parts: &RequestParts<'_, Context>) -> Result<BoxFuture<'static, Self::Response>, ErrorObjectOwned>;
+}
+
+macro_rules! impl_handler {
+	($($ty:ident),*) => {
+		impl<Context, F, Fut, $($ty,)*> Handler<Context, ($($ty,)*)> for F
+		where
+			F: Fn($($ty),*) -> Fut + Clone + Send + Sync + 'static,
+			Fut: Future + Send + 'static,
+			Fut::Output: IntoResponse + 'static,
+			$($ty: FromRequest<Context>,)*
+		{
+			type Response = Fut::Output;
+
+			#[allow(non_snake_case, unused_variables)]
+			fn call(&self, parts: &RequestParts<'_, Context>) -> Result<BoxFuture<'static, Self::Response>, ErrorObjectOwned> {
+				$(let $ty = $ty::from_request(parts)?;)*
+				Ok(Box::pin((self)($($ty),*)))
+			}
+		}
+	};
+}
+
+impl_handler!();
+impl_handler!(T1);
+impl_handler!(T1, T2);
+impl_handler!(T1, T2, T3);
+impl_handler!(T1, T2, T3, T4);
+impl_handler!(T1, T2, T3, T4, T5);
+impl_handler!(T1, T2, T3, T4, T5, T6);
+impl_handler!(T1, T2, T3, T4, T5, T6, T7);
+impl_handler!(T1, T2, T3, T4, T5, T6, T7, T8);
+
+fn invalid_params(e: impl ToString) -> ErrorObjectOwned {
+	ErrorObject::owned(ErrorCode::InvalidParams.code(), INVALID_PARAMS_MSG, Some(e.to_string()))
+}
+
+fn internal_error(e: String) -> ErrorObjectOwned {
+	ErrorObject::owned(ErrorCode::InternalError.code(), INTERNAL_ERROR_MSG, Some(e))
+}
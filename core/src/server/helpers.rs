@@ -24,11 +24,15 @@
 // IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::time::Duration;
 
 use jsonrpsee_types::{ErrorCode, ErrorObject, Id, InvalidRequest, Response, ResponsePayload};
+use parking_lot::Mutex;
 use serde_json::value::RawValue;
-use tokio::sync::mpsc;
+use tokio::sync::{Notify, mpsc};
 
 use super::{DisconnectError, SendTimeoutError, TrySendError};
 
@@ -39,17 +43,41 @@ pub struct MethodSink {
 	tx: mpsc::Sender<Box<RawValue>>,
 	/// Max response size in bytes for a executed call.
 	max_response_size: u32,
+	/// Byte budget of the messages buffered on the connection.
+	budget: Option<BufferBudget>,
+	/// Bytes buffered by the subscription this sink belongs to.
+	subscription: Option<SubscriptionBuffer>,
 }
 
 impl MethodSink {
 	/// Create a new `MethodSink` with unlimited response size.
 	pub fn new(tx: mpsc::Sender<Box<RawValue>>) -> Self {
-		MethodSink { tx, max_response_size: u32::MAX }
+		MethodSink { tx, max_response_size: u32::MAX, budget: None, subscription: None }
 	}
 
 	/// Create a new `MethodSink` with a limited response size.
 	pub fn new_with_limit(tx: mpsc::Sender<Box<RawValue>>, max_response_size: u32) -> Self {
-		MethodSink { tx, max_response_size }
+		MethodSink { tx, max_response_size, budget: None, subscription: None }
+	}
+
+	/// Limit the number of bytes buffered on the connection with the given budget.
+	///
+	/// The receiving end of the channel must [release](BufferBudget::release) the bytes
+	/// of every message once it has been sent out.
+	pub fn with_budget(mut self, budget: BufferBudget) -> Self {
+		self.budget = Some(budget);
+		self
+	}
+
+	/// Get the byte budget of the connection, if any.
+	pub fn budget(&self) -> Option<&BufferBudget> {
+		self.budget.as_ref()
+	}
+
+	/// Create a sink for a subscription which tracks the bytes buffered by the subscription.
+	pub(crate) fn for_subscription(&self) -> Self {
+		let subscription = self.budget.as_ref().and_then(|b| b.max_subscription_bytes).map(SubscriptionBuffer::new);
+		MethodSink { subscription, ..self.clone() }
 	}
 
 	/// Returns whether this channel is closed without needing a context.
@@ -76,12 +104,51 @@ impl MethodSink {
 	///
 	/// Returns the message if the send fails such that either can be thrown away or re-sent later.
 	pub fn try_send(&mut self, msg: Box<RawValue>) -> Result<(), TrySendError> {
-		self.tx.try_send(msg).map_err(Into::into)
+		let Some(budget) = &self.budget else {
+			return self.tx.try_send(msg).map_err(Into::into);
+		};
+
+		let permit = match self.tx.try_reserve() {
+			Ok(permit) => permit,
+			Err(mpsc::error::TrySendError::Full(_)) => return Err(TrySendError::Full(msg.into())),
+			Err(mpsc::error::TrySendError::Closed(_)) => return Err(TrySendError::Closed(msg.into())),
+		};
+
+		let len = msg.get().len();
+		if !budget.has_room(len, self.subscription.as_ref()) {
+			return Err(TrySendError::Full(msg.into()));
+		}
+
+		budget.enqueue(permit, msg, self.subscription.as_ref()).map_err(|msg| TrySendError::Closed(msg.into()))
 	}
 
 	/// Async send which will wait until there is space in channel buffer or that the subscription is disconnected.
+	///
+	/// If the connection has a [`BufferBudget`] and the message would exceed it, the connection is regarded
+	/// as a slow consumer and closed. Subscriptions wait until there is room in their own budget instead.
 	pub async fn send(&self, msg: Box<RawValue>) -> Result<(), DisconnectError> {
-		self.tx.send(msg).await.map_err(Into::into)
+		let Some(budget) = &self.budget else {
+			return self.tx.send(msg).await.map_err(Into::into);
+		};
+
+		if let Some(sub) = &self.subscription {
+			let len = msg.get().len();
+			let has_room = tokio::select! {
+				room = budget.wait_for_room(len, Some(sub), true) => room,
+				_ = self.tx.closed() => false,
+			};
+
+			if !has_room {
+				return Err(DisconnectError(msg.into()));
+			}
+		}
+
+		let permit = match self.tx.reserve().await {
+			Ok(permit) => permit,
+			Err(_) => return Err(DisconnectError(msg.into())),
+		};
+
+		budget.enqueue(permit, msg, self.subscription.as_ref()).map_err(|msg| DisconnectError(msg.into()))
 	}
 
 	/// Send a JSON-RPC error to the client
@@ -94,7 +161,15 @@ impl MethodSink {
 
 	/// Similar to `MethodSink::send` but only waits for a limited time.
 	pub async fn send_timeout(&self, msg: Box<RawValue>, timeout: Duration) -> Result<(), SendTimeoutError> {
-		self.tx.send_timeout(msg, timeout).await.map_err(Into::into)
+		if self.budget.is_none() {
+			return self.tx.send_timeout(msg, timeout).await.map_err(Into::into);
+		}
+
+		match tokio::time::timeout(timeout, self.send(msg.clone())).await {
+			Ok(Ok(())) => Ok(()),
+			Ok(Err(DisconnectError(msg))) => Err(SendTimeoutError::Closed(msg)),
+			Err(_) => Err(SendTimeoutError::Timeout(msg.into())),
+		}
 	}
 
 	/// Get the capacity of the channel.
@@ -108,13 +183,214 @@ impl MethodSink {
 	}
 
 	/// Waits for there to be space on the return channel.
+	///
+	/// If the connection has a [`BufferBudget`] this also waits until
+	/// the buffered bytes are below the budget.
 	pub async fn has_capacity(&self) -> Result<(), DisconnectError> {
+		let disconnected = || DisconnectError(RawValue::NULL.to_owned().into());
+
 		match self.tx.reserve().await {
 			// The permit is thrown away here because it's just
 			// a way to ensure that the return buffer has space.
-			Ok(_) => Ok(()),
-			Err(_) => Err(DisconnectError(RawValue::NULL.to_owned().into())),
+			Ok(_) => (),
+			Err(_) => return Err(disconnected()),
+		};
+
+		if let Some(budget) = &self.budget {
+			let has_room = tokio::select! {
+				room = budget.wait_for_room(1, self.subscription.as_ref(), false) => room,
+				_ = self.tx.closed() => false,
+			};
+
+			if !has_room {
+				return Err(disconnected());
+			}
 		}
+
+		Ok(())
+	}
+}
+
+/// Limits the number of bytes of the messages buffered for sending on a connection.
+///
+/// If a message doesn't fit into the budget of the connection, the connection is regarded as a slow
+/// consumer and is marked as [exceeded](BufferBudget::is_exceeded), which the transport should
+/// act upon by closing the connection. A single message is always let through if nothing is buffered.
+///
+/// The budget may also limit the bytes buffered by each subscription on the connection. In that case
+/// subscriptions wait until there is room in their own budget before sending.
+#[derive(Debug, Clone)]
+pub struct BufferBudget {
+	inner: Arc<BudgetInner>,
+	max_subscription_bytes: Option<usize>,
+}
+
+#[derive(Debug)]
+struct BudgetInner {
+	max_bytes: usize,
+	/// The total number of bytes that have been enqueued.
+	///
+	/// The lock is held while the message is enqueued such that the order of
+	/// the counter is the same as the order of the messages in the channel.
+	enqueued: Mutex<u64>,
+	/// The total number of bytes that have been released.
+	released: AtomicU64,
+	exceeded: AtomicBool,
+	notify: Notify,
+}
+
+impl BufferBudget {
+	/// Create a new budget of `max_bytes` for the connection.
+	pub fn new(max_bytes: usize) -> Self {
+		Self {
+			inner: Arc::new(BudgetInner {
+				max_bytes,
+				enqueued: Mutex::new(0),
+				released: AtomicU64::new(0),
+				exceeded: AtomicBool::new(false),
+				notify: Notify::new(),
+			}),
+			max_subscription_bytes: None,
+		}
+	}
+
+	/// Limit the number of bytes buffered by each subscription.
+	pub fn with_subscription_limit(mut self, max_bytes: usize) -> Self {
+		self.max_subscription_bytes = Some(max_bytes);
+		self
+	}
+
+	/// Get the max number of bytes of the connection.
+	pub fn max_bytes(&self) -> usize {
+		self.inner.max_bytes
+	}
+
+	/// Get the number of bytes that are currently buffered.
+	pub fn buffered_bytes(&self) -> usize {
+		// `released` is loaded while holding the lock such that it can't exceed `enqueued`.
+		let enqueued = self.inner.enqueued.lock();
+		(*enqueued - self.inner.released.load(Ordering::Acquire)) as usize
+	}
+
+	/// Returns whether a message didn't fit into the budget and the connection should be closed.
+	pub fn is_exceeded(&self) -> bool {
+		self.inner.exceeded.load(Ordering::Acquire)
+	}
+
+	/// Wait until a message didn't fit into the budget.
+	///
+	/// This resolves as soon as the message is rejected, the transport doesn't have
+	/// to wait for the next buffered message to notice that the budget was exceeded.
+	pub async fn exceeded(&self) {
+		loop {
+			let notified = self.inner.notify.notified();
+			tokio::pin!(notified);
+			notified.as_mut().enable();
+
+			if self.is_exceeded() {
+				return;
+			}
+
+			notified.await;
+		}
+	}
+
+	/// Release the bytes of a message that has been sent out.
+	pub fn release(&self, bytes: usize) {
+		self.inner.released.fetch_add(bytes as u64, Ordering::AcqRel);
+		self.inner.notify.notify_waiters();
+	}
+
+	fn has_room(&self, len: usize, sub: Option<&SubscriptionBuffer>) -> bool {
+		let released = self.inner.released.load(Ordering::Acquire);
+		let enqueued = *self.inner.enqueued.lock();
+
+		fits(self.inner.max_bytes, (enqueued - released) as usize, len)
+			&& sub.is_none_or(|sub| fits(sub.max_bytes, sub.buffered(released), len))
+	}
+
+	/// Wait until there is room for `len` bytes.
+	///
+	/// If `only_subscription` is set only the budget of the subscription is considered.
+	/// Returns `false` if the budget has been exceeded.
+	async fn wait_for_room(&self, len: usize, sub: Option<&SubscriptionBuffer>, only_subscription: bool) -> bool {
+		loop {
+			let notified = self.inner.notify.notified();
+			tokio::pin!(notified);
+			notified.as_mut().enable();
+
+			if self.is_exceeded() {
+				return false;
+			}
+
+			let released = self.inner.released.load(Ordering::Acquire);
+			let sub_has_room = sub.is_none_or(|sub| fits(sub.max_bytes, sub.buffered(released), len));
+			let conn_has_room = only_subscription || self.has_room(len, None);
+
+			if sub_has_room && conn_has_room {
+				return true;
+			}
+
+			notified.await;
+		}
+	}
+
+	/// Enqueue the message or mark the budget as exceeded if it doesn't fit.
+	fn enqueue(
+		&self,
+		permit: mpsc::Permit<'_, Box<RawValue>>,
+		msg: Box<RawValue>,
+		sub: Option<&SubscriptionBuffer>,
+	) -> Result<(), Box<RawValue>> {
+		let len = msg.get().len();
+		let mut enqueued = self.inner.enqueued.lock();
+		let released = self.inner.released.load(Ordering::Acquire);
+
+		if self.is_exceeded() || !fits(self.inner.max_bytes, (*enqueued - released) as usize, len) {
+			self.inner.exceeded.store(true, Ordering::Release);
+			self.inner.notify.notify_waiters();
+			return Err(msg);
+		}
+
+		*enqueued += len as u64;
+		if let Some(sub) = sub {
+			sub.push(*enqueued, len);
+		}
+		permit.send(msg);
+
+		Ok(())
+	}
+}
+
+/// Whether `len` more bytes fit into the budget, a single message always fits.
+fn fits(max: usize, buffered: usize, len: usize) -> bool {
+	buffered == 0 || buffered.saturating_add(len) <= max
+}
+
+/// Tracks the bytes of the messages of a subscription that are buffered on the connection.
+#[derive(Debug, Clone)]
+struct SubscriptionBuffer {
+	max_bytes: usize,
+	/// The position of the end of each buffered message and its length.
+	messages: Arc<Mutex<VecDeque<(u64, usize)>>>,
+}
+
+impl SubscriptionBuffer {
+	fn new(max_bytes: usize) -> Self {
+		Self { max_bytes, messages: Default::default() }
+	}
+
+	fn push(&self, end: u64, len: usize) {
+		self.messages.lock().push_back((end, len));
+	}
+
+	/// The number of bytes still buffered given the number of bytes released on the connection.
+	fn buffered(&self, released: u64) -> usize {
+		let mut messages = self.messages.lock();
+		while messages.front().is_some_and(|(end, _)| *end <= released) {
+			messages.pop_front();
+		}
+		messages.iter().map(|(_, len)| len).sum()
 	}
 }
 
@@ -126,3 +402,66 @@ pub fn prepare_error(data: &[u8]) -> (Id<'_>, ErrorCode) {
 		Err(_) => (Id::Null, ErrorCode::ParseError),
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn msg(len: usize) -> Box<RawValue> {
+		serde_json::value::to_raw_value(&"x".repeat(len - 2)).unwrap()
+	}
+
+	#[tokio::test]
+	async fn buffer_budget_marks_slow_consumer() {
+		let (tx, mut rx) = mpsc::channel(16);
+		let budget = BufferBudget::new(100);
+		let mut sink = MethodSink::new(tx).with_budget(budget.clone());
+
+		sink.send(msg(60)).await.unwrap();
+		assert_eq!(budget.buffered_bytes(), 60);
+
+		// Doesn't fit but `try_send` doesn't close the connection.
+		assert!(matches!(sink.try_send(msg(60)), Err(TrySendError::Full(_))));
+		assert!(!budget.is_exceeded());
+
+		// Release the message and the next one fits.
+		let sent = rx.recv().await.unwrap();
+		budget.release(sent.get().len());
+		sink.try_send(msg(60)).unwrap();
+
+		// The connection is regarded as slow consumer as soon as the message is rejected.
+		let exceeded = tokio::spawn({
+			let budget = budget.clone();
+			async move { budget.exceeded().await }
+		});
+		assert!(sink.send(msg(60)).await.is_err());
+		assert!(budget.is_exceeded());
+		exceeded.await.unwrap();
+		assert!(sink.has_capacity().await.is_err());
+	}
+
+	#[tokio::test]
+	async fn subscription_waits_for_its_budget() {
+		let (tx, mut rx) = mpsc::channel(16);
+		let budget = BufferBudget::new(1000).with_subscription_limit(100);
+		let sink = MethodSink::new(tx).with_budget(budget.clone());
+		let mut sub = sink.for_subscription();
+
+		sub.send(msg(60)).await.unwrap();
+		assert!(matches!(sub.try_send(msg(60)), Err(TrySendError::Full(_))));
+
+		// Other messages on the connection are not affected.
+		sink.send(msg(60)).await.unwrap();
+
+		let send = sub.send(msg(60));
+		tokio::pin!(send);
+		assert!(futures_util::poll!(send.as_mut()).is_pending());
+
+		let sent = rx.recv().await.unwrap();
+		budget.release(sent.get().len());
+		send.await.unwrap();
+
+		assert_eq!(budget.buffered_bytes(), 120);
+		assert!(!budget.is_exceeded());
+	}
+}
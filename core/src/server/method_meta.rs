@@ -0,0 +1,154 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use std::borrow::Cow;
+use std::sync::Arc;
+
+/// A value of the [`MethodMeta`] of a method.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MetaValue {
+	/// A boolean, such as `meta(public)` or `meta(public = true)`.
+	Bool(bool),
+	/// An integer, such as `meta(rate_limit = 10)`.
+	Int(i64),
+	/// A float, such as `meta(weight = 0.5)`.
+	Float(f64),
+	/// A string, such as `meta(auth = "admin")`.
+	Str(Cow<'static, str>),
+}
+
+impl MetaValue {
+	/// Returns the boolean if the value is a boolean.
+	pub fn as_bool(&self) -> Option<bool> {
+		match self {
+			Self::Bool(b) => Some(*b),
+			_ => None,
+		}
+	}
+
+	/// Returns the integer if the value is an integer.
+	pub fn as_int(&self) -> Option<i64> {
+		match self {
+			Self::Int(n) => Some(*n),
+			_ => None,
+		}
+	}
+
+	/// Returns the float if the value is a float or an integer.
+	pub fn as_float(&self) -> Option<f64> {
+		match self {
+			Self::Float(f) => Some(*f),
+			Self::Int(n) => Some(*n as f64),
+			_ => None,
+		}
+	}
+
+	/// Returns the string if the value is a string.
+	pub fn as_str(&self) -> Option<&str> {
+		match self {
+			Self::Str(s) => Some(s),
+			_ => None,
+		}
+	}
+}
+
+impl From<bool> for MetaValue {
+	fn from(b: bool) -> Self {
+		Self::Bool(b)
+	}
+}
+
+impl From<i64> for MetaValue {
+	fn from(n: i64) -> Self {
+		Self::Int(n)
+	}
+}
+
+impl From<f64> for MetaValue {
+	fn from(f: f64) -> Self {
+		Self::Float(f)
+	}
+}
+
+impl From<&'static str> for MetaValue {
+	fn from(s: &'static str) -> Self {
+		Self::Str(Cow::Borrowed(s))
+	}
+}
+
+impl From<String> for MetaValue {
+	fn from(s: String) -> Self {
+		Self::Str(Cow::Owned(s))
+	}
+}
+
+/// Metadata of a method, such as `#[method(name = "foo", meta(rate_limit = 10, auth = "admin"))]`.
+///
+/// The server inserts the metadata of a method into the extensions of its requests before they are
+/// processed by the RPC middleware, such that middleware can treat methods differently without
+/// matching on their names.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MethodMeta {
+	entries: Arc<Vec<(&'static str, MetaValue)>>,
+}
+
+impl MethodMeta {
+	/// Create empty metadata.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Set the value of `key`, replacing the previous value.
+	pub fn with(mut self, key: &'static str, value: impl Into<MetaValue>) -> Self {
+		let entries = Arc::make_mut(&mut self.entries);
+		let value = value.into();
+		match entries.iter_mut().find(|(k, _)| *k == key) {
+			Some((_, v)) => *v = value,
+			None => entries.push((key, value)),
+		}
+		self
+	}
+
+	/// Returns the value of `key`.
+	pub fn get(&self, key: &str) -> Option<&MetaValue> {
+		self.entries.iter().find(|(k, _)| *k == key).map(|(_, v)| v)
+	}
+
+	/// Returns an `Iterator` over the keys and values of the metadata.
+	pub fn iter(&self) -> impl Iterator<Item = (&'static str, &MetaValue)> + '_ {
+		self.entries.iter().map(|(k, v)| (*k, v))
+	}
+
+	/// Returns the number of keys.
+	pub fn len(&self) -> usize {
+		self.entries.len()
+	}
+
+	/// Returns whether the metadata is empty.
+	pub fn is_empty(&self) -> bool {
+		self.entries.is_empty()
+	}
+}
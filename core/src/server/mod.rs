@@ -26,22 +26,37 @@
 
 //! Shared modules for the JSON-RPC servers.
 
+/// Deprecation of methods.
+mod deprecation;
 /// Error types.
 mod error;
+/// Extractors for method handlers.
+mod extract;
 /// Helpers.
 pub mod helpers;
+/// Metadata of methods.
+mod method_meta;
 /// Method response.
 mod method_response;
+/// Topic based publish/subscribe.
+mod pubsub;
 /// JSON-RPC "modules" group sets of methods that belong together and handles method/subscription registration.
 mod rpc_module;
+/// Per-connection session state.
+mod session;
 /// Subscription related types.
 mod subscription;
 
+pub use deprecation::*;
 pub use error::*;
+pub use extract::*;
 pub use helpers::*;
 pub use http::Extensions;
+pub use method_meta::*;
 pub use method_response::*;
+pub use pubsub::*;
 pub use rpc_module::*;
+pub use session::*;
 pub use subscription::*;
 
 use jsonrpsee_types::ErrorObjectOwned;
@@ -0,0 +1,342 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Topic based publish/subscribe for subscriptions.
+
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Weak};
+
+use futures_util::future::{self, BoxFuture, join_all};
+use futures_util::stream::{self, BoxStream};
+use futures_util::{FutureExt, StreamExt};
+use parking_lot::{Mutex, RwLock};
+use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+use tokio::sync::broadcast;
+use tokio::task::AbortHandle;
+
+use super::{LOG_TARGET, PubSubError, SubscriptionSink};
+
+/// Backend to publish and subscribe to messages by topic, which makes it possible
+/// to share topics between several [`PubSubHub`]s such as servers in a cluster.
+///
+/// A message published on a topic must be delivered to all streams subscribed to that topic,
+/// including the ones created by the publisher itself.
+pub trait PubSubBackend: Send + Sync + 'static {
+	/// Publish a message on `topic`.
+	fn publish(&self, topic: &str, message: Box<RawValue>) -> BoxFuture<'static, Result<(), PubSubError>>;
+
+	/// Subscribe to the messages published on `topic`.
+	///
+	/// The subscription is terminated once the stream is dropped.
+	fn subscribe(&self, topic: &str) -> BoxFuture<'static, Result<BoxStream<'static, Box<RawValue>>, PubSubError>>;
+}
+
+impl<B: PubSubBackend> PubSubBackend for Arc<B> {
+	fn publish(&self, topic: &str, message: Box<RawValue>) -> BoxFuture<'static, Result<(), PubSubError>> {
+		(**self).publish(topic, message)
+	}
+
+	fn subscribe(&self, topic: &str) -> BoxFuture<'static, Result<BoxStream<'static, Box<RawValue>>, PubSubError>> {
+		(**self).subscribe(topic)
+	}
+}
+
+/// [`PubSubBackend`] that shares topics between [`PubSubHub`]s in the same process.
+#[derive(Debug, Clone)]
+pub struct InProcessBackend {
+	topics: Arc<Mutex<FxHashMap<String, broadcast::Sender<Box<RawValue>>>>>,
+	capacity: usize,
+}
+
+impl Default for InProcessBackend {
+	fn default() -> Self {
+		Self::with_capacity(1024)
+	}
+}
+
+impl InProcessBackend {
+	/// Create a new backend where each topic buffers up to 1024 messages per subscriber.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Create a new backend where each topic buffers up to `capacity` messages per subscriber.
+	///
+	/// Subscribers that fall further behind miss the oldest messages.
+	///
+	/// # Panics
+	///
+	/// Panics if `capacity` is zero.
+	pub fn with_capacity(capacity: usize) -> Self {
+		assert!(capacity > 0, "capacity must be greater than zero");
+		Self { topics: Default::default(), capacity }
+	}
+}
+
+impl PubSubBackend for InProcessBackend {
+	fn publish(&self, topic: &str, message: Box<RawValue>) -> BoxFuture<'static, Result<(), PubSubError>> {
+		let mut topics = self.topics.lock();
+
+		// Fails if there are no subscribers left.
+		if topics.get(topic).is_some_and(|tx| tx.send(message).is_err()) {
+			topics.remove(topic);
+		}
+
+		future::ready(Ok(())).boxed()
+	}
+
+	fn subscribe(&self, topic: &str) -> BoxFuture<'static, Result<BoxStream<'static, Box<RawValue>>, PubSubError>> {
+		let rx = self
+			.topics
+			.lock()
+			.entry(topic.to_owned())
+			.or_insert_with(|| broadcast::channel(self.capacity).0)
+			.subscribe();
+
+		let stream = stream::unfold(rx, |mut rx| async move {
+			loop {
+				match rx.recv().await {
+					Ok(msg) => return Some((msg, rx)),
+					Err(broadcast::error::RecvError::Lagged(n)) => {
+						tracing::debug!(target: LOG_TARGET, "In-process pub/sub subscriber lagged; {n} messages were missed");
+					}
+					Err(broadcast::error::RecvError::Closed) => return None,
+				}
+			}
+		});
+
+		future::ready(Ok(stream.boxed())).boxed()
+	}
+}
+
+/// Message passed via the [`PubSubBackend`], the origin makes it possible
+/// to skip messages that were published by the receiving hub itself.
+#[derive(Serialize, Deserialize)]
+struct BackendMessage<'a> {
+	origin: u64,
+	#[serde(borrow)]
+	result: &'a RawValue,
+}
+
+/// Hub that fans out subscription notifications to all sinks subscribed to a named topic.
+///
+/// Publishing serializes the `result` only once and splices it into the notification
+/// of each subscriber, which only differ in the subscription ID and method name.
+///
+/// Subscribers that can't keep up are handled according to the [`crate::server::OverflowPolicy`]
+/// the subscription was accepted with. Note that publishing waits for subscribers accepted with
+/// [`crate::server::OverflowPolicy::Block`] to have capacity.
+///
+/// Closed subscriptions are removed from the hub automatically.
+///
+/// To share topics between several hubs, for instance servers in a cluster,
+/// create the hub with a [`PubSubBackend`] via [`PubSubHub::with_backend`].
+///
+/// ```no_run
+/// use jsonrpsee::{PubSubHub, RpcModule};
+///
+/// let hub = PubSubHub::new();
+/// let mut module = RpcModule::new(hub.clone());
+///
+/// module
+///     .register_subscription("subscribe_newHeads", "newHeads", "unsubscribe_newHeads", |_, pending, hub, _| async move {
+///         let sink = pending.accept().await?;
+///         hub.subscribe("newHeads", sink);
+///         Ok(())
+///     })
+///     .unwrap();
+///
+/// # async fn publish(hub: PubSubHub) {
+/// hub.publish("newHeads", &1337).await.unwrap();
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PubSubHub {
+	inner: Arc<HubInner>,
+}
+
+#[derive(Default)]
+struct HubInner {
+	topics: RwLock<FxHashMap<String, Topic>>,
+	next_id: AtomicU64,
+	/// Unique ID of the hub on the backend.
+	origin: u64,
+	backend: Option<Box<dyn PubSubBackend>>,
+}
+
+impl fmt::Debug for HubInner {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("HubInner")
+			.field("topics", &self.topics)
+			.field("origin", &self.origin)
+			.field("backend", &self.backend.is_some())
+			.finish()
+	}
+}
+
+#[derive(Debug, Default)]
+struct Topic {
+	subscribers: FxHashMap<u64, Arc<SubscriptionSink>>,
+	/// Task forwarding the messages from the backend.
+	backend_task: Option<AbortHandle>,
+}
+
+impl HubInner {
+	fn remove(&self, topic: &str, id: u64) {
+		let mut topics = self.topics.write();
+
+		if let Some(t) = topics.get_mut(topic) {
+			t.subscribers.remove(&id);
+			if t.subscribers.is_empty() {
+				if let Some(task) = t.backend_task.take() {
+					task.abort();
+				}
+				topics.remove(topic);
+			}
+		}
+	}
+
+	async fn publish_local(&self, topic: &str, result: &RawValue) -> usize {
+		let sinks: Vec<_> = match self.topics.read().get(topic) {
+			Some(t) => t.subscribers.values().cloned().collect(),
+			None => return 0,
+		};
+
+		let sends = sinks.iter().map(|sink| sink.send_result(result));
+		join_all(sends).await.into_iter().filter(Result::is_ok).count()
+	}
+}
+
+impl PubSubHub {
+	/// Create a new hub without any topics.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Create a new hub which shares its topics with other hubs via `backend`.
+	///
+	/// Messages published on this hub are delivered to the local subscribers directly
+	/// and to subscribers of other hubs via the backend.
+	pub fn with_backend(backend: impl PubSubBackend) -> Self {
+		let inner = HubInner { origin: rand::random(), backend: Some(Box::new(backend)), ..Default::default() };
+		Self { inner: Arc::new(inner) }
+	}
+
+	/// Subscribe the sink to `topic`.
+	///
+	/// The sink is kept by the hub until the subscription is closed.
+	pub fn subscribe(&self, topic: impl Into<String>, sink: SubscriptionSink) {
+		let topic = topic.into();
+		let id = self.inner.next_id.fetch_add(1, Ordering::Relaxed);
+		let sink = Arc::new(sink);
+
+		{
+			let mut topics = self.inner.topics.write();
+			let t = topics.entry(topic.clone()).or_default();
+			if t.subscribers.is_empty() {
+				t.backend_task = self.spawn_backend_task(&topic);
+			}
+			t.subscribers.insert(id, sink.clone());
+		}
+
+		let hub: Weak<HubInner> = Arc::downgrade(&self.inner);
+
+		tokio::spawn(async move {
+			sink.closed().await;
+
+			if let Some(hub) = hub.upgrade() {
+				hub.remove(&topic, id);
+			}
+		});
+	}
+
+	/// Serialize `result` once and send it out as a subscription notification to all subscribers of `topic`.
+	///
+	/// Returns the number of local subscribers the notification was passed on to
+	/// or an error if `result` couldn't be serialized or the backend failed.
+	pub async fn publish<T: Serialize + ?Sized>(&self, topic: &str, result: &T) -> Result<usize, PubSubError> {
+		let result = serde_json::value::to_raw_value(result)?;
+		self.publish_raw(topic, &result).await
+	}
+
+	/// Similar to [`PubSubHub::publish`] but the `result` is already serialized.
+	pub async fn publish_raw(&self, topic: &str, result: &RawValue) -> Result<usize, PubSubError> {
+		if let Some(backend) = &self.inner.backend {
+			let msg = serde_json::value::to_raw_value(&BackendMessage { origin: self.inner.origin, result })?;
+			backend.publish(topic, msg).await?;
+		}
+
+		Ok(self.inner.publish_local(topic, result).await)
+	}
+
+	/// Get the number of local subscribers of `topic`.
+	pub fn subscriber_count(&self, topic: &str) -> usize {
+		self.inner.topics.read().get(topic).map_or(0, |t| t.subscribers.len())
+	}
+
+	/// Get all topics that have at least one local subscriber.
+	pub fn topics(&self) -> Vec<String> {
+		self.inner.topics.read().keys().cloned().collect()
+	}
+
+	/// Forward the messages published by other hubs on `topic` to the local subscribers.
+	fn spawn_backend_task(&self, topic: &str) -> Option<AbortHandle> {
+		let subscribe = self.inner.backend.as_ref()?.subscribe(topic);
+		let hub = Arc::downgrade(&self.inner);
+		let topic = topic.to_owned();
+
+		let task = tokio::spawn(async move {
+			let mut messages = match subscribe.await {
+				Ok(messages) => messages,
+				Err(e) => {
+					tracing::warn!(target: LOG_TARGET, "Failed to subscribe to topic `{topic}` on the pub/sub backend: {e}");
+					return;
+				}
+			};
+
+			while let Some(msg) = messages.next().await {
+				let Some(hub) = hub.upgrade() else {
+					break;
+				};
+
+				match serde_json::from_str::<BackendMessage>(msg.get()) {
+					Ok(msg) if msg.origin == hub.origin => (),
+					Ok(msg) => {
+						hub.publish_local(&topic, msg.result).await;
+					}
+					Err(e) => {
+						tracing::debug!(target: LOG_TARGET, "Invalid message on topic `{topic}` from the pub/sub backend: {e}");
+					}
+				}
+			}
+		});
+
+		Some(task.abort_handle())
+	}
+}
@@ -30,12 +30,13 @@ use std::future::Future;
 use std::ops::{Deref, DerefMut};
 use std::sync::Arc;
 
-use crate::error::RegisterMethodError;
+use crate::error::{RegisterMethodError, SubscriptionError};
 use crate::id_providers::RandomIntegerIdProvider;
 use crate::server::helpers::MethodSink;
 use crate::server::subscription::{
-	BoundedSubscriptions, IntoSubscriptionCloseResponse, PendingSubscriptionSink, Subscribers, Subscription,
-	SubscriptionCloseResponse, SubscriptionKey, SubscriptionPermit, SubscriptionState, sub_message_to_json,
+	BoundedSubscriptions, IntoSubscriptionCloseResponse, PendingOverflow, PendingSubscriptionSink, Subscribers,
+	Subscription, SubscriptionCloseResponse, SubscriptionKey, SubscriptionPermit, SubscriptionState,
+	sub_message_to_json,
 };
 use crate::server::{LOG_TARGET, MethodResponse, ResponsePayload};
 use crate::traits::ToRpcParams;
@@ -50,7 +51,7 @@ use serde::de::DeserializeOwned;
 use serde_json::value::RawValue;
 use tokio::sync::{mpsc, oneshot};
 
-use super::{IntoResponse, sub_err_to_json};
+use super::{DeprecatedMethod, Deprecation, Handler, IntoResponse, MethodMeta, RequestParts, sub_err_to_json};
 
 /// A `MethodCallback` is an RPC endpoint, callable with a standard JSON-RPC request,
 /// implemented as a function pointer to a `Fn` function taking four arguments:
@@ -208,10 +209,23 @@ impl Debug for MethodCallback {
 	}
 }
 
+/// Names and subscribers of a registered subscription.
+#[derive(Debug, Clone)]
+struct RegisteredSubscription {
+	notification: &'static str,
+	subscribers: Subscribers,
+}
+
 /// Reference-counted, clone-on-write collection of synchronous and asynchronous methods.
 #[derive(Default, Debug, Clone)]
 pub struct Methods {
 	callbacks: Arc<FxHashMap<&'static str, MethodCallback>>,
+	/// Registered subscriptions by subscribe method name.
+	subscriptions: Arc<FxHashMap<&'static str, RegisteredSubscription>>,
+	/// Deprecated methods by method name.
+	deprecated: Arc<FxHashMap<&'static str, DeprecatedMethod>>,
+	/// Metadata of the methods by method name.
+	meta: Arc<FxHashMap<&'static str, MethodMeta>>,
 	extensions: Extensions,
 }
 
@@ -263,9 +277,64 @@ impl Methods {
 			callbacks.insert(name, callback);
 		}
 
+		Arc::make_mut(&mut self.subscriptions).extend(other.subscriptions.iter().map(|(k, v)| (*k, v.clone())));
+		Arc::make_mut(&mut self.deprecated).extend(other.deprecated.iter().map(|(k, v)| (*k, v.clone())));
+		Arc::make_mut(&mut self.meta).extend(other.meta.iter().map(|(k, v)| (*k, v.clone())));
+
+		Ok(())
+	}
+
+	/// Mark the method `method_name` as deprecated.
+	///
+	/// The calls of deprecated methods are counted, see [`Methods::deprecated_method`], and the server
+	/// may be configured to reject them or to add a [`DeprecationNotice`](super::DeprecationNotice) to their responses.
+	///
+	/// Fails if the method isn't registered.
+	pub fn deprecate(
+		&mut self,
+		method_name: &'static str,
+		deprecation: Deprecation,
+	) -> Result<(), RegisterMethodError> {
+		if !self.callbacks.contains_key(method_name) {
+			return Err(RegisterMethodError::MethodNotFound(method_name.into()));
+		}
+
+		Arc::make_mut(&mut self.deprecated).insert(method_name, DeprecatedMethod::new(deprecation));
+
+		Ok(())
+	}
+
+	/// Returns the deprecated method `method_name`, if it's deprecated.
+	pub fn deprecated_method(&self, method_name: &str) -> Option<&DeprecatedMethod> {
+		self.deprecated.get(method_name)
+	}
+
+	/// Returns an `Iterator` with all the deprecated methods registered on this server.
+	pub fn deprecated_methods(&self) -> impl Iterator<Item = (&'static str, &DeprecatedMethod)> + '_ {
+		self.deprecated.iter().map(|(k, v)| (*k, v))
+	}
+
+	/// Set the metadata of the method `method_name`, replacing its previous metadata.
+	///
+	/// The server inserts the metadata into the extensions of the requests to the method
+	/// before they are processed by the RPC middleware.
+	///
+	/// Fails if the method isn't registered.
+	pub fn set_method_meta(&mut self, method_name: &'static str, meta: MethodMeta) -> Result<(), RegisterMethodError> {
+		if !self.callbacks.contains_key(method_name) {
+			return Err(RegisterMethodError::MethodNotFound(method_name.into()));
+		}
+
+		Arc::make_mut(&mut self.meta).insert(method_name, meta);
+
 		Ok(())
 	}
 
+	/// Returns the metadata of the method `method_name`, if it has any.
+	pub fn method_meta(&self, method_name: &str) -> Option<&MethodMeta> {
+		self.meta.get(method_name)
+	}
+
 	/// Returns the method callback.
 	pub fn method(&self, method_name: &str) -> Option<&MethodCallback> {
 		self.callbacks.get(method_name)
@@ -277,6 +346,59 @@ impl Methods {
 		self.callbacks.get_key_value(method_name).map(|(k, v)| (*k, v))
 	}
 
+	/// Close all active subscriptions of the connection `conn_id` as if the client had unsubscribed
+	/// and send `error` to the client as the last notification of each subscription.
+	///
+	/// Returns the number of subscriptions that were closed.
+	pub async fn close_subscriptions(&self, conn_id: ConnectionId, error: SubscriptionError) -> usize {
+		let mut closed = Vec::new();
+
+		for sub in self.subscriptions.values() {
+			let mut subscribers = sub.subscribers.lock();
+			let keys: Vec<_> = subscribers.keys().filter(|k| k.conn_id == conn_id).cloned().collect();
+
+			for key in keys {
+				if let Some((sink, _)) = subscribers.remove(&key) {
+					closed.push((sink, key.sub_id, sub.notification));
+				}
+			}
+		}
+
+		let count = closed.len();
+
+		for (sink, sub_id, method) in closed {
+			let _ = sink.send(sub_err_to_json(error.clone(), sub_id, method)).await;
+		}
+
+		count
+	}
+
+	/// Close the subscription `sub_id` of the connection `conn_id` as if the client had unsubscribed
+	/// and send `error` to the client as the last notification of the subscription.
+	///
+	/// Returns `false` if no such subscription exists.
+	pub async fn close_subscription(
+		&self,
+		conn_id: ConnectionId,
+		sub_id: &RpcSubscriptionId<'_>,
+		error: SubscriptionError,
+	) -> bool {
+		let key = SubscriptionKey { conn_id, sub_id: sub_id.clone().into_owned() };
+
+		let closed = self
+			.subscriptions
+			.values()
+			.find_map(|sub| sub.subscribers.lock().remove(&key).map(|(sink, _)| (sink, sub.notification)));
+
+		let Some((sink, method)) = closed else {
+			return false;
+		};
+
+		let _ = sink.send(sub_err_to_json(error, key.sub_id, method)).await;
+
+		true
+	}
+
 	/// Helper to call a method on the `RPC module` without having to spin up a server.
 	///
 	/// The params must be serializable as JSON array, see [`ToRpcParams`] for further documentation.
@@ -384,8 +506,12 @@ impl Methods {
 				(cb)(id.into_owned(), params.into_owned(), conn_id, max_response_size, ext).await
 			}
 			Some(MethodCallback::Subscription(cb)) => {
-				let conn_state =
-					SubscriptionState { conn_id, id_provider: &RandomIntegerIdProvider, subscription_permit };
+				let conn_state = SubscriptionState {
+					conn_id,
+					id_provider: &RandomIntegerIdProvider,
+					subscription_permit,
+					observer: None,
+				};
 				let res = (cb)(id, params, MethodSink::new(tx.clone()), conn_state, ext).await;
 
 				// This message is not used because it's used for metrics so we discard in other to
@@ -597,6 +723,12 @@ impl<Context: Send + Sync + 'static> RpcModule<Context> {
 	/// Be aware that a subscription consist of two methods, `subscribe` and `unsubscribe` and
 	/// it's the caller responsibility to remove both `subscribe` and `unsubscribe` methods for subscriptions.
 	pub fn remove_method(&mut self, method_name: &'static str) -> Option<MethodCallback> {
+		if self.methods.deprecated.contains_key(method_name) {
+			Arc::make_mut(&mut self.methods.deprecated).remove(method_name);
+		}
+		if self.methods.meta.contains_key(method_name) {
+			Arc::make_mut(&mut self.methods.meta).remove(method_name);
+		}
 		self.methods.mut_callbacks().remove(method_name)
 	}
 
@@ -640,6 +772,58 @@ impl<Context: Send + Sync + 'static> RpcModule<Context> {
 		)
 	}
 
+	/// Register a new asynchronous RPC method whose arguments are [`FromRequest`](super::FromRequest) extractors.
+	///
+	/// The arguments are extracted before the handler is called and if any of them fails
+	/// the call is answered with the error of the extractor, see [`FromRequest`](super::FromRequest).
+	///
+	/// ## Examples
+	///
+	/// ```
+	/// use jsonrpsee_core::server::{ConnectionId, Ctx, Named, Positional, RpcModule};
+	///
+	/// #[derive(serde::Deserialize)]
+	/// struct Greet {
+	///     name: String,
+	/// }
+	///
+	/// let mut module = RpcModule::new(String::from("hello"));
+	/// module.register_handler("add", |Positional((a, b)): Positional<(u64, u64)>| async move { a + b }).unwrap();
+	/// module
+	///     .register_handler("greet", |Named(greet): Named<Greet>, Ctx(ctx): Ctx<String>, conn_id: ConnectionId| async move {
+	///         format!("{ctx} {} on connection {conn_id:?}", greet.name)
+	///     })
+	///     .unwrap();
+	/// ```
+	pub fn register_handler<H, Args>(
+		&mut self,
+		method_name: &'static str,
+		handler: H,
+	) -> Result<&mut MethodCallback, RegisterMethodError>
+	where
+		H: Handler<Context, Args>,
+	{
+		let ctx = self.ctx.clone();
+		self.methods.verify_and_insert(
+			method_name,
+			MethodCallback::Async(Arc::new(move |id, params, conn_id, max_response_size, extensions| {
+				let parts = RequestParts { params: &params, ctx: &ctx, conn_id, extensions: &extensions };
+
+				match handler.call(&parts) {
+					Ok(future) => async move {
+						let rp = future.await.into_response();
+						MethodResponse::response(id, rp, max_response_size).with_extensions(extensions)
+					}
+					.boxed(),
+					Err(err) => {
+						let rp = MethodResponse::error(id, err).with_extensions(extensions);
+						async move { rp }.boxed()
+					}
+				}
+			})),
+		)
+	}
+
 	/// Register a new **blocking** synchronous RPC method, which computes the response with the given callback.
 	/// Unlike the regular [`register_method`](RpcModule::register_method), this method can block its thread and perform
 	/// expensive computations.
@@ -794,7 +978,8 @@ impl<Context: Send + Sync + 'static> RpcModule<Context> {
 		Fut: Future<Output = R> + Send + 'static,
 		R: IntoSubscriptionCloseResponse + Send,
 	{
-		let subscribers = self.verify_and_register_unsubscribe(subscribe_method_name, unsubscribe_method_name)?;
+		let subscribers =
+			self.verify_and_register_unsubscribe(subscribe_method_name, notif_method_name, unsubscribe_method_name)?;
 		let ctx = self.ctx.clone();
 
 		// Subscribe
@@ -811,6 +996,8 @@ impl<Context: Send + Sync + 'static> RpcModule<Context> {
 					let sub_id = uniq_sub.sub_id.clone();
 					let method = notif_method_name;
 
+					let pending_overflow = PendingOverflow::default();
+
 					let sink = PendingSubscriptionSink {
 						inner: method_sink.clone(),
 						method: notif_method_name,
@@ -819,6 +1006,8 @@ impl<Context: Send + Sync + 'static> RpcModule<Context> {
 						id: id.clone().into_owned(),
 						subscribe: tx,
 						permit: conn.subscription_permit,
+						observer: conn.observer,
+						pending_overflow: pending_overflow.clone(),
 					};
 
 					// The subscription callback is a future from the subscription
@@ -838,6 +1027,15 @@ impl<Context: Send + Sync + 'static> RpcModule<Context> {
 							Err(_) => return,
 						};
 
+						// Messages queued by the overflow policy must be sent before the close notification.
+						let pending = pending_overflow.lock().take();
+						if let Some(mut pending) = pending {
+							tokio::select! {
+								_ = pending.wait_for(|n| *n == 0) => (),
+								_ = method_sink.closed() => return,
+							}
+						}
+
 						match response {
 							SubscriptionCloseResponse::Notif(msg) => {
 								let json = sub_message_to_json(msg, &sub_id, method);
@@ -932,7 +1130,8 @@ impl<Context: Send + Sync + 'static> RpcModule<Context> {
 		F: (Fn(Params, PendingSubscriptionSink, Arc<Context>, &Extensions) -> R) + Send + Sync + Clone + 'static,
 		R: IntoSubscriptionCloseResponse,
 	{
-		let subscribers = self.verify_and_register_unsubscribe(subscribe_method_name, unsubscribe_method_name)?;
+		let subscribers =
+			self.verify_and_register_unsubscribe(subscribe_method_name, notif_method_name, unsubscribe_method_name)?;
 		let ctx = self.ctx.clone();
 
 		// Subscribe
@@ -953,6 +1152,8 @@ impl<Context: Send + Sync + 'static> RpcModule<Context> {
 						id: id.clone().into_owned(),
 						subscribe: tx,
 						permit: conn.subscription_permit,
+						observer: conn.observer,
+						pending_overflow: Default::default(),
 					};
 
 					callback(params, sink, ctx.clone(), &extensions);
@@ -979,6 +1180,7 @@ impl<Context: Send + Sync + 'static> RpcModule<Context> {
 	fn verify_and_register_unsubscribe(
 		&mut self,
 		subscribe_method_name: &'static str,
+		notif_method_name: &'static str,
 		unsubscribe_method_name: &'static str,
 	) -> Result<Subscribers, RegisterMethodError> {
 		if subscribe_method_name == unsubscribe_method_name {
@@ -990,6 +1192,11 @@ impl<Context: Send + Sync + 'static> RpcModule<Context> {
 
 		let subscribers = Subscribers::default();
 
+		Arc::make_mut(&mut self.methods.subscriptions).insert(
+			subscribe_method_name,
+			RegisteredSubscription { notification: notif_method_name, subscribers: subscribers.clone() },
+		);
+
 		// Unsubscribe
 		{
 			let subscribers = subscribers.clone();
@@ -1047,6 +1254,10 @@ impl<Context: Send + Sync + 'static> RpcModule<Context> {
 
 		self.methods.mut_callbacks().insert(alias, callback);
 
+		if let Some(sub) = self.methods.subscriptions.get(existing_method).cloned() {
+			Arc::make_mut(&mut self.methods.subscriptions).insert(alias, sub);
+		}
+
 		Ok(())
 	}
 }
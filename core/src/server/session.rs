@@ -0,0 +1,97 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Per-connection session state.
+
+use std::sync::Arc;
+
+use http::Extensions;
+use parking_lot::Mutex;
+
+/// Typed state that belongs to a single connection and survives between calls
+/// on the same connection such as a login, a selected network or negotiated features.
+///
+/// The server creates the session when the connection is established and inserts it
+/// into the [`Extensions`] of every request on that connection. It's dropped with the connection.
+///
+/// - Method and subscription handlers can get it via [`Session::from_extensions`].
+/// - RPC middleware can get it via `request.extensions().get::<Session>()`.
+/// - The `#[rpc]` macro injects it into methods annotated with `with_session`.
+///
+/// Each value is identified by its type, so it's recommended to use a new type per value.
+/// The session is cheap to clone and all clones refer to the same state.
+#[derive(Debug, Clone, Default)]
+pub struct Session(Arc<Mutex<Extensions>>);
+
+impl Session {
+	/// Create a new empty session.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Get the session of the connection from the extensions of a request.
+	///
+	/// If there is no session, for instance if the method was called directly on the [`crate::server::RpcModule`],
+	/// a new empty session is returned that isn't attached to any connection.
+	pub fn from_extensions(ext: &Extensions) -> Self {
+		ext.get::<Session>().cloned().unwrap_or_default()
+	}
+
+	/// Insert a value into the session and returns the previous value of the same type.
+	pub fn insert<T: Clone + Send + Sync + 'static>(&self, value: T) -> Option<T> {
+		self.0.lock().insert(value)
+	}
+
+	/// Get a clone of the value of type `T`.
+	pub fn get<T: Clone + Send + Sync + 'static>(&self) -> Option<T> {
+		self.0.lock().get::<T>().cloned()
+	}
+
+	/// Returns whether the session has a value of type `T`.
+	pub fn contains<T: Send + Sync + 'static>(&self) -> bool {
+		self.0.lock().get::<T>().is_some()
+	}
+
+	/// Remove the value of type `T` from the session.
+	pub fn remove<T: Send + Sync + 'static>(&self) -> Option<T> {
+		self.0.lock().remove::<T>()
+	}
+
+	/// Update the value of type `T` in place, inserting `T::default()` if it doesn't exist.
+	///
+	/// The session is locked while `f` runs, so it must not access the session itself.
+	pub fn update<T, R>(&self, f: impl FnOnce(&mut T) -> R) -> R
+	where
+		T: Clone + Default + Send + Sync + 'static,
+	{
+		f(self.0.lock().get_or_insert_default::<T>())
+	}
+
+	/// Remove all values from the session.
+	pub fn clear(&self) {
+		self.0.lock().clear();
+	}
+}
@@ -32,6 +32,7 @@ use crate::server::LOG_TARGET;
 use crate::server::error::{DisconnectError, PendingSubscriptionAcceptError, SendTimeoutError, TrySendError};
 use crate::server::rpc_module::ConnectionId;
 use crate::{error::SubscriptionError, traits::IdProvider};
+use futures_util::{Stream, StreamExt};
 use jsonrpsee_types::SubscriptionPayload;
 use jsonrpsee_types::response::SubscriptionPayloadError;
 use jsonrpsee_types::{ErrorObjectOwned, Id, SubscriptionId, SubscriptionResponse};
@@ -39,14 +40,123 @@ use parking_lot::Mutex;
 use rustc_hash::FxHashMap;
 use serde::{Serialize, de::DeserializeOwned};
 use serde_json::value::RawValue;
+use std::collections::VecDeque;
 use std::{sync::Arc, time::Duration};
-use tokio::sync::{OwnedSemaphorePermit, Semaphore, mpsc, oneshot};
+use tokio::sync::{Notify, OwnedSemaphorePermit, Semaphore, mpsc, oneshot, watch};
 
 /// Type-alias for subscribers.
 pub type Subscribers = Arc<Mutex<FxHashMap<SubscriptionKey, (MethodSink, mpsc::Receiver<()>)>>>;
 /// Subscription permit.
 pub type SubscriptionPermit = OwnedSemaphorePermit;
 
+/// Observer of the subscriptions opened by a single subscription call, see [`SubscriptionState::observer`].
+pub trait SubscriptionObserver: Send + Sync + std::fmt::Debug {
+	/// The subscription was accepted.
+	fn on_open(&self, sub_id: &SubscriptionId<'static>);
+
+	/// The subscription that was accepted has been closed.
+	fn on_close(&self, sub_id: &SubscriptionId<'static>);
+}
+
+/// Holds the permit of an accepted subscription and notifies the observer once it's dropped.
+#[derive(Debug)]
+struct AcceptedPermit {
+	_permit: SubscriptionPermit,
+	observer: Option<(Arc<dyn SubscriptionObserver>, SubscriptionId<'static>)>,
+}
+
+impl Drop for AcceptedPermit {
+	fn drop(&mut self) {
+		if let Some((observer, sub_id)) = self.observer.take() {
+			observer.on_close(&sub_id);
+		}
+	}
+}
+
+/// Number of messages that are still queued by an [`OverflowPolicy`] which is
+/// awaited before the subscription close notification is sent.
+pub(crate) type PendingOverflow = Arc<Mutex<Option<watch::Receiver<usize>>>>;
+
+/// Decides what happens to a subscription message when the subscriber
+/// can't keep up and the send buffer is full.
+///
+/// The policy is chosen once when the subscription is accepted via
+/// [`PendingSubscriptionSink::accept_with_policy`] and applies to [`SubscriptionSink::send`],
+/// [`SubscriptionSink::send_timeout`] and [`SubscriptionSink::try_send`].
+///
+/// Dropped messages are counted and can be read via [`SubscriptionSink::dropped_items`]
+/// or awaited via [`SubscriptionSink::lagged`].
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+	/// Wait until there is capacity in the buffer.
+	///
+	/// This is the behaviour of [`PendingSubscriptionSink::accept`].
+	#[default]
+	Block,
+	/// Drop the message that is being sent if the buffer is full.
+	DropNewest,
+	/// Keep at most `n` messages in a subscription-local queue and drop the oldest
+	/// queued message when a new message arrives and the queue is full.
+	///
+	/// A value of zero is treated as one.
+	DropOldest(usize),
+	/// Only keep the latest message while the subscriber is busy, older
+	/// messages that haven't been sent yet are replaced.
+	ConflateLatest,
+	/// Close the subscription and send out a subscription error notification
+	/// once the buffer is full.
+	CloseWithLagError,
+}
+
+/// Configuration for driving a subscription from a [`Stream`], see [`PendingSubscriptionSink::pipe_from_stream`].
+#[derive(Debug)]
+pub struct PipeFromStreamConfig {
+	item_timeout: Option<Duration>,
+	overflow_policy: OverflowPolicy,
+	close_response: SubscriptionCloseResponse,
+}
+
+impl Default for PipeFromStreamConfig {
+	fn default() -> Self {
+		Self {
+			item_timeout: None,
+			overflow_policy: OverflowPolicy::Block,
+			close_response: SubscriptionCloseResponse::None,
+		}
+	}
+}
+
+impl PipeFromStreamConfig {
+	/// Create a new configuration with no item timeout, [`OverflowPolicy::Block`]
+	/// and no close notification.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Configure the max time to wait for each item to be sent.
+	///
+	/// If an item couldn't be sent in time the subscription is closed
+	/// with a subscription error notification.
+	///
+	/// Only has an effect with [`OverflowPolicy::Block`] because the other policies never wait.
+	pub fn item_timeout(mut self, timeout: Duration) -> Self {
+		self.item_timeout = Some(timeout);
+		self
+	}
+
+	/// Configure the overflow policy used when accepting the subscription.
+	pub fn overflow_policy(mut self, policy: OverflowPolicy) -> Self {
+		self.overflow_policy = policy;
+		self
+	}
+
+	/// Configure the notification that is sent when the stream has been exhausted.
+	pub fn close_response(mut self, response: impl IntoSubscriptionCloseResponse) -> Self {
+		self.close_response = response.into_response();
+		self
+	}
+}
+
 /// Convert something into a subscription close notification
 /// before a subscription is terminated.
 pub trait IntoSubscriptionCloseResponse {
@@ -209,6 +319,11 @@ pub struct PendingSubscriptionSink {
 	pub(crate) subscribe: oneshot::Sender<MethodResponse>,
 	/// Subscription permit.
 	pub(crate) permit: OwnedSemaphorePermit,
+	/// Observer of the subscription.
+	pub(crate) observer: Option<Arc<dyn SubscriptionObserver>>,
+	/// Messages queued by the overflow policy that must be sent before the subscription
+	/// close notification.
+	pub(crate) pending_overflow: PendingOverflow,
 }
 
 impl PendingSubscriptionSink {
@@ -232,6 +347,31 @@ impl PendingSubscriptionSink {
 	///
 	/// Panics if the subscription response exceeded the `max_response_size`.
 	pub async fn accept(self) -> Result<SubscriptionSink, PendingSubscriptionAcceptError> {
+		self.accept_with_policy(OverflowPolicy::Block).await
+	}
+
+	/// Accept the subscription and send out each item of `stream` as a subscription notification
+	/// until the stream is exhausted or the subscription is closed.
+	///
+	/// Returns the [`SubscriptionCloseResponse`] that should be returned from the subscription callback,
+	/// see [`SubscriptionSink::pipe_from_stream`] for further details.
+	pub async fn pipe_from_stream<S, T>(self, stream: S, config: PipeFromStreamConfig) -> SubscriptionCloseResponse
+	where
+		S: Stream<Item = T>,
+		T: Serialize,
+	{
+		match self.accept_with_policy(config.overflow_policy).await {
+			Ok(sink) => sink.pipe_from_stream(stream, config).await,
+			Err(_) => SubscriptionCloseResponse::None,
+		}
+	}
+
+	/// Same as [`PendingSubscriptionSink::accept`] but with an [`OverflowPolicy`] that decides
+	/// what happens to subscription messages when the subscriber can't keep up.
+	pub async fn accept_with_policy(
+		self,
+		policy: OverflowPolicy,
+	) -> Result<SubscriptionSink, PendingSubscriptionAcceptError> {
 		let response = MethodResponse::subscription_response(
 			self.id,
 			ResponsePayload::success_borrowed(&self.uniq_sub.sub_id),
@@ -251,13 +391,39 @@ impl PendingSubscriptionSink {
 		if success {
 			let (tx, rx) = mpsc::channel(1);
 			self.subscribers.lock().insert(self.uniq_sub.clone(), (self.inner.clone(), rx));
+
+			let inner = self.inner.for_subscription();
+			let unsubscribe = IsUnsubscribed(tx);
+			let (dropped, _) = watch::channel(0);
+
+			let queue = match policy {
+				OverflowPolicy::DropOldest(_) | OverflowPolicy::ConflateLatest => {
+					let (queue, pending) = OverflowQueue::spawn(inner.clone(), unsubscribe.clone());
+					*self.pending_overflow.lock() = Some(pending);
+					Some(queue)
+				}
+				_ => None,
+			};
+
+			let permit = AcceptedPermit {
+				_permit: self.permit,
+				observer: self.observer.map(|observer| {
+					let sub_id = self.uniq_sub.sub_id.clone();
+					observer.on_open(&sub_id);
+					(observer, sub_id)
+				}),
+			};
+
 			Ok(SubscriptionSink {
-				inner: self.inner,
+				inner,
 				method: self.method,
 				subscribers: self.subscribers,
 				uniq_sub: self.uniq_sub,
-				unsubscribe: IsUnsubscribed(tx),
-				_permit: Arc::new(self.permit),
+				unsubscribe,
+				policy,
+				dropped: Arc::new(dropped),
+				queue,
+				_permit: Arc::new(permit),
 			})
 		} else {
 			panic!(
@@ -305,8 +471,14 @@ pub struct SubscriptionSink {
 	uniq_sub: SubscriptionKey,
 	/// A future to that fires once the unsubscribe method has been called.
 	unsubscribe: IsUnsubscribed,
+	/// What to do when the subscriber can't keep up.
+	policy: OverflowPolicy,
+	/// Number of messages dropped by the overflow policy.
+	dropped: Arc<watch::Sender<u64>>,
+	/// Subscription-local queue used by [`OverflowPolicy::DropOldest`] and [`OverflowPolicy::ConflateLatest`].
+	queue: Option<Arc<OverflowQueue>>,
 	/// Subscription permit
-	_permit: Arc<SubscriptionPermit>,
+	_permit: Arc<AcceptedPermit>,
 }
 
 impl SubscriptionSink {
@@ -344,7 +516,11 @@ impl SubscriptionSink {
 		}
 
 		let json = sub_message_to_json(msg, &self.uniq_sub.sub_id, self.method);
-		self.inner.send(json).await
+
+		match self.policy {
+			OverflowPolicy::Block => self.inner.send(json).await,
+			_ => self.send_with_policy(json).map_err(|e| DisconnectError(e.into_message())),
+		}
 	}
 
 	/// Similar to `SubscriptionSink::send` but only waits for a limited time.
@@ -361,7 +537,11 @@ impl SubscriptionSink {
 		}
 
 		let json = sub_message_to_json(msg, &self.uniq_sub.sub_id, self.method);
-		self.inner.send_timeout(json, timeout).await
+
+		match self.policy {
+			OverflowPolicy::Block => self.inner.send_timeout(json, timeout).await,
+			_ => self.send_with_policy(json).map_err(|e| SendTimeoutError::Closed(e.into_message())),
+		}
 	}
 
 	/// Attempts to immediately send out the message as JSON string to the subscribers but fails if the
@@ -379,7 +559,160 @@ impl SubscriptionSink {
 		}
 
 		let json = sub_message_to_json(msg, &self.uniq_sub.sub_id, self.method);
-		self.inner.try_send(json)
+
+		match self.policy {
+			OverflowPolicy::Block => self.inner.try_send(json),
+			_ => self.send_with_policy(json),
+		}
+	}
+
+	/// Get the overflow policy of the subscription.
+	pub fn overflow_policy(&self) -> OverflowPolicy {
+		self.policy
+	}
+
+	/// Get the number of messages that have been dropped by the [`OverflowPolicy`]
+	/// because the subscriber couldn't keep up.
+	pub fn dropped_items(&self) -> u64 {
+		*self.dropped.borrow()
+	}
+
+	/// Completes when the [`OverflowPolicy`] drops messages and returns the total
+	/// number of messages dropped so far.
+	///
+	/// # Cancel safety
+	///
+	/// This method is cancel safe.
+	pub async fn lagged(&self) -> u64 {
+		let mut rx = self.dropped.subscribe();
+		// The sender is kept alive by `self`.
+		let _ = rx.changed().await;
+		*rx.borrow()
+	}
+
+	/// Send out an already serialized `result` as a subscription notification.
+	pub(crate) async fn send_result(&self, result: &RawValue) -> Result<(), DisconnectError> {
+		let json = serde_json::value::to_raw_value(&SubscriptionResponse::new(
+			self.method.into(),
+			SubscriptionPayload { subscription: self.uniq_sub.sub_id.clone(), result },
+		))
+		.expect("Serialize infallible; qed");
+
+		self.send(SubscriptionMessage::from_complete_message(json)).await
+	}
+
+	/// Send out each item of `stream` as a subscription notification until the stream
+	/// is exhausted or the subscription is closed.
+	///
+	/// Returns
+	/// - the configured close response if the stream was exhausted.
+	/// - [`SubscriptionCloseResponse::NotifErr`] if an item couldn't be serialized or the item timeout elapsed.
+	/// - [`SubscriptionCloseResponse::None`] if the subscription or connection was closed.
+	///
+	/// The [`PipeFromStreamConfig::overflow_policy`] is ignored here, it's only used when accepting the subscription.
+	pub async fn pipe_from_stream<S, T>(&self, stream: S, config: PipeFromStreamConfig) -> SubscriptionCloseResponse
+	where
+		S: Stream<Item = T>,
+		T: Serialize,
+	{
+		let PipeFromStreamConfig { item_timeout, close_response, .. } = config;
+		let mut stream = std::pin::pin!(stream);
+
+		loop {
+			let item = tokio::select! {
+				_ = self.closed() => return SubscriptionCloseResponse::None,
+				item = stream.next() => item,
+			};
+
+			let Some(item) = item else {
+				return close_response;
+			};
+
+			let msg = match SubscriptionMessage::new(self.method, self.subscription_id(), &item) {
+				Ok(msg) => msg,
+				Err(e) => return SubscriptionCloseResponse::NotifErr(e.into()),
+			};
+
+			let res = match item_timeout {
+				Some(timeout) => self.send_timeout(msg, timeout).await,
+				None => self.send(msg).await.map_err(|e| SendTimeoutError::Closed(e.0)),
+			};
+
+			match res {
+				Ok(()) => (),
+				Err(SendTimeoutError::Closed(_)) => return SubscriptionCloseResponse::None,
+				Err(SendTimeoutError::Timeout(_)) => {
+					let timeout = item_timeout.expect("Timeout only occurs if configured; qed");
+					return SubscriptionCloseResponse::NotifErr(
+						format!("Subscription item could not be sent within {timeout:?}").into(),
+					);
+				}
+			}
+		}
+	}
+
+	/// Send a message according to the overflow policy without waiting for capacity.
+	///
+	/// Only `TrySendError::Closed` is returned.
+	fn send_with_policy(&self, json: Box<RawValue>) -> Result<(), TrySendError> {
+		match self.policy {
+			OverflowPolicy::Block => unreachable!("Block doesn't use the overflow policy; qed"),
+			OverflowPolicy::DropNewest => match self.inner.clone().try_send(json) {
+				Err(TrySendError::Full(_)) => {
+					self.record_dropped(1);
+					Ok(())
+				}
+				res => res,
+			},
+			OverflowPolicy::CloseWithLagError => match self.inner.clone().try_send(json) {
+				Err(TrySendError::Full(msg)) => {
+					self.record_dropped(1);
+					self.close_lagged();
+					Err(TrySendError::Closed(msg))
+				}
+				res => res,
+			},
+			OverflowPolicy::DropOldest(_) | OverflowPolicy::ConflateLatest => {
+				let queue = self.queue.as_ref().expect("Queue is created for queueing policies; qed");
+				let limit = match self.policy {
+					OverflowPolicy::DropOldest(n) => n.max(1),
+					_ => 1,
+				};
+				let dropped = queue.push(json, limit);
+				if dropped > 0 {
+					self.record_dropped(dropped);
+				}
+				Ok(())
+			}
+		}
+	}
+
+	fn record_dropped(&self, n: u64) {
+		self.dropped.send_modify(|dropped| *dropped += n);
+	}
+
+	/// Unsubscribe and send out a subscription error notification once there is capacity.
+	fn close_lagged(&self) {
+		if self.subscribers.lock().remove(&self.uniq_sub).is_none() {
+			return;
+		}
+
+		tracing::debug!(
+			target: LOG_TARGET,
+			"Subscription {:?} closed; the subscriber couldn't keep up",
+			self.uniq_sub.sub_id
+		);
+
+		let err = SubscriptionError::from(format!(
+			"Subscription closed because the subscriber couldn't keep up; dropped {} message(s)",
+			self.dropped_items()
+		));
+		let json = sub_err_to_json(err, self.uniq_sub.sub_id.clone(), self.method);
+		let sink = self.inner.clone();
+
+		tokio::spawn(async move {
+			let _ = sink.send(json).await;
+		});
 	}
 
 	/// Returns whether the subscription is closed.
@@ -419,6 +752,77 @@ impl Drop for SubscriptionSink {
 	}
 }
 
+/// Subscription-local queue which is drained into the connection by a background task.
+#[derive(Debug)]
+struct OverflowQueue {
+	items: Mutex<VecDeque<Box<RawValue>>>,
+	notify: Notify,
+	/// Number of queued messages including the one currently being sent.
+	///
+	/// Only modified while holding the lock of `items` such that it's consistent with the queue.
+	pending: watch::Sender<usize>,
+}
+
+impl OverflowQueue {
+	fn spawn(sink: MethodSink, unsubscribe: IsUnsubscribed) -> (Arc<Self>, watch::Receiver<usize>) {
+		let (pending, pending_rx) = watch::channel(0);
+		let queue = Arc::new(Self { items: Mutex::new(VecDeque::new()), notify: Notify::new(), pending });
+
+		tokio::spawn(queue.clone().run(sink, unsubscribe));
+
+		(queue, pending_rx)
+	}
+
+	/// Push a message to the queue and returns the number of dropped messages.
+	fn push(&self, json: Box<RawValue>, limit: usize) -> u64 {
+		let mut items = self.items.lock();
+		let mut dropped = 0;
+
+		while items.len() >= limit {
+			items.pop_front();
+			dropped += 1;
+		}
+		items.push_back(json);
+		self.pending.send_modify(|n| *n = *n + 1 - dropped);
+		drop(items);
+
+		self.notify.notify_one();
+
+		dropped as u64
+	}
+
+	/// Forward queued messages until the connection is closed or the
+	/// subscription is unsubscribed and the queue is empty.
+	async fn run(self: Arc<Self>, sink: MethodSink, unsubscribe: IsUnsubscribed) {
+		loop {
+			let notified = self.notify.notified();
+
+			let item = self.items.lock().pop_front();
+			let Some(json) = item else {
+				if unsubscribe.is_unsubscribed() {
+					break;
+				}
+
+				tokio::select! {
+					_ = notified => continue,
+					_ = unsubscribe.unsubscribed() => continue,
+					_ = sink.closed() => break,
+				}
+			};
+
+			if sink.send(json).await.is_err() {
+				break;
+			}
+
+			let items = self.items.lock();
+			self.pending.send_modify(|n| *n = n.saturating_sub(1));
+			drop(items);
+		}
+
+		self.pending.send_replace(0);
+	}
+}
+
 /// Wrapper struct that maintains a subscription "mainly" for testing.
 #[derive(Debug)]
 pub struct Subscription {
@@ -500,6 +904,8 @@ pub struct SubscriptionState<'a> {
 	pub id_provider: &'a dyn IdProvider,
 	/// Subscription limit
 	pub subscription_permit: SubscriptionPermit,
+	/// Observer which is notified once the subscription is accepted and closed.
+	pub observer: Option<Arc<dyn SubscriptionObserver>>,
 }
 
 pub(crate) fn sub_message_to_json(msg: SubscriptionMessage, sub_id: &SubscriptionId, method: &str) -> Box<RawValue> {
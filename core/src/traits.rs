@@ -95,6 +95,13 @@ impl ToRpcParams for serde_json::Map<String, serde_json::Value> {
 	to_rpc_params_impl!();
 }
 
+/// Already serialized params, which is useful to send the same params more than once.
+impl ToRpcParams for Option<Box<RawValue>> {
+	fn to_rpc_params(self) -> Result<Option<Box<RawValue>>, serde_json::Error> {
+		Ok(self)
+	}
+}
+
 impl<P: Serialize> ToRpcParams for &[P] {
 	to_rpc_params_impl!();
 }
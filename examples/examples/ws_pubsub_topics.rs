@@ -0,0 +1,106 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Example that shows how to publish to all subscriptions of a topic using `PubSubHub`
+//! where each item is only serialized once regardless of the number of subscribers.
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use futures::StreamExt;
+use futures::future;
+use jsonrpsee::core::client::{Subscription, SubscriptionClientT};
+use jsonrpsee::rpc_params;
+use jsonrpsee::server::{RpcModule, Server};
+use jsonrpsee::ws_client::WsClientBuilder;
+use jsonrpsee::{OverflowPolicy, PubSubHub};
+
+const NUM_SUBSCRIPTION_RESPONSES: usize = 5;
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+	tracing_subscriber::FmtSubscriber::builder()
+		.with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+		.try_init()
+		.expect("setting default subscriber failed");
+
+	let addr = run_server().await?;
+	let url = format!("ws://{}", addr);
+
+	let client1 = WsClientBuilder::default().build(&url).await?;
+	let client2 = WsClientBuilder::default().build(&url).await?;
+	let sub1: Subscription<u64> =
+		client1.subscribe("subscribe_topic", rpc_params!["newHeads"], "unsubscribe_topic").await?;
+	let sub2: Subscription<u64> =
+		client2.subscribe("subscribe_topic", rpc_params!["newHeads"], "unsubscribe_topic").await?;
+
+	let fut1 = sub1.take(NUM_SUBSCRIPTION_RESPONSES).for_each(|r| async move { tracing::info!("sub1 rx: {:?}", r) });
+	let fut2 = sub2.take(NUM_SUBSCRIPTION_RESPONSES).for_each(|r| async move { tracing::info!("sub2 rx: {:?}", r) });
+
+	future::join(fut1, fut2).await;
+
+	Ok(())
+}
+
+async fn run_server() -> anyhow::Result<SocketAddr> {
+	let server = Server::builder().build("127.0.0.1:0").await?;
+	let hub = PubSubHub::new();
+	let mut module = RpcModule::new(hub.clone());
+
+	module.register_subscription(
+		"subscribe_topic",
+		"s_topic",
+		"unsubscribe_topic",
+		|params, pending, hub, _| async move {
+			let topic: String = params.one()?;
+			// Slow subscribers only get the most recent items.
+			let sink = pending.accept_with_policy(OverflowPolicy::DropOldest(16)).await?;
+			hub.subscribe(topic, sink);
+			Ok(())
+		},
+	)?;
+
+	tokio::spawn(produce_items(hub));
+
+	let addr = server.local_addr()?;
+	let handle = server.start(module);
+
+	// In this example we don't care about doing shutdown so let's it run forever.
+	// You may use the `ServerHandle` to shut it down or manage it yourself.
+	tokio::spawn(handle.stopped());
+
+	Ok(addr)
+}
+
+// Publishes a new item every 100ms to all subscribers of the `newHeads` topic.
+async fn produce_items(hub: PubSubHub) {
+	let mut interval = tokio::time::interval(Duration::from_millis(100));
+
+	for block in 1.. {
+		interval.tick().await;
+		let _ = hub.publish("newHeads", &block).await;
+	}
+}
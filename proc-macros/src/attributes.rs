@@ -47,6 +47,15 @@ pub enum ParamKind {
 	Map,
 }
 
+/// How the version of a method is added to its name.
+#[derive(Debug, Clone, Copy)]
+pub enum VersionStyle {
+	/// `foo_v2`
+	Suffix,
+	/// `v2_foo` where the version is part of the namespace, `ns_v2_foo`.
+	Namespace,
+}
+
 pub struct NameMapping {
 	pub name: String,
 	pub mapped: Option<String>,
@@ -230,3 +239,74 @@ pub(crate) fn parse_param_kind(arg: Result<Argument, MissingArgument>) -> syn::R
 		ident => Err(Error::new(ident.span(), "param_kind must be either `map` or `array`")),
 	}
 }
+
+/// An entry of the `meta` argument of methods and subscriptions, `key = literal`
+/// or just `key` which is the same as `key = true`.
+#[derive(Debug, Clone)]
+pub struct MetaEntry {
+	pub key: syn::Ident,
+	pub value: MetaLit,
+}
+
+/// The literal value of a [`MetaEntry`].
+#[derive(Debug, Clone)]
+pub enum MetaLit {
+	Bool(bool),
+	Int(i64),
+	Float(f64),
+	Str(String),
+}
+
+impl Parse for MetaEntry {
+	fn parse(input: ParseStream) -> syn::Result<Self> {
+		let key: syn::Ident = input.parse()?;
+		if !input.peek(Token![=]) {
+			return Ok(MetaEntry { key, value: MetaLit::Bool(true) });
+		}
+		input.parse::<Token![=]>()?;
+
+		let negative = input.parse::<Option<Token![-]>>()?.is_some();
+		let lit: syn::Lit = input.parse()?;
+		let value = match &lit {
+			syn::Lit::Int(int) => {
+				let n = int.base10_parse::<i128>()?;
+				let n = if negative { -n } else { n };
+				MetaLit::Int(n.try_into().map_err(|_| Error::new(lit.span(), "Integer doesn't fit in an i64"))?)
+			}
+			syn::Lit::Float(float) => {
+				let f = float.base10_parse::<f64>()?;
+				MetaLit::Float(if negative { -f } else { f })
+			}
+			syn::Lit::Bool(b) if !negative => MetaLit::Bool(b.value),
+			syn::Lit::Str(s) if !negative => MetaLit::Str(s.value()),
+			_ => return Err(Error::new(lit.span(), "Expected a boolean, integer, float or string literal")),
+		};
+
+		Ok(MetaEntry { key, value })
+	}
+}
+
+pub(crate) fn parse_meta(arg: Result<Argument, MissingArgument>) -> syn::Result<Vec<MetaEntry>> {
+	let entries = optional(arg, Argument::group::<MetaEntry>)?.unwrap_or_default();
+
+	let mut meta: Vec<MetaEntry> = Vec::with_capacity(entries.len());
+	for entry in entries {
+		if meta.iter().any(|e| e.key == entry.key) {
+			return Err(Error::new(entry.key.span(), format!("Duplicate meta key `{}`", entry.key)));
+		}
+		meta.push(entry);
+	}
+
+	Ok(meta)
+}
+
+pub(crate) fn parse_version_style(arg: Result<Argument, MissingArgument>) -> syn::Result<VersionStyle> {
+	let style: Option<syn::Ident> = optional(arg, Argument::value)?;
+
+	match style {
+		None => Ok(VersionStyle::Suffix),
+		Some(ident) if ident == "suffix" => Ok(VersionStyle::Suffix),
+		Some(ident) if ident == "namespace" => Ok(VersionStyle::Namespace),
+		ident => Err(Error::new(ident.span(), "version_style must be either `suffix` or `namespace`")),
+	}
+}
@@ -42,6 +42,17 @@ pub(crate) fn find_jsonrpsee_server_crate() -> Result<proc_macro2::TokenStream,
 	find_jsonrpsee_crate(&["jsonrpsee-server"])
 }
 
+/// Search for `jsonrpsee-core` in `Cargo.toml`, either re-exported by `jsonrpsee` or as a direct dependency.
+pub(crate) fn find_jsonrpsee_core_crate() -> Result<proc_macro2::TokenStream, syn::Error> {
+	match crate_name("jsonrpsee") {
+		Ok(FoundCrate::Name(name)) => {
+			let ident = syn::Ident::new(&name, Span::call_site());
+			Ok(quote!(#ident::core))
+		}
+		_ => find_jsonrpsee_crate(&["jsonrpsee-core"]),
+	}
+}
+
 fn find_jsonrpsee_crate(crate_names: &[&str]) -> Result<proc_macro2::TokenStream, syn::Error> {
 	match crate_name("jsonrpsee") {
 		Ok(FoundCrate::Name(name)) => {
@@ -74,7 +85,8 @@ fn find_jsonrpsee_crate(crate_names: &[&str]) -> Result<proc_macro2::TokenStream
 	}
 }
 
-/// Traverses the RPC trait definition and applies the required bounds for the generic type parameters that are used.
+/// Traverses the RPC trait definition and applies the required bounds for the generic type parameters and the
+/// associated types, as `Self::Foo`, that are used.
 /// The bounds applied depend on whether the type parameter is used as a parameter, return value or subscription result
 /// and whether it's used in client or server mode.
 /// Type params get `Send + Sync + 'static` bounds and input/output parameters get `Serialize` and/or `DeserializeOwned`
@@ -119,28 +131,33 @@ pub(crate) fn generate_where_clause(
 		return bounds;
 	}
 
-	item_trait
-		.generics
-		.type_params()
-		.map(|ty| {
-			let ty_path = syn::TypePath { qself: None, path: ty.ident.clone().into() };
+	let type_params = item_trait.generics.type_params().map(|ty| (&ty.ident, syn::Path::from(ty.ident.clone())));
+	let assoc_types = assoc_types(item_trait).map(|assoc| {
+		let ident = &assoc.ident;
+		(ident, parse_quote!(Self::#ident))
+	});
+
+	type_params
+		.chain(assoc_types)
+		.map(|(ident, path)| {
+			let ty_path = syn::TypePath { qself: None, path };
 			let mut bounds: Punctuated<syn::TypeParamBound, Token![+]> = parse_quote!(Send + Sync + 'static);
 
 			if is_client {
-				if visitor.input_params.contains(&ty.ident) {
+				if visitor.input_params.contains(ident) {
 					bounds.push(parse_quote!(jsonrpsee::core::Serialize))
 				}
-				if visitor.ret_params.contains(&ty.ident) || visitor.sub_params.contains(&ty.ident) {
+				if visitor.ret_params.contains(ident) || visitor.sub_params.contains(ident) {
 					bounds.push(parse_quote!(jsonrpsee::core::DeserializeOwned))
 				}
 			} else {
-				if visitor.input_params.contains(&ty.ident) {
+				if visitor.input_params.contains(ident) {
 					bounds.push(parse_quote!(jsonrpsee::core::DeserializeOwned))
 				}
-				if visitor.ret_params.contains(&ty.ident) {
+				if visitor.ret_params.contains(ident) {
 					bounds.push(parse_quote!(std::clone::Clone))
 				}
-				if visitor.ret_params.contains(&ty.ident) || visitor.sub_params.contains(&ty.ident) {
+				if visitor.ret_params.contains(ident) || visitor.sub_params.contains(ident) {
 					bounds.push(parse_quote!(jsonrpsee::core::Serialize))
 				}
 			}
@@ -151,7 +168,7 @@ pub(crate) fn generate_where_clause(
 					if let syn::WherePredicate::Type(where_ty) = predicate {
 						if let syn::Type::Path(ref predicate) = where_ty.bounded_ty {
 							if *predicate == ty_path {
-								bounds.extend(where_ty.bounds.clone().into_iter());
+								bounds.extend(where_ty.bounds.clone());
 							}
 						}
 					}
@@ -172,12 +189,22 @@ pub(crate) fn generate_where_clause(
 /// needed for generating the `client` and `server` traits/implementations.
 fn visit_trait(item_trait: &syn::ItemTrait, sub_tys: &[syn::Type]) -> FindAllParams {
 	let type_params: HashSet<_> = item_trait.generics.type_params().map(|t| t.ident.clone()).collect();
-	let sub_tys = FindSubscriptionParams::new(type_params).visit(sub_tys);
+	let assoc_types: HashSet<_> = assoc_types(item_trait).map(|assoc| assoc.ident.clone()).collect();
+	let sub_tys = FindSubscriptionParams::new(type_params, assoc_types.clone()).visit(sub_tys);
 	let mut visitor = FindAllParams::new(sub_tys);
+	visitor.trait_generics.extend(assoc_types);
 	visitor.visit_item_trait(item_trait);
 	visitor
 }
 
+/// Returns the associated types of the trait.
+pub(crate) fn assoc_types(item_trait: &syn::ItemTrait) -> impl Iterator<Item = &syn::TraitItemType> {
+	item_trait.items.iter().filter_map(|item| match item {
+		syn::TraitItem::Type(assoc) => Some(assoc),
+		_ => None,
+	})
+}
+
 /// Checks whether provided type is an `Option<...>`.
 pub(crate) fn is_option(ty: &syn::Type) -> bool {
 	if let syn::Type::Path(path) = ty {
@@ -193,6 +220,34 @@ pub(crate) fn is_option(ty: &syn::Type) -> bool {
 	false
 }
 
+/// Returns the `Item` type if the provided return type is `impl Stream<Item = ...>`.
+pub(crate) fn stream_item(output: &syn::ReturnType) -> Option<syn::Type> {
+	let syn::ReturnType::Type(_, ty) = output else {
+		return None;
+	};
+	let syn::Type::ImplTrait(impl_trait) = &**ty else {
+		return None;
+	};
+
+	impl_trait.bounds.iter().find_map(|bound| {
+		let syn::TypeParamBound::Trait(bound) = bound else {
+			return None;
+		};
+		let seg = bound.path.segments.last()?;
+		if seg.ident != "Stream" {
+			return None;
+		}
+		let syn::PathArguments::AngleBracketed(args) = &seg.arguments else {
+			return None;
+		};
+
+		args.args.iter().find_map(|arg| match arg {
+			syn::GenericArgument::AssocType(assoc) if assoc.ident == "Item" => Some(assoc.ty.clone()),
+			_ => None,
+		})
+	})
+}
+
 /// Iterates over all Attribute's and parses only the attributes that are doc comments.
 ///
 /// Note that `doc comments` are expanded into `#[doc = "some comment"]`
@@ -208,6 +263,28 @@ pub(crate) fn extract_doc_comments(attrs: &[syn::Attribute]) -> TokenStream2 {
 	quote! ( #(#docs)* )
 }
 
+/// Returns the type of a successful response, `T` in `Result<T, E>`, `RpcResult<T>` or `ResponsePayload<'a, T>`.
+pub(crate) fn ok_type(ty: &syn::Type) -> Option<syn::Type> {
+	let syn::Type::Path(type_path) = ty else {
+		return None;
+	};
+	let segment = type_path.path.segments.last()?;
+	let syn::PathArguments::AngleBracketed(syn::AngleBracketedGenericArguments { args, .. }) = &segment.arguments
+	else {
+		return None;
+	};
+	let mut types = args.iter().filter_map(|arg| match arg {
+		syn::GenericArgument::Type(ty) => Some(ty.clone()),
+		_ => None,
+	});
+
+	if segment.ident == "Result" || segment.ident == "RpcResult" || segment.ident == "ResponsePayload" {
+		types.next()
+	} else {
+		None
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use super::is_option;
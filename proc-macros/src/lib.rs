@@ -35,7 +35,10 @@ use rpc_macro::RpcDescription;
 mod attributes;
 mod helpers;
 mod render_client;
+mod render_mock;
 mod render_server;
+mod render_typescript;
+mod rpc_error;
 mod rpc_macro;
 pub(crate) mod visitor;
 
@@ -58,6 +61,7 @@ pub(crate) mod visitor;
 /// - For subscription methods:
 ///   - There will be one additional argument inserted right after `&self`: `subscription_sink: SubscriptionSink`.
 ///     It should be used to accept or reject a subscription and send data back to the subscribers.
+///     Subscriptions returning `impl Stream` don't get this argument.
 ///   - The return type of the subscription method must implement `IntoSubscriptionCloseResponse`.
 ///
 /// Since this macro can generate up to two traits, both server and client traits will have
@@ -67,6 +71,10 @@ pub(crate) mod visitor;
 /// To use the `FooClient`, just import it in the context. To use the server, the `FooServer` trait must be implemented
 /// on your type first.
 ///
+/// The client also gets a `FooBatch` trait which inserts the methods of the trait into a typed batch created by
+/// `ClientT::typed_batch`, such that `client.typed_batch().foo(1).bar().send().await` returns a tuple with the
+/// typed result of each call.
+///
 /// Note: you need to import the `jsonrpsee` façade crate in your code for the macro to work properly.
 ///
 /// ## Prerequisites
@@ -153,17 +161,37 @@ pub(crate) mod visitor;
 ///   implementation.
 /// - `client_bounds`: replace *all* auto-generated trait bounds with the user-defined ones for the client
 ///   implementation.
+/// - `error`: the error type of the RPC API, such as `error = MyError`. The client methods return
+///   `Result<T, ClientError<MyError>>` where the JSON-RPC errors are decoded with `TryFrom<ErrorObjectOwned>`,
+///   see [`macro@RpcError`] to derive the conversions. Requires `client`.
+/// - `version_style`: how the `version` of a method is added to its name, either `suffix` (default) where method `foo`
+///   with version `2` is named `foo_v2`, or `namespace` where it's named `v2_foo` and with namespace `ns` `ns_v2_foo`,
+///   using the `namespace_separator`.
+/// - `mock`: generate a `<Trait>Mock` struct implementing the client traits for testing code which uses
+///   `<Trait>Client` without a server. Each method or subscription `foo` gets an `expect_foo` method setting the
+///   closure which answers its calls and a `foo_calls` method returning the arguments of the calls made so far,
+///   a call which isn't expected panics. `checkpoint` panics if an expected method was never called.
+///   The arguments and the type parameters of the trait must be owned and `Clone`. Requires `client`.
+/// - `typescript`: generate a `<trait>_typescript()` function, such as `foo_typescript` for `Foo`, returning the
+///   source of TypeScript bindings of the API, which a build script can write to a file. The bindings contain a
+///   `FooApi` interface and a `FooClient` class of the methods and subscriptions calling a user provided `Transport`.
+///   Rust types are mapped to their JSON representation, other types are referred to by name and must be provided
+///   by the user. Integers of 64 bits and more, which may not fit in a JavaScript `number`, are `number | bigint`.
 ///
 /// **Trait requirements:**
 ///
 /// A trait wrapped with the `rpc` attribute **must not**:
 ///
-/// - have associated types or constants;
+/// - have associated constants, or associated types with generics or a default;
 /// - have Rust methods not marked with either the `method` or `subscription` attribute;
 /// - be empty.
 ///
 /// At least one of the `server` or `client` flags must be provided, otherwise the compilation will err.
 ///
+/// Associated types, such as `type Hash;`, are kept by the `<Trait>Server` trait and become type parameters
+/// of the `<Trait>Client` trait appended to the generics of the trait, where `Self::Hash` is replaced by `Hash`.
+/// Their bounds are inferred like the bounds of the type parameters.
+///
 /// ### `method` attribute
 ///
 /// `method` attribute is used to define an RPC method.
@@ -175,6 +203,20 @@ pub(crate) mod visitor;
 ///   Aliases are processed ignoring the namespace, so add the complete name, including the namespace.
 /// - `blocking`: when set method execution will always spawn on a dedicated thread. Only usable with non-`async` methods.
 /// - `param_kind`: kind of structure to use for parameter passing. Can be "array" or "map", defaults to "array".
+/// - `idempotent`: mark the method as safe to retry. The client has a constant `<TRAIT>_IDEMPOTENT_METHODS`
+///   with the names and aliases of all idempotent methods, which can be provided to the client `RetryLayer`.
+///   The constant is only generated if at least one method is idempotent.
+/// - `with_extensions`: the server method gets the `Extensions` of the request as `ext: &Extensions` right after `&self`.
+/// - `with_session`: the server method gets the per-connection `Session` as `session: Session`
+///   right after `&self` or after `ext` if `with_extensions` is also used.
+/// - `version`: the version of the method which is added to its name according to the `version_style` of the trait,
+///   such that several versions of a method can coexist, for example `#[method(name = "foo", version = 2)]`.
+/// - `deny_unknown_params`: the server rejects calls with more params than the method has arguments in an array,
+///   or with unknown keys in a map, instead of ignoring them.
+/// - `meta`: metadata of the method as a list of `key = literal` pairs where the literal is a boolean, integer, float
+///   or string and a bare `key` means `key = true`, for example `meta(rate_limit = 10, auth = "admin")`.
+///   The server inserts it as `MethodMeta` into the extensions of each request to the method (and its aliases)
+///   before the RPC middleware runs.
 ///
 /// **Method requirements:**
 ///
@@ -182,7 +224,10 @@ pub(crate) mod visitor;
 ///
 /// - be either `async` or not;
 /// - have input parameters or not;
-/// - have a return value or not (in the latter case, it will be considered a notification method).
+/// - have a return value or not (in the latter case, it will be considered a notification method);
+/// - be `#[deprecated]`, in which case the client method is deprecated and the server registers the method
+///   as deprecated with `Methods::deprecate`, along with its aliases, which counts their calls and allows the server
+///   to reject them.
 ///
 /// ### `subscription` attribute
 ///
@@ -197,14 +242,27 @@ pub(crate) mod visitor;
 ///   so add the complete name, including the namespace.
 /// - `unsubscribe_aliases` (optional): Similar to `aliases` but for `unsubscribe`.
 /// - `item` (mandatory): type of items yielded by the subscription. Note that it must be the type, not string.
+///   May be omitted if the method returns `impl Stream<Item = T>`.
 /// - `param_kind`: kind of structure to use for parameter passing. Can be "array" or "map", defaults to "array".
+/// - `with_extensions`: the server method gets the `Extensions` of the request as `ext: &Extensions`
+///   right after the subscription sink.
+/// - `with_session`: the server method gets the per-connection `Session` as `session: Session`
+///   right after the subscription sink or after `ext` if `with_extensions` is also used.
+/// - `meta`: metadata of the subscription, same as for `method`. It is only attached to `name` and its aliases,
+///   not to `unsubscribe`.
+/// - `pipe_config` (optional): path to a function `fn() -> PipeFromStreamConfig` which configures how the stream
+///   is piped into the subscription, such as the item timeout or the overflow policy.
+///   Only supported if the method returns `impl Stream<Item = T>`, defaults to `PipeFromStreamConfig::default()`.
 ///
 /// **Method requirements:**
 ///
-/// Rust method marked with the `subscription` attribute **must**:
+/// Rust method marked with the `subscription` attribute **must** either:
 ///
-/// - be asynchronous;
-/// - return a type that implements `jsonrpsee::server::IntoSubscriptionCloseResponse`.
+/// - be asynchronous and return a type that implements `jsonrpsee::server::IntoSubscriptionCloseResponse`;
+/// - or return `impl Stream<Item = T> + Send` where `T: Serialize`. Such methods don't take the
+///   `PendingSubscriptionSink`, instead each item of the stream is sent out as a subscription notification
+///   until the stream is exhausted or the subscription is closed,
+///   see `PendingSubscriptionSink::pipe_from_stream` for further details.
 ///
 /// Rust method marked with `subscription` attribute **may**:
 ///
@@ -217,6 +275,10 @@ pub(crate) mod visitor;
 /// **Arguments:**
 ///
 /// - `rename`: rename the generated JSON key.
+/// - `default`: the argument may be omitted or be `null` in which case the server uses `Default::default()`,
+///   or the given expression with `default = expr`. For methods with such arguments the client gets a
+///   `<method>_builder` method which takes the other arguments and returns a builder where the arguments
+///   with a default value can be set before the call is sent with `send`.
 ///
 ///
 /// ## Full workflow example
@@ -433,3 +495,57 @@ fn rpc_impl(attr: syn::Attribute, item: TokenStream) -> Result<proc_macro2::Toke
 	let rpc = RpcDescription::from_item(attr, trait_data)?;
 	rpc.render()
 }
+
+/// Derive `From<E> for ErrorObjectOwned` and `TryFrom<ErrorObjectOwned> for E` for an error enum `E`
+/// so that it can be returned by the methods of a server and decoded by the clients generated by
+/// `#[rpc(client, error = E)]`.
+///
+/// Each variant is annotated with `#[rpc_error(code = .., message = "..", data = ..)]`:
+///
+/// - `code`: the JSON-RPC error code of the variant, an integer literal. The codes must be unique
+///   and must not be in the range `-32768..=-32000` reserved by the JSON-RPC specification (see
+///   `jsonrpsee::types::ErrorCode`), both are checked at compile time.
+/// - `message`: the message of the error which defaults to the name of the variant. The message is
+///   a template where the fields can be interpolated as `{name}` for named fields and `{0}` for unnamed fields,
+///   using the syntax of `format!`.
+/// - `data`: either `fields` (default) where the fields of the variant are encoded as the `data` of the error,
+///   a single unnamed field as is, several unnamed fields as an array and named fields as an object, or
+///   `none` where no `data` is sent and the fields are decoded with `Default::default()`.
+///   Converting the error to `ErrorObjectOwned` panics if the fields can't be serialized.
+///
+/// ```
+/// use jsonrpsee::proc_macros::RpcError;
+/// use jsonrpsee::types::ErrorObjectOwned;
+///
+/// #[derive(Debug, PartialEq, RpcError)]
+/// pub enum TransferError {
+///     #[rpc_error(code = 1000, message = "Unknown account")]
+///     UnknownAccount(String),
+///     #[rpc_error(code = 1001, message = "Insufficient funds")]
+///     InsufficientFunds { balance: u64, required: u64 },
+///     #[rpc_error(code = 1002)]
+///     Frozen,
+///     #[rpc_error(code = 1003, message = "Limit of {0} exceeded", data = none)]
+///     LimitExceeded(u64),
+/// }
+///
+/// let err: ErrorObjectOwned = TransferError::InsufficientFunds { balance: 1, required: 2 }.into();
+/// assert_eq!(err.code(), 1001);
+/// assert_eq!(err.message(), "Insufficient funds");
+/// assert_eq!(TransferError::try_from(err), Ok(TransferError::InsufficientFunds { balance: 1, required: 2 }));
+///
+/// let err: ErrorObjectOwned = TransferError::LimitExceeded(100).into();
+/// assert_eq!(err.message(), "Limit of 100 exceeded");
+/// assert!(err.data().is_none());
+/// assert_eq!(TransferError::try_from(err), Ok(TransferError::LimitExceeded(0)));
+/// ```
+#[proc_macro_derive(RpcError, attributes(rpc_error))]
+pub fn rpc_error(input: TokenStream) -> TokenStream {
+	let input = syn::parse_macro_input!(input as syn::DeriveInput);
+
+	match rpc_error::rpc_error_impl(input) {
+		Ok(tokens) => tokens,
+		Err(err) => err.to_compile_error(),
+	}
+	.into()
+}
@@ -24,7 +24,7 @@
 // IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 use crate::attributes::ParamKind;
-use crate::helpers::generate_where_clause;
+use crate::helpers::{generate_where_clause, ok_type};
 use crate::rpc_macro::{RpcDescription, RpcFnArg, RpcMethod, RpcSubscription};
 use proc_macro2::TokenStream as TokenStream2;
 use quote::{quote, quote_spanned};
@@ -49,6 +49,13 @@ impl RpcDescription {
 
 		let method_impls =
 			self.methods.iter().map(|method| self.render_method(method)).collect::<Result<Vec<_>, _>>()?;
+		let (builder_methods, builders): (Vec<_>, Vec<_>) = self
+			.methods
+			.iter()
+			.filter(|method| method.params.iter().any(|arg| arg.default().is_some()))
+			.map(|method| self.render_method_builder(method, &super_trait, &where_clause))
+			.unzip();
+		let batch = self.render_batch(&super_trait, &where_clause);
 		let sub_impls = self.subscriptions.iter().map(|sub| self.render_sub(sub)).collect::<Result<Vec<_>, _>>()?;
 
 		// Doc-comment to be associated with the client.
@@ -57,13 +64,49 @@ impl RpcDescription {
 			#[doc = #doc_comment]
 			pub trait #trait_name #impl_generics: #super_trait where #(#where_clause,)* {
 				#(#method_impls)*
+				#(#builder_methods)*
 				#(#sub_impls)*
 			}
 
 			impl<TypeJsonRpseeInternal #(,#type_idents)*> #trait_name #type_generics for TypeJsonRpseeInternal where TypeJsonRpseeInternal: #super_trait #(,#where_clause)* {}
+
+			#(#builders)*
+
+			#batch
 		};
 
-		Ok(trait_impl)
+		let idempotent_methods: Vec<_> = self
+			.methods
+			.iter()
+			.filter(|m| m.idempotent)
+			.flat_map(|m| std::iter::once(self.rpc_identifier(&m.name)).chain(m.aliases.iter().map(|a| a.into())))
+			.collect();
+
+		if idempotent_methods.is_empty() {
+			return Ok(trait_impl);
+		}
+
+		let const_name = quote::format_ident!(
+			"{}_IDEMPOTENT_METHODS",
+			heck::ToShoutySnakeCase::to_shouty_snake_case(self.trait_def.ident.to_string().as_str())
+		);
+		let const_doc = format!("Names of the idempotent methods in the `{}` RPC API.", &self.trait_def.ident);
+
+		Ok(quote! {
+			#trait_impl
+
+			#[doc = #const_doc]
+			pub const #const_name: &[&str] = &[#(#idempotent_methods),*];
+		})
+	}
+
+	/// The error type returned by the client, `ClientError<E>` if the RPC API declares
+	/// its own error type `E` otherwise `Error`.
+	fn client_error(&self) -> TokenStream2 {
+		match &self.error_ty {
+			Some(err) => self.jrps_client_item(quote! { core::client::ClientError<#err> }),
+			None => self.jrps_client_item(quote! { core::client::Error }),
+		}
 	}
 
 	/// Verify and rewrite the return type (for methods).
@@ -90,10 +133,9 @@ impl RpcDescription {
 				return quote_spanned!(args.span() => compile_error!("Result must be have two arguments"));
 			}
 
-			// Force the last argument to be `jsonrpsee::core::ClientError`:
+			// Force the last argument to be the client error:
 			let error_arg = args.last_mut().unwrap();
-			*error_arg =
-				syn::GenericArgument::Type(syn::Type::Verbatim(self.jrps_client_item(quote! { core::client::Error })));
+			*error_arg = syn::GenericArgument::Type(syn::Type::Verbatim(self.client_error()));
 
 			quote!(#ty)
 		} else if type_name.ident == "RpcResult" {
@@ -104,7 +146,7 @@ impl RpcDescription {
 
 			// The type alias `RpcResult<T>` is modified to `Result<T, Error>`.
 			let ret_ty = args.last_mut().unwrap();
-			let err_ty = self.jrps_client_item(quote! { core::client::Error });
+			let err_ty = self.client_error();
 
 			quote! { core::result::Result<#ret_ty, #err_ty> }
 		} else if type_name.ident == "ResponsePayload" {
@@ -115,7 +157,7 @@ impl RpcDescription {
 
 			// The type alias `RpcResult<T>` is modified to `Result<T, Error>`.
 			let ret_ty = args.last_mut().unwrap();
-			let err_ty = self.jrps_client_item(quote! { core::client::Error });
+			let err_ty = self.client_error();
 
 			quote! { core::result::Result<#ret_ty, #err_ty> }
 		} else {
@@ -132,30 +174,23 @@ impl RpcDescription {
 		// List of inputs to put into `Params` (e.g. `self.foo(<12, "baz">)`).
 		// Includes `&self` receiver.
 		let rust_method_params = &method.signature.sig.inputs;
-		// Name of the RPC method (e.g. `foo_makeSpam`).
-		let rpc_method_name = self.rpc_identifier(&method.name);
 
-		// Called method is either `request` or `notification`.
 		// `returns` represent the return type of the *rust method* (`Result<T, jsonrpsee::core::ClientError>`).
-		let (called_method, returns) = if let Some(returns) = &method.returns {
-			let called_method = quote::format_ident!("request");
+		let returns = if let Some(returns) = &method.returns {
 			let returns = self.return_result_type(returns.clone());
-			let returns = quote! { impl core::future::Future<Output = #returns> + Send };
-
-			(called_method, returns)
+			quote! { impl core::future::Future<Output = #returns> + Send }
 		} else {
-			let called_method = quote::format_ident!("notification");
-			let returns = quote! { impl core::future::Future<Output = Result<(), #jrps_error>> + Send };
-
-			(called_method, returns)
+			quote! { impl core::future::Future<Output = Result<(), #jrps_error>> + Send }
 		};
 
 		// Encoded parameters for the request.
-		let parameter_builder = self.encode_params(&method.params, &method.param_kind, &method.signature);
+		let parameter_builder = self.encode_params(&method.params, &method.param_kind, &method.signature, false);
 		// Doc-comment to be associated with the method.
 		let docs = &method.docs;
 		// Mark the method as deprecated, if previously declared as so.
 		let deprecated = &method.deprecated;
+		// Called method is either `request` or `notification`.
+		let call = self.render_call(method, quote!(self));
 
 		let method = quote! {
 			#docs
@@ -164,15 +199,211 @@ impl RpcDescription {
 			#[allow(clippy::used_underscore_binding)]
 			fn #rust_method_name(#rust_method_params) -> #returns {
 				let params = { #parameter_builder };
-				self.#called_method(#rpc_method_name, params)
+				#call
 			}
 		};
 		Ok(method)
 	}
 
-	fn render_sub(&self, sub: &RpcSubscription) -> Result<TokenStream2, syn::Error> {
-		// `jsonrpsee::core::ClientError`
+	/// Returns the future of the request or notification of the method sent by `client` with the encoded `params`.
+	fn render_call(&self, method: &RpcMethod, client: TokenStream2) -> TokenStream2 {
+		let rpc_method_name = self.rpc_identifier(&method.name);
+		let called_method = if method.returns.is_some() { quote!(request) } else { quote!(notification) };
+
+		// Errors are decoded as the error type of the RPC API if the method has a response.
+		if self.error_ty.is_some() && method.returns.is_some() {
+			quote! {
+				let fut = #client.#called_method(#rpc_method_name, params);
+				async move { fut.await.map_err(Into::into) }
+			}
+		} else {
+			quote! { #client.#called_method(#rpc_method_name, params) }
+		}
+	}
+
+	/// Renders the `<method>_builder` method of the client trait and the builder it returns, which
+	/// allows to omit the arguments with a default value.
+	fn render_method_builder(
+		&self,
+		method: &RpcMethod,
+		super_trait: &TokenStream2,
+		where_clause: &[syn::WherePredicate],
+	) -> (TokenStream2, TokenStream2) {
 		let jrps_error = self.jrps_client_item(quote! { core::client::Error });
+		let rust_method_name = &method.signature.sig.ident;
+		let builder_method_name = quote::format_ident!("{}_builder", rust_method_name);
+		let builder_name = quote::format_ident!(
+			"{}{}Builder",
+			self.trait_def.ident,
+			heck::ToUpperCamelCase::to_upper_camel_case(rust_method_name.to_string().as_str())
+		);
+		let type_idents = self.trait_def.generics.type_params().collect::<Vec<&TypeParam>>();
+		let type_names = type_idents.iter().map(|ty| &ty.ident).collect::<Vec<_>>();
+
+		let returns = match &method.returns {
+			Some(returns) => self.return_result_type(returns.clone()),
+			None => quote! { Result<(), #jrps_error> },
+		};
+
+		let (required, optional): (Vec<_>, Vec<_>) = method.params.iter().partition(|arg| arg.default().is_none());
+		let required_pats = required.iter().map(|arg| &arg.arg_pat().ident).collect::<Vec<_>>();
+		let required_tys = required.iter().map(|arg| arg.ty()).collect::<Vec<_>>();
+		let optional_pats = optional.iter().map(|arg| &arg.arg_pat().ident).collect::<Vec<_>>();
+		let optional_tys = optional.iter().map(|arg| arg.ty()).collect::<Vec<_>>();
+		let all_pats = method.params.iter().map(|arg| &arg.arg_pat().ident);
+		let setter_docs = optional.iter().map(|arg| {
+			let default = arg.default().unwrap();
+			format!("Set the `{}` argument, which is `{}` if it's not set.", arg.name(), quote!(#default))
+		});
+
+		let parameter_builder = self.encode_params(&method.params, &method.param_kind, &method.signature, true);
+		let call = self.render_call(method, quote!(client));
+
+		let method_doc = format!(
+			"Returns a builder of the `{}` call where the arguments with a default value may be omitted.",
+			self.rpc_identifier(&method.name)
+		);
+		let builder_doc =
+			format!("Builder of the `{}` call returned by `{builder_method_name}`.", self.rpc_identifier(&method.name));
+		let deprecated = &method.deprecated;
+
+		let builder_method = quote! {
+			#[doc = #method_doc]
+			#deprecated
+			#[allow(non_snake_case)]
+			#[allow(clippy::used_underscore_binding)]
+			fn #builder_method_name(&self, #(#required_pats: #required_tys),*) -> #builder_name<'_, Self #(,#type_names)*> {
+				#builder_name {
+					client: self,
+					#(#required_pats,)*
+					#(#optional_pats: None,)*
+					_marker: core::marker::PhantomData,
+				}
+			}
+		};
+
+		let builder = quote! {
+			#[doc = #builder_doc]
+			#[must_use = "the call is only sent when `send` is called"]
+			#[allow(non_snake_case)]
+			pub struct #builder_name<'a, TypeJsonRpseeInternal: ?Sized #(,#type_idents)*> {
+				client: &'a TypeJsonRpseeInternal,
+				#(#required_pats: #required_tys,)*
+				#(#optional_pats: Option<#optional_tys>,)*
+				_marker: core::marker::PhantomData<fn() -> (#(#type_names,)*)>,
+			}
+
+			impl<'a, TypeJsonRpseeInternal #(,#type_idents)*> #builder_name<'a, TypeJsonRpseeInternal #(,#type_names)*>
+			where
+				TypeJsonRpseeInternal: #super_trait + ?Sized
+				#(,#where_clause)*
+			{
+				#(
+					#[doc = #setter_docs]
+					pub fn #optional_pats(mut self, #optional_pats: #optional_tys) -> Self {
+						self.#optional_pats = Some(#optional_pats);
+						self
+					}
+				)*
+
+				/// Send the call.
+				#deprecated
+				#[allow(clippy::used_underscore_binding)]
+				pub fn send(self) -> impl core::future::Future<Output = #returns> + Send + 'a {
+					let Self { client, #(#all_pats,)* .. } = self;
+					let params = { #parameter_builder };
+					#call
+				}
+			}
+		};
+
+		(builder_method, builder)
+	}
+
+	/// Renders the `<TRAIT>Batch` trait which inserts the calls of the methods into a `TypedBatch`.
+	fn render_batch(&self, super_trait: &TokenStream2, where_clause: &[syn::WherePredicate]) -> TokenStream2 {
+		let typed_batch = self.jrps_client_item(quote! { core::client::TypedBatch });
+		let batch_push = self.jrps_client_item(quote! { core::client::BatchPush });
+		let trait_name = quote::format_ident!("{}Batch", &self.trait_def.ident);
+		let type_idents = self.trait_def.generics.type_params().collect::<Vec<&TypeParam>>();
+		let type_names = type_idents.iter().map(|ty| &ty.ident).collect::<Vec<_>>();
+
+		// Notifications have no response and can't be part of a typed batch.
+		let methods: Vec<_> =
+			self.methods.iter().filter_map(|method| Some((method, ok_type(method.returns.as_ref()?)?))).collect();
+
+		if methods.is_empty() {
+			return TokenStream2::new();
+		}
+
+		let (decls, impls): (Vec<_>, Vec<_>) = methods
+			.into_iter()
+			.map(|(method, ok_ty)| {
+				let rust_method_name = &method.signature.sig.ident;
+				let rpc_method_name = self.rpc_identifier(&method.name);
+				let docs = &method.docs;
+				let deprecated = &method.deprecated;
+				let idents = method.params.iter().map(|arg| &arg.arg_pat().ident).collect::<Vec<_>>();
+				let tys = method.params.iter().map(RpcFnArg::ty).collect::<Vec<_>>();
+				let parameter_builder =
+					self.encode_params(&method.params, &method.param_kind, &method.signature, false);
+
+				let returns = quote! {
+					#typed_batch<'a, TypeJsonRpseeInternal, <TypeJsonRpseeBatch as #batch_push<#ok_ty>>::Output>
+				};
+
+				let decl = quote! {
+					#docs
+					#deprecated
+					#[allow(non_snake_case)]
+					fn #rust_method_name(self, #(#idents: #tys),*) -> #returns
+					where
+						TypeJsonRpseeBatch: #batch_push<#ok_ty>;
+				};
+
+				let imp = quote! {
+					#[allow(clippy::used_underscore_binding)]
+					fn #rust_method_name(self, #(#idents: #tys),*) -> #returns
+					where
+						TypeJsonRpseeBatch: #batch_push<#ok_ty>,
+					{
+						let params = { #parameter_builder };
+						self.insert(#rpc_method_name, params)
+					}
+				};
+
+				(decl, imp)
+			})
+			.unzip();
+
+		let doc_comment = format!(
+			"Inserts the calls of the `{}` RPC API into a `TypedBatch`, see `ClientT::typed_batch`.",
+			&self.trait_def.ident
+		);
+
+		quote! {
+			#[doc = #doc_comment]
+			pub trait #trait_name<'a, TypeJsonRpseeInternal: ?Sized, TypeJsonRpseeBatch #(,#type_idents)*>
+			where
+				#(#where_clause,)*
+			{
+				#(#decls)*
+			}
+
+			impl<'a, TypeJsonRpseeInternal, TypeJsonRpseeBatch #(,#type_idents)*> #trait_name<'a, TypeJsonRpseeInternal, TypeJsonRpseeBatch #(,#type_names)*>
+				for #typed_batch<'a, TypeJsonRpseeInternal, TypeJsonRpseeBatch>
+			where
+				TypeJsonRpseeInternal: #super_trait + ?Sized
+				#(,#where_clause)*
+			{
+				#(#impls)*
+			}
+		}
+	}
+
+	fn render_sub(&self, sub: &RpcSubscription) -> Result<TokenStream2, syn::Error> {
+		// `jsonrpsee::core::ClientError` or `jsonrpsee::core::client::ClientError<E>`
+		let jrps_error = self.client_error();
 		// Rust method to invoke (e.g. `self.<foo>(...)`).
 		let rust_method_name = &sub.signature.sig.ident;
 		// List of inputs to put into `Params` (e.g. `self.foo(<12, "baz">)`).
@@ -189,7 +420,15 @@ impl RpcDescription {
 		let returns = quote! { impl core::future::Future<Output = Result<#sub_type<#item>, #jrps_error>> + Send };
 
 		// Encoded parameters for the request.
-		let parameter_builder = self.encode_params(&sub.params, &sub.param_kind, &sub.signature);
+		let parameter_builder = self.encode_params(&sub.params, &sub.param_kind, &sub.signature, false);
+		let subscribe = if self.error_ty.is_some() {
+			quote! {
+				let fut = self.subscribe(#rpc_sub_name, params, #rpc_unsub_name);
+				async move { fut.await.map_err(Into::into) }
+			}
+		} else {
+			quote! { self.subscribe(#rpc_sub_name, params, #rpc_unsub_name) }
+		};
 		// Doc-comment to be associated with the method.
 		let docs = &sub.docs;
 
@@ -199,13 +438,23 @@ impl RpcDescription {
 			#[allow(clippy::used_underscore_binding)]
 			fn #rust_method_name(#rust_method_params) -> #returns {
 				let params = #parameter_builder;
-				self.subscribe(#rpc_sub_name, params, #rpc_unsub_name)
+				#subscribe
 			}
 		};
 		Ok(method)
 	}
 
-	fn encode_params(&self, params: &[RpcFnArg], param_kind: &ParamKind, signature: &syn::TraitItemFn) -> TokenStream2 {
+	/// Encodes the arguments as params of the request.
+	///
+	/// If `builder` is set, the arguments with a default value are bound as `Option` which are
+	/// omitted from the object or sent as `null` in the array when they are not set.
+	fn encode_params(
+		&self,
+		params: &[RpcFnArg],
+		param_kind: &ParamKind,
+		signature: &syn::TraitItemFn,
+		builder: bool,
+	) -> TokenStream2 {
 		const ILLEGAL_PARAM_NAME: &str = "__RpcParams__";
 
 		let jsonrpsee = self.jsonrpsee_client_path.as_ref().unwrap();
@@ -235,7 +484,21 @@ impl RpcDescription {
 					// Throw away the type.
 					let value = arg.arg_pat();
 					let name = arg.name();
-					quote!(#name, #value)
+					let insert = quote! {
+						if let Err(err) = #p.insert(#name, #value) {
+							#reexports::panic_fail_serialize(stringify!(#name, #value), err);
+						}
+					};
+
+					if builder && arg.default().is_some() {
+						quote! {
+							if let Some(#value) = #value {
+								#insert
+							}
+						}
+					} else {
+						insert
+					}
 				});
 
 				// It's possible that the user has a parameter named `ILLEGAL_PARAM_NAME` in there API
@@ -248,11 +511,7 @@ impl RpcDescription {
 
 				quote!({
 					let mut #p = #jsonrpsee::core::params::ObjectParams::new();
-					#(
-						if let Err(err) = #p.insert(#params_insert) {
-							#reexports::panic_fail_serialize(stringify!(#params_insert), err);
-						}
-					)*
+					#(#params_insert)*
 					#p
 				})
 			}
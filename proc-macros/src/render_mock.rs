@@ -0,0 +1,378 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Renders the mock of an RPC API, enabled by `#[rpc(mock)]`.
+
+use crate::attributes::ParamKind;
+use crate::helpers::{is_option, ok_type};
+use crate::rpc_macro::{RpcDescription, RpcFnArg};
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+
+/// A method or a subscription answered by the mock.
+struct MockEntry<'a> {
+	rust_name: &'a syn::Ident,
+	rpc_name: String,
+	params: &'a [RpcFnArg],
+	param_kind: &'a ParamKind,
+	/// The value returned by the expectation of the entry.
+	ret: TokenStream2,
+}
+
+impl RpcDescription {
+	pub(super) fn render_mock(&self) -> Result<TokenStream2, syn::Error> {
+		let core = self.jrps_client_item(quote! { core });
+		let reexports = quote! { #core::__reexports };
+		let serde_json = quote! { #reexports::serde_json };
+		let mock_method = quote! { #core::client::mock::MockMethod };
+		let error_object = quote! { #reexports::jsonrpsee_types::ErrorObjectOwned };
+		let error = quote! { #core::client::Error };
+
+		let trait_ident = &self.trait_def.ident;
+		let mock_name = format_ident!("{}Mock", trait_ident);
+		let client_name = format!("{trait_ident}Client");
+		let (impl_generics, type_generics, _) = self.trait_def.generics.split_for_impl();
+		let type_params = self.trait_def.generics.type_params().map(|param| &param.ident);
+		let bounds = quote! {
+			#(#type_params: #reexports::serde::Serialize + #reexports::serde::de::DeserializeOwned + Clone + Send + Sync + 'static,)*
+		};
+
+		let methods: Vec<_> = self
+			.methods
+			.iter()
+			.map(|method| {
+				let ret = match &method.returns {
+					Some(ty) => {
+						let ty = ok_type(ty).unwrap_or_else(|| ty.clone());
+						quote! { #ty }
+					}
+					None => quote! { () },
+				};
+				MockEntry {
+					rust_name: &method.signature.sig.ident,
+					rpc_name: self.rpc_identifier(&method.name).into_owned(),
+					params: &method.params,
+					param_kind: &method.param_kind,
+					ret,
+				}
+			})
+			.collect();
+		let subscriptions: Vec<_> = self
+			.subscriptions
+			.iter()
+			.map(|sub| {
+				let item = &sub.item;
+				MockEntry {
+					rust_name: &sub.signature.sig.ident,
+					rpc_name: self.rpc_identifier(&sub.name).into_owned(),
+					params: &sub.params,
+					param_kind: &sub.param_kind,
+					ret: quote! { Vec<#item> },
+				}
+			})
+			.collect();
+		let entries = || methods.iter().chain(subscriptions.iter());
+
+		let fields = entries().map(|entry| {
+			let MockEntry { rust_name, ret, params, .. } = entry;
+			let tys = params.iter().map(RpcFnArg::ty);
+			quote! { #rust_name: #mock_method<(#(#tys,)*), #ret> }
+		});
+		let field_inits = entries().map(|MockEntry { rust_name, rpc_name, .. }| {
+			quote! { #rust_name: #mock_method::new(#rpc_name) }
+		});
+		let checkpoints = entries().map(|MockEntry { rust_name, .. }| quote! { self.#rust_name.checkpoint(); });
+
+		let setters = entries().map(|entry| {
+			let MockEntry { rust_name, rpc_name, params, ret, .. } = entry;
+			let expect = format_ident!("expect_{}", rust_name);
+			let calls = format_ident!("{}_calls", rust_name);
+			let idents: Vec<_> = params.iter().map(|arg| &arg.arg_pat().ident).collect();
+			let tys: Vec<_> = params.iter().map(RpcFnArg::ty).collect();
+			let expect_doc = format!("Expect calls of RPC method `{rpc_name}` which are answered by `handler`.");
+			let calls_doc = format!("The arguments of the calls of RPC method `{rpc_name}` made so far.");
+
+			quote! {
+				#[doc = #expect_doc]
+				pub fn #expect(
+					&self,
+					mut handler: impl FnMut(#(#tys),*) -> Result<#ret, #error_object> + Send + 'static,
+				) -> &Self {
+					self.#rust_name.expect(move |(#(#idents,)*)| handler(#(#idents),*));
+					self
+				}
+
+				#[doc = #calls_doc]
+				pub fn #calls(&self) -> Vec<(#(#tys,)*)> {
+					self.#rust_name.calls()
+				}
+			}
+		});
+
+		let dispatch_arms = |entries: &[MockEntry], encode: TokenStream2| {
+			let arms = entries.iter().map(|entry| {
+				let MockEntry { rust_name, rpc_name, .. } = entry;
+				let decode = self.render_mock_params_decoding(entry);
+				quote! {
+					#rpc_name => {
+						#decode
+						let res = self.#rust_name.call(args)?;
+						#encode
+					}
+				}
+			});
+			quote! { #(#arms)* }
+		};
+		let method_arms = dispatch_arms(&methods, quote! { Ok(#serde_json::to_value(res)?) });
+		let sub_arms = dispatch_arms(
+			&subscriptions,
+			quote! { res.iter().map(#serde_json::value::to_raw_value).collect::<Result<_, _>>().map_err(Into::into) },
+		);
+		let unknown_method = quote! {
+			panic!("RPC method `{}` is not part of the `{}` RPC API", method, stringify!(#trait_ident))
+		};
+
+		let subscription_impl = if subscriptions.is_empty() {
+			TokenStream2::new()
+		} else {
+			quote! {
+				impl #impl_generics #core::client::SubscriptionClientT for #mock_name #type_generics where #bounds {
+					fn subscribe<'a, Notif, Params>(
+						&self,
+						subscribe_method: &'a str,
+						params: Params,
+						_unsubscribe_method: &'a str,
+					) -> impl ::core::future::Future<Output = Result<#core::client::Subscription<Notif>, #error>> + Send
+					where
+						Params: #core::traits::ToRpcParams + Send,
+						Notif: #reexports::serde::de::DeserializeOwned,
+					{
+						let params = params.to_rpc_params();
+						async move {
+							let notifications = self.dispatch_subscription(subscribe_method, params?)?;
+							Ok(#core::client::mock::mock_subscription(subscribe_method, notifications))
+						}
+					}
+
+					fn subscribe_to_method<Notif>(
+						&self,
+						method: &str,
+					) -> impl ::core::future::Future<Output = Result<#core::client::Subscription<Notif>, #error>> + Send
+					where
+						Notif: #reexports::serde::de::DeserializeOwned,
+					{
+						let err = #error::Custom(format!("Method subscription `{method}` is not supported by the mock"));
+						async move { Err(err) }
+					}
+				}
+
+				impl #impl_generics #mock_name #type_generics where #bounds {
+					fn dispatch_subscription(
+						&self,
+						method: &str,
+						params: Option<Box<#serde_json::value::RawValue>>,
+					) -> Result<Vec<Box<#serde_json::value::RawValue>>, #error> {
+						let params = #reexports::jsonrpsee_types::Params::new(params.as_deref().map(|p| p.get()));
+						match method {
+							#sub_arms
+							_ => #unknown_method,
+						}
+					}
+				}
+			}
+		};
+
+		let methods_dispatch = if methods.is_empty() {
+			quote! {
+				let _ = params;
+				#unknown_method
+			}
+		} else {
+			quote! {
+				let params = #reexports::jsonrpsee_types::Params::new(params.as_deref().map(|p| p.get()));
+				match method {
+					#method_arms
+					_ => #unknown_method,
+				}
+			}
+		};
+
+		let doc = format!(
+			"Mock of the `{trait_ident}` RPC API which implements the `{client_name}` trait.\n\n\
+			Calls of the RPC methods must be expected with the `expect_*` methods of the mock."
+		);
+
+		Ok(quote! {
+			#[doc = #doc]
+			pub struct #mock_name #impl_generics {
+				#(#fields,)*
+			}
+
+			impl #impl_generics #mock_name #type_generics where #bounds {
+				/// Create a mock which doesn't expect any calls.
+				pub fn new() -> Self {
+					Self { #(#field_inits,)* }
+				}
+
+				#(#setters)*
+
+				/// Check that all the RPC methods which are expected to be called were called.
+				///
+				/// # Panics
+				///
+				/// Panics if an expected RPC method was never called.
+				pub fn checkpoint(&self) {
+					#(#checkpoints)*
+				}
+			}
+
+			impl #impl_generics Default for #mock_name #type_generics where #bounds {
+				fn default() -> Self {
+					Self::new()
+				}
+			}
+
+			impl #impl_generics #mock_name #type_generics where #bounds {
+				fn dispatch(
+					&self,
+					method: &str,
+					params: Option<Box<#serde_json::value::RawValue>>,
+				) -> Result<#serde_json::Value, #error> {
+					#methods_dispatch
+				}
+			}
+
+			impl #impl_generics #core::client::ClientT for #mock_name #type_generics where #bounds {
+				fn notification<Params>(
+					&self,
+					method: &str,
+					params: Params,
+				) -> impl ::core::future::Future<Output = Result<(), #error>> + Send
+				where
+					Params: #core::traits::ToRpcParams + Send,
+				{
+					let params = params.to_rpc_params();
+					async move { self.dispatch(method, params?).map(|_| ()) }
+				}
+
+				fn request<R, Params>(
+					&self,
+					method: &str,
+					params: Params,
+				) -> impl ::core::future::Future<Output = Result<R, #error>> + Send
+				where
+					R: #reexports::serde::de::DeserializeOwned,
+					Params: #core::traits::ToRpcParams + Send,
+				{
+					let params = params.to_rpc_params();
+					async move {
+						let value = self.dispatch(method, params?)?;
+						#serde_json::from_value(value).map_err(Into::into)
+					}
+				}
+
+				fn batch_request<'a, R>(
+					&self,
+					batch: #core::params::BatchRequestBuilder<'a>,
+				) -> impl ::core::future::Future<Output = Result<#core::client::BatchResponse<'a, R>, #error>> + Send
+				where
+					R: #reexports::serde::de::DeserializeOwned + ::core::fmt::Debug + 'a,
+				{
+					async move {
+						let batch = batch.build()?;
+						let mut responses = Vec::with_capacity(batch.len());
+						let mut failed_calls = 0;
+						for (method, params) in batch {
+							match self.dispatch(method, params) {
+								Ok(value) => responses.push(Ok(#serde_json::from_value(value)?)),
+								Err(#error::Call(err)) => {
+									failed_calls += 1;
+									responses.push(Err(err));
+								}
+								Err(err) => return Err(err),
+							}
+						}
+						let successful_calls = responses.len() - failed_calls;
+						Ok(#core::client::BatchResponse::new(successful_calls, responses, failed_calls))
+					}
+				}
+			}
+
+			#subscription_impl
+		})
+	}
+
+	/// Renders the decoding of the arguments of a call made by the client into `args`.
+	fn render_mock_params_decoding(&self, entry: &MockEntry) -> TokenStream2 {
+		let serde_json = self.jrps_client_item(quote! { core::__reexports::serde_json });
+		let params = entry.params;
+		if params.is_empty() {
+			return quote! { let args = (); };
+		}
+		let idents: Vec<_> = params.iter().map(|arg| &arg.arg_pat().ident).collect();
+
+		let decode = match entry.param_kind {
+			ParamKind::Array => {
+				let fields = params.iter().zip(&idents).map(|(arg, ident)| {
+					let ty = arg.ty();
+					match arg.default() {
+						Some(default) => quote! {
+							let #ident: #ty = seq.optional_next()?.unwrap_or_else(|| #default);
+						},
+						None if is_option(ty) => quote! { let #ident: #ty = seq.optional_next()?; },
+						None => quote! { let #ident: #ty = seq.next()?; },
+					}
+				});
+				quote! {
+					let mut seq = params.sequence();
+					#(#fields)*
+				}
+			}
+			ParamKind::Map => {
+				let fields = params.iter().zip(&idents).map(|(arg, ident)| {
+					let ty = arg.ty();
+					let name = arg.name();
+					let value = quote! { map.remove(#name).unwrap_or(#serde_json::Value::Null) };
+					match arg.default() {
+						Some(default) => quote! {
+							let #ident: #ty = #serde_json::from_value::<Option<#ty>>(#value)?.unwrap_or_else(|| #default);
+						},
+						None => quote! { let #ident: #ty = #serde_json::from_value(#value)?; },
+					}
+				});
+				quote! {
+					let mut map: #serde_json::Map<String, #serde_json::Value> = params.parse()?;
+					#(#fields)*
+				}
+			}
+		};
+
+		quote! {
+			#decode
+			let args = (#(#idents,)*);
+		}
+	}
+}
@@ -28,8 +28,9 @@ use std::collections::HashSet;
 use std::str::FromStr;
 
 use super::RpcDescription;
+use crate::attributes::{MetaEntry, MetaLit};
 use crate::{
-	helpers::{generate_where_clause, is_option},
+	helpers::{assoc_types, generate_where_clause, is_option},
 	rpc_macro::RpcFnArg,
 };
 use proc_macro2::{Span, TokenStream as TokenStream2};
@@ -45,6 +46,7 @@ impl RpcDescription {
 		let method_impls = self.render_methods()?;
 		let into_rpc_impl = self.render_into_rpc()?;
 		let async_trait = self.jrps_server_item(quote! { core::__reexports::async_trait });
+		let assoc_types = assoc_types(&self.trait_def);
 
 		// Doc-comment to be associated with the server.
 		let doc_comment = format!("Server trait implementation for the `{}` RPC API.", &self.trait_def.ident);
@@ -53,6 +55,7 @@ impl RpcDescription {
 			#[#async_trait]
 			#[doc = #doc_comment]
 			pub trait #trait_name #impl_generics: Sized + Send + Sync + 'static #where_clause {
+				#(#assoc_types)*
 				#method_impls
 				#into_rpc_impl
 			}
@@ -66,11 +69,11 @@ impl RpcDescription {
 			let docs = &method.docs;
 			let mut method_sig = method.signature.clone();
 
-			if method.with_extensions {
-				let ext_ty = self.jrps_server_item(quote! { Extensions });
-				// Add `Extension` as the second parameter to the signature.
-				let ext: syn::FnArg = syn::parse_quote!(ext: &#ext_ty);
-				method_sig.sig.inputs.insert(1, ext);
+			// Add `Extensions` and `Session` right after `&self` to the signature.
+			for (pos, arg) in
+				self.render_extra_inputs(method.with_extensions, method.with_session).into_iter().enumerate()
+			{
+				method_sig.sig.inputs.insert(pos + 1, arg);
 			}
 
 			quote! {
@@ -83,16 +86,19 @@ impl RpcDescription {
 			let docs = &sub.docs;
 			let subscription_sink_ty = self.jrps_server_item(quote! { PendingSubscriptionSink });
 
-			// Add `SubscriptionSink` as the second input parameter to the signature.
-			let subscription_sink: syn::FnArg = syn::parse_quote!(subscription_sink: #subscription_sink_ty);
 			let mut sub_sig = sub.signature.clone();
-			sub_sig.sig.inputs.insert(1, subscription_sink);
 
-			if sub.with_extensions {
-				let ext_ty = self.jrps_server_item(quote! { Extensions });
-				// Add `Extension` as the third parameter to the signature.
-				let ext: syn::FnArg = syn::parse_quote!(ext: &#ext_ty);
-				sub_sig.sig.inputs.insert(2, ext);
+			// Subscriptions that return a stream don't take the `SubscriptionSink`.
+			if !sub.returns_stream {
+				// Add `SubscriptionSink` as the second input parameter to the signature.
+				let subscription_sink: syn::FnArg = syn::parse_quote!(subscription_sink: #subscription_sink_ty);
+				sub_sig.sig.inputs.insert(1, subscription_sink);
+			}
+
+			// Add `Extensions` and `Session` after the `SubscriptionSink` to the signature.
+			let offset = if sub.returns_stream { 1 } else { 2 };
+			for (pos, arg) in self.render_extra_inputs(sub.with_extensions, sub.with_session).into_iter().enumerate() {
+				sub_sig.sig.inputs.insert(pos + offset, arg);
 			}
 
 			quote! {
@@ -107,6 +113,43 @@ impl RpcDescription {
 		})
 	}
 
+	/// Renders the `Extensions` and `Session` inputs that are added to the signature of the server trait.
+	fn render_extra_inputs(&self, with_extensions: bool, with_session: bool) -> Vec<syn::FnArg> {
+		let mut inputs = Vec::new();
+
+		if with_extensions {
+			let ext_ty = self.jrps_server_item(quote! { Extensions });
+			inputs.push(syn::parse_quote!(ext: &#ext_ty));
+		}
+		if with_session {
+			let session_ty = self.jrps_server_item(quote! { Session });
+			inputs.push(syn::parse_quote!(session: #session_ty));
+		}
+
+		inputs
+	}
+
+	/// Renders the pattern of the extensions in the callback and the `Extensions` and `Session` arguments
+	/// passed to the server trait method, where the arguments are followed by a comma.
+	fn render_extra_args(&self, with_extensions: bool, with_session: bool) -> (TokenStream2, TokenStream2) {
+		let mut args = TokenStream2::new();
+
+		if with_extensions {
+			args.extend(quote! { &ext, });
+		}
+		if with_session {
+			let session_ty = self.jrps_server_item(quote! { Session });
+			args.extend(quote! { #session_ty::from_extensions(&ext), });
+		}
+
+		let ext = if with_extensions || with_session {
+			quote! { ext }
+		} else {
+			quote! { _ }
+		};
+		(ext, args)
+	}
+
 	/// Helper that will ignore results of `register_*` method calls, and panic if there have been
 	/// any errors in debug builds.
 	///
@@ -149,47 +192,32 @@ impl RpcDescription {
 				// provided `Params` object.
 				// `params_seq` is the comma-delimited sequence of parameters we're passing to the rust function
 				// called..
-				let (parsing, params_seq) = self.render_params_decoding(&method.params, None);
+				let (parsing, params_seq) =
+					self.render_params_decoding(&method.params, None, method.deny_unknown_params);
 
 				let into_response = self.jrps_server_item(quote! { IntoResponse });
 
 				check_name(&rpc_method_name, rust_method_name.span());
 
+				let (ext, extra_args) = self.render_extra_args(method.with_extensions, method.with_session);
+
 				if method.signature.sig.asyncness.is_some() {
-					if method.with_extensions {
-						self.handle_register_result(quote! {
-							rpc.register_async_method(#rpc_method_name, |params, context, ext| async move {
-								#parsing
-								#into_response::into_response(context.as_ref().#rust_method_name(&ext, #params_seq).await)
-							})
-						})
-					} else {
-						self.handle_register_result(quote! {
-							rpc.register_async_method(#rpc_method_name, |params, context, _| async move {
-								#parsing
-								#into_response::into_response(context.as_ref().#rust_method_name(#params_seq).await)
-							})
+					self.handle_register_result(quote! {
+						rpc.register_async_method(#rpc_method_name, |params, context, #ext| async move {
+							#parsing
+							#into_response::into_response(context.as_ref().#rust_method_name(#extra_args #params_seq).await)
 						})
-					}
+					})
 				} else {
 					let register_kind =
 						if method.blocking { quote!(register_blocking_method) } else { quote!(register_method) };
 
-					if method.with_extensions {
-						self.handle_register_result(quote! {
-							rpc.#register_kind(#rpc_method_name, |params, context, ext| {
-								#parsing
-								#into_response::into_response(context.#rust_method_name(&ext, #params_seq))
-							})
-						})
-					} else {
-						self.handle_register_result(quote! {
-							rpc.#register_kind(#rpc_method_name, |params, context, _| {
-								#parsing
-								#into_response::into_response(context.#rust_method_name(#params_seq))
-							})
+					self.handle_register_result(quote! {
+						rpc.#register_kind(#rpc_method_name, |params, context, #ext| {
+							#parsing
+							#into_response::into_response(context.#rust_method_name(#extra_args #params_seq))
 						})
-					}
+					})
 				}
 			})
 			.collect::<Vec<_>>();
@@ -210,7 +238,7 @@ impl RpcDescription {
 				// provided `Params` object.
 				// `params_seq` is the comma-delimited sequence of parameters.
 				let pending = proc_macro2::Ident::new("pending", rust_method_name.span());
-				let (parsing, params_seq) = self.render_params_decoding(&sub.params, Some(pending));
+				let (parsing, params_seq) = self.render_params_decoding(&sub.params, Some(pending), false);
 				let sub_err = self.jrps_server_item(quote! { SubscriptionCloseResponse });
 				let into_sub_response = self.jrps_server_item(quote! { IntoSubscriptionCloseResponse });
 
@@ -225,35 +253,38 @@ impl RpcDescription {
 					None => rpc_sub_name.clone(),
 				};
 
-				if sub.signature.sig.asyncness.is_some() {
-					if sub.with_extensions {
-						self.handle_register_result(quote! {
-							rpc.register_subscription(#rpc_sub_name, #rpc_notif_name, #rpc_unsub_name, |params, mut pending, context, ext| async move {
-								#parsing
-								#into_sub_response::into_response(context.as_ref().#rust_method_name(pending, &ext, #params_seq).await)
-							})
-						})
-					} else {
-						self.handle_register_result(quote! {
-							rpc.register_subscription(#rpc_sub_name, #rpc_notif_name, #rpc_unsub_name, |params, mut pending, context, _| async move {
-								#parsing
-								#into_sub_response::into_response(context.as_ref().#rust_method_name(pending, #params_seq).await)
-							})
+				let (ext, extra_args) = self.render_extra_args(sub.with_extensions, sub.with_session);
+
+				if sub.returns_stream {
+					let pipe_config = match &sub.pipe_config {
+						Some(path) => quote! { #path() },
+						None => {
+							let pipe_config = self.jrps_server_item(quote! { PipeFromStreamConfig });
+							quote! { #pipe_config::default() }
+						}
+					};
+					let call = quote! { context.as_ref().#rust_method_name(#extra_args #params_seq) };
+					let call = if sub.signature.sig.asyncness.is_some() { quote! { #call.await } } else { call };
+
+					self.handle_register_result(quote! {
+						rpc.register_subscription(#rpc_sub_name, #rpc_notif_name, #rpc_unsub_name, |params, mut pending, context, #ext| async move {
+							#parsing
+							let stream = #call;
+							pending.pipe_from_stream(stream, #pipe_config).await
 						})
-					}
-				} else if sub.with_extensions {
+					})
+				} else if sub.signature.sig.asyncness.is_some() {
 					self.handle_register_result(quote! {
-						rpc.register_subscription_raw(#rpc_sub_name, #rpc_notif_name, #rpc_unsub_name, |params, mut pending, context, ext| {
+						rpc.register_subscription(#rpc_sub_name, #rpc_notif_name, #rpc_unsub_name, |params, mut pending, context, #ext| async move {
 							#parsing
-							let _ = context.as_ref().#rust_method_name(pending, &ext, #params_seq);
-							#sub_err::None
+							#into_sub_response::into_response(context.as_ref().#rust_method_name(pending, #extra_args #params_seq).await)
 						})
 					})
 				} else {
 					self.handle_register_result(quote! {
-						rpc.register_subscription_raw(#rpc_sub_name, #rpc_notif_name, #rpc_unsub_name, |params, mut pending, context, _| {
+						rpc.register_subscription_raw(#rpc_sub_name, #rpc_notif_name, #rpc_unsub_name, |params, mut pending, context, #ext| {
 							#parsing
-							let _ = context.as_ref().#rust_method_name(pending, #params_seq);
+							let _ = context.as_ref().#rust_method_name(pending, #extra_args #params_seq);
 							#sub_err::None
 						})
 					})
@@ -284,6 +315,58 @@ impl RpcDescription {
 			})
 			.collect::<Vec<_>>();
 
+		let method_deprecations = self
+			.methods
+			.iter()
+			.filter_map(|method| {
+				let (since, note) = method.deprecation.as_ref()?;
+				let rpc_name = self.rpc_identifier(&method.name);
+				let deprecation = self.jrps_server_item(quote! { Deprecation });
+				let since = since.as_ref().map_or_else(|| quote!(None), |since| quote!(Some(#since.into())));
+				let note = note.as_ref().map_or_else(|| quote!(None), |note| quote!(Some(#note.into())));
+
+				let names = std::iter::once(rpc_name.as_ref()).chain(method.aliases.iter().map(String::as_str));
+				let deprecate =
+					names.map(|name| self.handle_register_result(quote! { rpc.deprecate(#name, deprecation.clone()) }));
+
+				Some(quote! {{
+					let deprecation = #deprecation { since: #since, note: #note };
+					#(#deprecate)*
+				}})
+			})
+			.collect::<Vec<_>>();
+
+		let method_metas = self
+			.methods
+			.iter()
+			.map(|method| (&method.name, &method.aliases, &method.meta))
+			.chain(self.subscriptions.iter().map(|sub| (&sub.name, &sub.aliases, &sub.meta)))
+			.filter(|(_, _, meta)| !meta.is_empty())
+			.map(|(name, aliases, meta)| {
+				let method_meta = self.jrps_server_item(quote! { MethodMeta });
+				let meta_value = self.jrps_server_item(quote! { MetaValue });
+				let entries = meta.iter().map(|MetaEntry { key, value }| {
+					let key = key.to_string();
+					let value = match value {
+						MetaLit::Bool(b) => quote! { #meta_value::Bool(#b) },
+						MetaLit::Int(n) => quote! { #meta_value::Int(#n) },
+						MetaLit::Float(f) => quote! { #meta_value::Float(#f) },
+						MetaLit::Str(s) => quote! { #meta_value::from(#s) },
+					};
+					quote! { .with(#key, #value) }
+				});
+				let rpc_name = self.rpc_identifier(name);
+				let names = std::iter::once(rpc_name.as_ref()).chain(aliases.iter().map(String::as_str));
+				let set_meta =
+					names.map(|name| self.handle_register_result(quote! { rpc.set_method_meta(#name, meta.clone()) }));
+
+				quote! {{
+					let meta = #method_meta::new() #(#entries)*;
+					#(#set_meta)*
+				}}
+			})
+			.collect::<Vec<_>>();
+
 		let subscription_aliases = self
 			.subscriptions
 			.iter()
@@ -336,7 +419,9 @@ impl RpcDescription {
 				#(#methods)*
 				#(#subscriptions)*
 				#(#method_aliases)*
+				#(#method_deprecations)*
 				#(#subscription_aliases)*
+				#(#method_metas)*
 
 				rpc
 			}
@@ -347,8 +432,9 @@ impl RpcDescription {
 		&self,
 		params: &[RpcFnArg],
 		sub: Option<proc_macro2::Ident>,
+		deny_unknown_params: bool,
 	) -> (TokenStream2, TokenStream2) {
-		if params.is_empty() {
+		if params.is_empty() && !deny_unknown_params {
 			return (TokenStream2::default(), TokenStream2::default());
 		}
 
@@ -373,8 +459,23 @@ impl RpcDescription {
 
 		// Code to decode sequence of parameters from a JSON array.
 		let decode_array = {
-			let decode_fields = params.iter().map(|RpcFnArg { arg_pat, ty, .. }| {
+			let decode_fields = params.iter().map(|RpcFnArg { arg_pat, ty, default, .. }| {
 				let is_option = is_option(ty);
+
+				// Missing or `null` arguments with a default value are replaced by the default.
+				if let Some(default) = default {
+					return quote! {
+						let #arg_pat: #ty = match seq.optional_next() {
+							Ok(Some(v)) => v,
+							Ok(None) => #default,
+							Err(e) => {
+								#reexports::log_fail_parse(stringify!(#arg_pat), stringify!(#ty), &e, true);
+								#error_ret
+							}
+						};
+					};
+				}
+
 				let next_method = if is_option { quote!(optional_next) } else { quote!(next) };
 				quote! {
 					let #arg_pat: #ty = match seq.#next_method() {
@@ -387,10 +488,28 @@ impl RpcDescription {
 				}
 			});
 
+			let finish = if deny_unknown_params {
+				quote! {
+					if let Err(e) = seq.finish() {
+						#error_ret
+					}
+				}
+			} else {
+				TokenStream2::new()
+			};
+
+			// There is nothing to parse if the method has no arguments and only the unknown params are checked.
+			let (seq, tail) = if params.is_empty() {
+				(quote!(seq), TokenStream2::new())
+			} else {
+				(quote!(mut seq), quote!((#params_fields)))
+			};
+
 			quote! {
-				let mut seq = params.sequence();
+				let #seq = params.sequence();
 				#(#decode_fields);*
-				(#params_fields)
+				#finish
+				#tail
 			}
 		};
 
@@ -419,23 +538,52 @@ impl RpcDescription {
 					#[serde(#alias)]
 				};
 
+				// Arguments with a default value are optional in the object.
+				if fn_arg.default().is_some() {
+					return quote! {
+						#serde_alias
+						#serde_rename
+						#[serde(default)]
+						#arg_pat: Option<#ty>,
+					};
+				}
+
 				quote! {
 					#serde_alias
 					#serde_rename
 					#arg_pat: #ty,
 				}
 			});
-			let destruct = params.iter().map(RpcFnArg::arg_pat).map(|a| quote!(parsed.#a));
+			let destruct = params.iter().map(|fn_arg| {
+				let arg_pat = fn_arg.arg_pat();
+				match fn_arg.default() {
+					Some(default) => quote! {
+						match parsed.#arg_pat {
+							Some(v) => v,
+							None => #default,
+						}
+					},
+					None => quote!(parsed.#arg_pat),
+				}
+			});
 			let types = params.iter().map(RpcFnArg::ty);
+			let (parsed, destruct) = if params.is_empty() {
+				(quote!(_), TokenStream2::new())
+			} else {
+				(quote!(parsed), quote!((#(#destruct),*)))
+			};
+			let deny_unknown_fields =
+				if deny_unknown_params { quote!(#[serde(deny_unknown_fields)]) } else { TokenStream2::new() };
 
 			quote! {
 				#[derive(#serde::Deserialize)]
 				#[serde(crate = #serde_crate)]
+				#deny_unknown_fields
 				struct ParamsObject<#(#generics,)*> {
 					#(#fields)*
 				}
 
-				let parsed: ParamsObject<#(#types,)*> = match params.parse() {
+				let #parsed: ParamsObject<#(#types,)*> = match params.parse() {
 					Ok(p) => p,
 					Err(e) => {
 						#reexports::log_fail_parse_as_object(&e);
@@ -443,16 +591,27 @@ impl RpcDescription {
 					}
 				};
 
-				(#(#destruct),*)
+				#destruct
 			}
 		};
 
-		let parsing = quote! {
-			let (#params_fields) = if params.is_object() {
-				#decode_map
-			} else {
-				#decode_array
-			};
+		// Without arguments the params are only checked.
+		let parsing = if params.is_empty() {
+			quote! {
+				if params.is_object() {
+					#decode_map
+				} else {
+					#decode_array
+				}
+			}
+		} else {
+			quote! {
+				let (#params_fields) = if params.is_object() {
+					#decode_map
+				} else {
+					#decode_array
+				};
+			}
 		};
 
 		(parsing, params_fields)
@@ -0,0 +1,392 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Renders TypeScript bindings of an RPC API, enabled by `#[rpc(typescript)]`.
+
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use crate::attributes::ParamKind;
+use crate::helpers::{is_option, ok_type};
+use crate::rpc_macro::{RpcDescription, RpcFnArg};
+use heck::ToLowerCamelCase;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+
+/// Words which can be used as Rust identifiers but not as TypeScript parameter names,
+/// the generated code is a module and thus in strict mode.
+const RESERVED_WORDS: &[&str] = &[
+	"arguments",
+	"case",
+	"catch",
+	"class",
+	"debugger",
+	"default",
+	"delete",
+	"do",
+	"eval",
+	"export",
+	"extends",
+	"finally",
+	"function",
+	"implements",
+	"import",
+	"instanceof",
+	"interface",
+	"new",
+	"null",
+	"package",
+	"private",
+	"protected",
+	"public",
+	"switch",
+	"this",
+	"throw",
+	"try",
+	"typeof",
+	"var",
+	"void",
+	"with",
+];
+
+/// Declarations shared by all the generated clients.
+const PRELUDE: &str = r#"export type JsonRpcParams = unknown[] | Record<string, unknown>;
+
+/** Active subscription, yields the notifications of the server. */
+export interface Subscription<T> extends AsyncIterable<T> {
+  unsubscribe(): Promise<void>;
+}
+
+/** JSON-RPC connection used by the generated clients. */
+export interface Transport {
+  request<T>(method: string, params: JsonRpcParams): Promise<T>;
+  notify(method: string, params: JsonRpcParams): Promise<void>;
+  subscribe<T>(method: string, params: JsonRpcParams, unsubscribe: string, notification: string): Promise<Subscription<T>>;
+}
+"#;
+
+/// Maps Rust types to TypeScript types and keeps track of the types which
+/// have to be defined by the user of the bindings.
+struct TypeMapper {
+	generics: BTreeSet<String>,
+	external: BTreeSet<String>,
+	/// Whether integers which may not fit in a JavaScript `number` are used.
+	big_integers: bool,
+}
+
+impl TypeMapper {
+	fn map(&mut self, ty: &syn::Type) -> String {
+		match ty {
+			syn::Type::Reference(r) => self.map(&r.elem),
+			syn::Type::Paren(p) => self.map(&p.elem),
+			syn::Type::Group(g) => self.map(&g.elem),
+			syn::Type::Slice(s) => self.array_of(&s.elem),
+			syn::Type::Array(a) => self.array_of(&a.elem),
+			syn::Type::Tuple(t) if t.elems.is_empty() => "null".to_string(),
+			syn::Type::Tuple(t) => {
+				let elems: Vec<_> = t.elems.iter().map(|ty| self.map(ty)).collect();
+				format!("[{}]", elems.join(", "))
+			}
+			syn::Type::Path(p) if p.qself.is_none() => self.map_path(&p.path),
+			_ => "unknown".to_string(),
+		}
+	}
+
+	fn map_path(&mut self, path: &syn::Path) -> String {
+		let Some(segment) = path.segments.last() else {
+			return "unknown".to_string();
+		};
+		let ident = segment.ident.to_string();
+		let args: Vec<&syn::Type> = match &segment.arguments {
+			syn::PathArguments::AngleBracketed(generics) => generics
+				.args
+				.iter()
+				.filter_map(|arg| match arg {
+					syn::GenericArgument::Type(ty) => Some(ty),
+					_ => None,
+				})
+				.collect(),
+			_ => Vec::new(),
+		};
+
+		match (ident.as_str(), args.as_slice()) {
+			("u8" | "u16" | "u32" | "i8" | "i16" | "i32" | "f32" | "f64", []) => "number".to_string(),
+			("u64" | "u128" | "usize" | "i64" | "i128" | "isize", []) => {
+				self.big_integers = true;
+				"number | bigint".to_string()
+			}
+			("bool", []) => "boolean".to_string(),
+			("String" | "str" | "char" | "PathBuf" | "Path", []) => "string".to_string(),
+			("Value" | "RawValue", []) => "unknown".to_string(),
+			("Option", [inner]) => format!("{} | null", self.map(inner)),
+			("Vec" | "VecDeque" | "HashSet" | "BTreeSet" | "IndexSet", [inner, ..]) => self.array_of(inner),
+			("HashMap" | "BTreeMap" | "IndexMap", [_, value, ..]) => format!("Record<string, {}>", self.map(value)),
+			("Box" | "Arc" | "Rc" | "Cow", [inner]) => self.map(inner),
+			_ if path.segments.len() == 1 && self.generics.contains(&ident) => ident,
+			_ => {
+				self.external.insert(ident.clone());
+				if args.is_empty() {
+					ident
+				} else {
+					let args: Vec<_> = args.into_iter().map(|ty| self.map(ty)).collect();
+					format!("{ident}<{}>", args.join(", "))
+				}
+			}
+		}
+	}
+
+	fn array_of(&mut self, elem: &syn::Type) -> String {
+		let elem = self.map(elem);
+		if elem.contains('|') { format!("({elem})[]") } else { format!("{elem}[]") }
+	}
+}
+
+/// A parameter of a TypeScript method.
+struct TsParam {
+	/// Name of the parameter in the TypeScript signature.
+	ident: String,
+	/// Name of the parameter in the JSON-RPC params.
+	name: String,
+	ty: String,
+	optional: bool,
+}
+
+impl RpcDescription {
+	pub(super) fn render_typescript(&self) -> TokenStream2 {
+		let trait_name = self.trait_def.ident.to_string();
+		let generics: Vec<String> = self.trait_def.generics.type_params().map(|p| p.ident.to_string()).collect();
+		let mut mapper =
+			TypeMapper { generics: generics.iter().cloned().collect(), external: BTreeSet::new(), big_integers: false };
+		let generics = if generics.is_empty() { String::new() } else { format!("<{}>", generics.join(", ")) };
+
+		let mut names = String::new();
+		let mut api = String::new();
+		let mut client = String::new();
+
+		for method in &self.methods {
+			let ts_name = method.signature.sig.ident.to_string().to_lower_camel_case();
+			let rpc_name = self.rpc_identifier(&method.name);
+			let params = ts_params(&mut mapper, &method.params);
+			let (returns, call) = match &method.returns {
+				Some(ty) => {
+					let ty = ok_type(ty).unwrap_or_else(|| ty.clone());
+					(mapper.map(&ty), "request")
+				}
+				None => ("void".to_string(), "notify"),
+			};
+
+			let rpc_name = ts_string(&rpc_name);
+			let _ = writeln!(names, "  {ts_name}: {{ name: {rpc_name}, aliases: {} }},", ts_strings(&method.aliases));
+			write_docs(&mut api, &method.docs, &method.aliases);
+			let signature = format!("{ts_name}({}): Promise<{returns}>", ts_signature(&params));
+			let _ = writeln!(api, "  {signature};");
+			let _ = writeln!(
+				client,
+				"\n  {signature} {{\n    return this.transport.{call}({rpc_name}, {});\n  }}",
+				ts_encode(&params, &method.param_kind)
+			);
+		}
+
+		for sub in &self.subscriptions {
+			let ts_name = sub.signature.sig.ident.to_string().to_lower_camel_case();
+			let rpc_sub_name = self.rpc_identifier(&sub.name);
+			let rpc_unsub_name = ts_string(&self.rpc_identifier(&sub.unsubscribe));
+			let rpc_notif_name = match &sub.notif_name_override {
+				Some(notif) => ts_string(&self.rpc_identifier(notif)),
+				None => ts_string(&rpc_sub_name),
+			};
+			let rpc_sub_name = ts_string(&rpc_sub_name);
+			let params = ts_params(&mut mapper, &sub.params);
+			let item = mapper.map(&sub.item);
+
+			let _ = writeln!(
+				names,
+				"  {ts_name}: {{ name: {rpc_sub_name}, unsubscribe: {rpc_unsub_name}, notification: {rpc_notif_name}, aliases: {}, unsubscribeAliases: {} }},",
+				ts_strings(&sub.aliases),
+				ts_strings(&sub.unsubscribe_aliases)
+			);
+			write_docs(&mut api, &sub.docs, &sub.aliases);
+			let signature = format!("{ts_name}({}): Promise<Subscription<{item}>>", ts_signature(&params));
+			let _ = writeln!(api, "  {signature};");
+			let _ = writeln!(
+				client,
+				"\n  {signature} {{\n    return this.transport.subscribe({rpc_sub_name}, {}, {rpc_unsub_name}, {rpc_notif_name});\n  }}",
+				ts_encode(&params, &sub.param_kind)
+			);
+		}
+
+		let mut source = format!("// Generated by jsonrpsee from the `{trait_name}` RPC API, do not edit.\n");
+		if !mapper.external.is_empty() {
+			let external: Vec<_> = mapper.external.iter().map(|ty| format!("`{ty}`")).collect();
+			let _ =
+				writeln!(source, "//\n// The following types must be provided by the user: {}.", external.join(", "));
+		}
+		if mapper.big_integers {
+			source.push_str(
+				"//\n// 64-bit and larger integers are typed `number | bigint`. `JSON.parse` decodes them to a `number`\n\
+				// which is only exact up to `Number.MAX_SAFE_INTEGER` and `JSON.stringify` can't encode a `bigint`,\n\
+				// thus the `Transport` must use a JSON parser with `bigint` support if the exact values are needed.\n",
+			);
+		}
+		let _ = write!(
+			source,
+			"\n{PRELUDE}\n/** Names of the methods and subscriptions of the `{trait_name}` RPC API. */\n\
+			export const {trait_name}Methods = {{\n{names}}} as const;\n\n\
+			/** The `{trait_name}` RPC API. */\n\
+			export interface {trait_name}Api{generics} {{\n{api}}}\n\n\
+			/** Client of the `{trait_name}` RPC API. */\n\
+			export class {trait_name}Client{generics} implements {trait_name}Api{generics} {{\n  \
+			constructor(private readonly transport: Transport) {{}}\n{client}}}\n"
+		);
+
+		let fn_name = quote::format_ident!("{}_typescript", heck::ToSnakeCase::to_snake_case(trait_name.as_str()));
+		let doc = format!("TypeScript bindings of the `{trait_name}` RPC API.");
+
+		quote! {
+			#[doc = #doc]
+			#[allow(dead_code)]
+			pub fn #fn_name() -> &'static str {
+				#source
+			}
+		}
+	}
+}
+
+fn ts_params(mapper: &mut TypeMapper, args: &[RpcFnArg]) -> Vec<TsParam> {
+	let mut params: Vec<TsParam> = args
+		.iter()
+		.map(|arg| {
+			let mut ident = arg.arg_pat().ident.to_string().to_lower_camel_case();
+			if RESERVED_WORDS.contains(&ident.as_str()) {
+				ident.push('_');
+			}
+			TsParam {
+				ident,
+				name: arg.name(),
+				ty: mapper.map(arg.ty()),
+				optional: arg.default().is_some() || is_option(arg.ty()),
+			}
+		})
+		.collect();
+
+	// Only the trailing parameters may be omitted in TypeScript.
+	let mut required = false;
+	for param in params.iter_mut().rev() {
+		required |= !param.optional;
+		param.optional &= !required;
+	}
+
+	params
+}
+
+fn ts_signature(params: &[TsParam]) -> String {
+	let params: Vec<_> = params
+		.iter()
+		.map(|p| if p.optional { format!("{}?: {}", p.ident, p.ty) } else { format!("{}: {}", p.ident, p.ty) })
+		.collect();
+	params.join(", ")
+}
+
+fn ts_encode(params: &[TsParam], param_kind: &ParamKind) -> String {
+	match param_kind {
+		ParamKind::Array => {
+			let params: Vec<_> = params
+				.iter()
+				.map(|p| if p.optional { format!("{} ?? null", p.ident) } else { p.ident.clone() })
+				.collect();
+			format!("[{}]", params.join(", "))
+		}
+		ParamKind::Map if params.is_empty() => "{}".to_string(),
+		ParamKind::Map => {
+			let params: Vec<_> = params.iter().map(|p| format!("{}: {}", ts_string(&p.name), p.ident)).collect();
+			format!("{{ {} }}", params.join(", "))
+		}
+	}
+}
+
+fn ts_strings(strings: &[String]) -> String {
+	let strings: Vec<_> = strings.iter().map(|s| ts_string(s)).collect();
+	format!("[{}]", strings.join(", "))
+}
+
+/// Quotes `s` as a JavaScript string literal.
+///
+/// Rust's `Debug` escapes aren't used because some of them aren't valid in JavaScript,
+/// such as `\0` followed by a digit which is an octal escape in strict mode.
+fn ts_string(s: &str) -> String {
+	let mut out = String::with_capacity(s.len() + 2);
+	out.push('"');
+	for c in s.chars() {
+		match c {
+			'"' => out.push_str("\\\""),
+			'\\' => out.push_str("\\\\"),
+			'\n' => out.push_str("\\n"),
+			'\r' => out.push_str("\\r"),
+			'\t' => out.push_str("\\t"),
+			c if c.is_control() || c == '\u{2028}' || c == '\u{2029}' => {
+				let _ = write!(out, "\\u{:04x}", c as u32);
+			}
+			c => out.push(c),
+		}
+	}
+	out.push('"');
+	out
+}
+
+/// Writes the doc comments of a method or a subscription as JSDoc.
+fn write_docs(out: &mut String, docs: &TokenStream2, aliases: &[String]) {
+	let attrs = syn::parse::Parser::parse2(syn::Attribute::parse_outer, docs.clone()).unwrap_or_default();
+	let mut lines: Vec<String> = attrs
+		.iter()
+		.filter_map(|attr| match &attr.meta {
+			syn::Meta::NameValue(syn::MetaNameValue {
+				value: syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(doc), .. }),
+				..
+			}) => Some(doc.value()),
+			_ => None,
+		})
+		.flat_map(|doc| {
+			doc.lines().map(|line| line.strip_prefix(' ').unwrap_or(line).replace("*/", "*\\/")).collect::<Vec<_>>()
+		})
+		.collect();
+
+	if !aliases.is_empty() {
+		if !lines.is_empty() {
+			lines.push(String::new());
+		}
+		let aliases: Vec<_> = aliases.iter().map(|a| format!("`{a}`")).collect();
+		lines.push(format!("Aliases: {}.", aliases.join(", ")));
+	}
+
+	if lines.is_empty() {
+		return;
+	}
+	let _ = writeln!(out, "  /**");
+	for line in lines {
+		let _ = writeln!(out, "   *{}{line}", if line.is_empty() { "" } else { " " });
+	}
+	let _ = writeln!(out, "   */");
+}
@@ -0,0 +1,301 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Implementation of the `RpcError` derive macro.
+
+use crate::attributes::{Argument, AttributeMeta, optional};
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::spanned::Spanned;
+
+/// The range of error codes reserved by the JSON-RPC specification.
+const RESERVED_CODES: std::ops::RangeInclusive<i64> = -32768..=-32000;
+
+/// How the fields of a variant are encoded in the `data` of the error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DataKind {
+	/// A single unnamed field is encoded as is, several unnamed fields as an array and named fields as an object.
+	Fields,
+	/// No `data` is sent and the fields are decoded with `Default::default()`.
+	None,
+}
+
+/// A variant of the error enum and its JSON-RPC error code.
+struct ErrorVariant {
+	ident: syn::Ident,
+	fields: syn::Fields,
+	code: i32,
+	code_span: Span,
+	message: syn::LitStr,
+	data: DataKind,
+}
+
+impl ErrorVariant {
+	fn from_variant(variant: &syn::Variant) -> syn::Result<Self> {
+		let Some(attr) = variant.attrs.iter().find(|a| a.path().is_ident("rpc_error")) else {
+			return Err(syn::Error::new_spanned(variant, "Missing `#[rpc_error(code = ..)]` attribute"));
+		};
+
+		let [code, data, message] = AttributeMeta::parse(attr.clone())?.retain(["code", "data", "message"])?;
+		let code_expr = code?.value::<syn::Expr>()?;
+		let code_span = code_expr.span();
+		let code = parse_code(&code_expr)?;
+		let message = optional(message, Argument::value::<syn::LitStr>)?
+			.unwrap_or_else(|| syn::LitStr::new(&variant.ident.to_string(), variant.ident.span()));
+		let data = match optional(data, Argument::value::<syn::Ident>)? {
+			None => DataKind::Fields,
+			Some(ident) if ident == "fields" => DataKind::Fields,
+			Some(ident) if ident == "none" => DataKind::None,
+			Some(ident) => return Err(syn::Error::new(ident.span(), "data must be either `fields` or `none`")),
+		};
+
+		if RESERVED_CODES.contains(&i64::from(code)) {
+			return Err(syn::Error::new(
+				code_span,
+				format!(
+					"Error code `{code}` is in the range {}..={} reserved by the JSON-RPC specification, see `jsonrpsee::types::ErrorCode`",
+					RESERVED_CODES.start(),
+					RESERVED_CODES.end()
+				),
+			));
+		}
+
+		Ok(Self { ident: variant.ident.clone(), fields: variant.fields.clone(), code, code_span, message, data })
+	}
+
+	/// Bindings of the fields when the variant is destructured.
+	fn bindings(&self) -> Vec<syn::Ident> {
+		match &self.fields {
+			syn::Fields::Named(fields) => fields.named.iter().map(|f| f.ident.clone().unwrap()).collect(),
+			syn::Fields::Unnamed(fields) => (0..fields.unnamed.len()).map(|i| format_ident!("field_{i}")).collect(),
+			syn::Fields::Unit => Vec::new(),
+		}
+	}
+
+	/// Pattern which destructures the variant of the enum `ty`.
+	fn pattern(&self, ty: &syn::Ident) -> TokenStream2 {
+		let ident = &self.ident;
+		let bindings = self.bindings();
+
+		match &self.fields {
+			syn::Fields::Named(_) => quote!(#ty::#ident { #(#bindings),* }),
+			syn::Fields::Unnamed(_) => quote!(#ty::#ident(#(#bindings),*)),
+			syn::Fields::Unit => quote!(#ty::#ident),
+		}
+	}
+
+	/// The message of the error.
+	///
+	/// The message is a template where `{name}` and `{0}` are replaced by the named and unnamed fields.
+	fn message(&self) -> TokenStream2 {
+		let template = self.message.value();
+
+		if !template.contains('{') {
+			let message = &self.message;
+			return quote!(#message);
+		}
+
+		let template = syn::LitStr::new(&rewrite_positional_args(&template), self.message.span());
+		quote!(format!(#template))
+	}
+
+	/// Encode the variant as `ErrorObjectOwned`.
+	fn encode(&self, ty: &syn::Ident, core: &TokenStream2) -> TokenStream2 {
+		let code = self.code;
+		let message = self.message();
+		let bindings = self.bindings();
+		let types = quote!(#core::__reexports::jsonrpsee_types);
+		let serde_json = quote!(#core::__reexports::serde_json);
+		let panic_fail_serialize = quote!(#core::__reexports::panic_fail_serialize_error_data);
+		let variant = format!("{ty}::{}", self.ident);
+
+		// Failing to serialize the data is a bug in the error type, it's not silently sent as `null`.
+		let data = match &self.fields {
+			_ if self.data == DataKind::None => quote!(None::<()>),
+			syn::Fields::Unit => quote!(None::<()>),
+			syn::Fields::Unnamed(_) if bindings.len() == 1 => quote!(Some(
+				#serde_json::to_value(#(#bindings)*).unwrap_or_else(|err| #panic_fail_serialize(#variant, err))
+			)),
+			syn::Fields::Unnamed(_) => quote!(Some(
+				#serde_json::to_value((#(#bindings),*)).unwrap_or_else(|err| #panic_fail_serialize(#variant, err))
+			)),
+			syn::Fields::Named(_) => {
+				let names: Vec<_> = bindings.iter().map(|b| b.to_string()).collect();
+				let fields = names.iter().map(|name| format!("{variant}::{name}"));
+				quote!({
+					let mut data = #serde_json::Map::new();
+					#(
+						let value = #serde_json::to_value(#bindings).unwrap_or_else(|err| #panic_fail_serialize(#fields, err));
+						data.insert(#names.into(), value);
+					)*
+					Some(data)
+				})
+			}
+		};
+
+		let pattern = self.pattern(ty);
+		quote!(#pattern => {
+			let message = #message;
+			#types::ErrorObject::owned(#code, message, #data)
+		})
+	}
+
+	/// Decode the variant from the `data` of the error object if the code matches.
+	fn decode(&self, core: &TokenStream2) -> TokenStream2 {
+		let code = self.code;
+		let ident = &self.ident;
+		let bindings = self.bindings();
+		let types: Vec<_> = self.fields.iter().map(|f| &f.ty).collect();
+		let serde_json = quote!(#core::__reexports::serde_json);
+
+		let decoded = match &self.fields {
+			syn::Fields::Unit => quote!(Some(Self::#ident)),
+			syn::Fields::Named(_) if self.data == DataKind::None => {
+				quote!(Some(Self::#ident { #(#bindings: Default::default()),* }))
+			}
+			syn::Fields::Unnamed(_) if self.data == DataKind::None => {
+				let defaults = bindings.iter().map(|_| quote!(Default::default()));
+				quote!(Some(Self::#ident(#(#defaults),*)))
+			}
+			syn::Fields::Unnamed(_) if bindings.len() == 1 => quote!({
+				let #(#bindings)*: #(#types)* = #serde_json::from_str(data?).ok()?;
+				Some(Self::#ident(#(#bindings)*))
+			}),
+			syn::Fields::Unnamed(_) => quote!({
+				let (#(#bindings,)*): (#(#types,)*) = #serde_json::from_str(data?).ok()?;
+				Some(Self::#ident(#(#bindings),*))
+			}),
+			syn::Fields::Named(_) => {
+				let names = bindings.iter().map(|b| b.to_string());
+				quote!({
+					let mut data: #serde_json::Map<String, #serde_json::Value> = #serde_json::from_str(data?).ok()?;
+					Some(Self::#ident {
+						#(
+							#bindings: #serde_json::from_value(data.remove(#names).unwrap_or_default()).ok()?,
+						)*
+					})
+				})
+			}
+		};
+
+		quote! {
+			if code == #code {
+				let decode = || -> Option<Self> { #decoded };
+				return match decode() {
+					Some(err) => Ok(err),
+					None => Err(err),
+				};
+			}
+		}
+	}
+}
+
+pub(crate) fn rpc_error_impl(input: syn::DeriveInput) -> syn::Result<TokenStream2> {
+	let syn::Data::Enum(data) = &input.data else {
+		return Err(syn::Error::new(input.span(), "`RpcError` can only be derived for enums"));
+	};
+
+	let core = crate::helpers::find_jsonrpsee_core_crate()?;
+	let variants = data.variants.iter().map(ErrorVariant::from_variant).collect::<syn::Result<Vec<_>>>()?;
+
+	for (i, variant) in variants.iter().enumerate() {
+		if let Some(other) = variants[..i].iter().find(|other| other.code == variant.code) {
+			return Err(syn::Error::new(
+				variant.code_span,
+				format!("Duplicate error code `{}`, already used by `{}`", variant.code, other.ident),
+			));
+		}
+	}
+
+	let ident = &input.ident;
+	let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+	let types = quote!(#core::__reexports::jsonrpsee_types);
+
+	let encode = variants.iter().map(|v| v.encode(ident, &core));
+	let decode = variants.iter().map(|v| v.decode(&core));
+
+	Ok(quote! {
+		impl #impl_generics From<#ident #type_generics> for #types::ErrorObjectOwned #where_clause {
+			#[allow(unused_variables)]
+			fn from(err: #ident #type_generics) -> Self {
+				match err {
+					#(#encode,)*
+				}
+			}
+		}
+
+		impl #impl_generics TryFrom<#types::ErrorObjectOwned> for #ident #type_generics #where_clause {
+			type Error = #types::ErrorObjectOwned;
+
+			#[allow(unused_variables)]
+			fn try_from(err: #types::ErrorObjectOwned) -> Result<Self, Self::Error> {
+				let code = err.code();
+				let data = err.data().map(|d| d.get());
+
+				#(#decode)*
+
+				Err(err)
+			}
+		}
+	})
+}
+
+/// Parse the error code which must be an integer literal, optionally negated.
+fn parse_code(expr: &syn::Expr) -> syn::Result<i32> {
+	let (lit, negative) = match expr {
+		syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Int(lit), .. }) => (lit, false),
+		syn::Expr::Unary(syn::ExprUnary { op: syn::UnOp::Neg(_), expr, .. }) => match &**expr {
+			syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Int(lit), .. }) => (lit, true),
+			_ => return Err(syn::Error::new(expr.span(), "Error code must be an integer literal")),
+		},
+		_ => return Err(syn::Error::new(expr.span(), "Error code must be an integer literal")),
+	};
+
+	let code = lit.base10_parse::<i64>()?;
+	let code = if negative { -code } else { code };
+
+	i32::try_from(code).map_err(|_| syn::Error::new(expr.span(), "Error code must fit into `i32`"))
+}
+
+/// Rewrite the positional arguments `{0}` of a message template to the bindings of the unnamed fields.
+fn rewrite_positional_args(template: &str) -> String {
+	let mut out = String::with_capacity(template.len());
+	let mut chars = template.chars().peekable();
+
+	while let Some(c) = chars.next() {
+		out.push(c);
+
+		if c == '{' {
+			match chars.peek() {
+				Some('{') => out.extend(chars.next()),
+				Some(c) if c.is_ascii_digit() => out.push_str("field_"),
+				_ => (),
+			}
+		}
+	}
+
+	out
+}
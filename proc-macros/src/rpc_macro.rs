@@ -29,12 +29,14 @@
 use std::borrow::Cow;
 
 use crate::attributes::{
-	Aliases, Argument, AttributeMeta, MissingArgument, NameMapping, ParamKind, optional, parse_param_kind,
+	Aliases, Argument, AttributeMeta, MetaEntry, MissingArgument, NameMapping, ParamKind, VersionStyle, optional,
+	parse_meta, parse_param_kind, parse_version_style,
 };
-use crate::helpers::extract_doc_comments;
+use crate::helpers::{extract_doc_comments, stream_item};
 use proc_macro2::TokenStream as TokenStream2;
 use quote::quote;
 use syn::spanned::Spanned;
+use syn::visit_mut::VisitMut;
 use syn::{Attribute, Token, punctuated::Punctuated};
 
 /// Represents a single argument in a RPC call.
@@ -45,26 +47,38 @@ pub struct RpcFnArg {
 	pub(crate) arg_pat: syn::PatIdent,
 	rename_to: Option<String>,
 	pub(crate) ty: syn::Type,
+	/// The value of the argument when it's omitted by the caller.
+	pub(crate) default: Option<syn::Expr>,
 }
 
 impl RpcFnArg {
 	pub fn from_arg_attrs(arg_pat: syn::PatIdent, ty: syn::Type, attrs: &mut Vec<syn::Attribute>) -> syn::Result<Self> {
 		let mut rename_to = None;
+		let mut default = None;
 
 		if let Some(attr) = find_attr(attrs, "argument") {
-			let [rename] = AttributeMeta::parse(attr.clone())?.retain(["rename"])?;
+			let [default_value, rename] = AttributeMeta::parse(attr.clone())?.retain(["default", "rename"])?;
 
 			let rename = optional(rename, Argument::string)?;
 
 			if let Some(rename) = rename {
 				rename_to = Some(rename);
 			}
+
+			// `#[argument(default)]` falls back to `Default::default()`.
+			default = optional(default_value, |arg| {
+				if arg.tokens.is_empty() {
+					Ok(syn::parse_quote!(::core::default::Default::default()))
+				} else {
+					arg.value()
+				}
+			})?;
 		}
 
 		// remove argument attribute after inspection
 		attrs.retain(|attr| !attr.meta.path().is_ident("argument"));
 
-		Ok(Self { arg_pat, rename_to, ty })
+		Ok(Self { arg_pat, rename_to, ty, default })
 	}
 
 	/// Return the pattern identifier of the argument.
@@ -79,38 +93,77 @@ impl RpcFnArg {
 	pub fn ty(&self) -> &syn::Type {
 		&self.ty
 	}
+	/// Return the default value of the argument if it may be omitted.
+	pub fn default(&self) -> Option<&syn::Expr> {
+		self.default.as_ref()
+	}
 }
 
 #[derive(Debug, Clone)]
 pub struct RpcMethod {
 	pub name: String,
+	pub version: Option<u32>,
 	pub blocking: bool,
 	pub docs: TokenStream2,
 	pub deprecated: TokenStream2,
+	/// The `since` and `note` of the `#[deprecated]` attribute, if the method is deprecated.
+	pub deprecation: Option<(Option<String>, Option<String>)>,
 	pub params: Vec<RpcFnArg>,
 	pub param_kind: ParamKind,
 	pub returns: Option<syn::Type>,
 	pub signature: syn::TraitItemFn,
 	pub aliases: Vec<String>,
 	pub with_extensions: bool,
+	pub with_session: bool,
+	pub idempotent: bool,
+	pub deny_unknown_params: bool,
+	/// The metadata of the method which the server inserts into the extensions of its requests.
+	pub meta: Vec<MetaEntry>,
 }
 
 impl RpcMethod {
 	pub fn from_item(attr: Attribute, mut method: syn::TraitItemFn) -> syn::Result<Self> {
-		let [aliases, blocking, name, param_kind, with_extensions] =
-			AttributeMeta::parse(attr)?.retain(["aliases", "blocking", "name", "param_kind", "with_extensions"])?;
+		let [
+			aliases,
+			blocking,
+			deny_unknown_params,
+			idempotent,
+			meta,
+			name,
+			param_kind,
+			version,
+			with_extensions,
+			with_session,
+		] = AttributeMeta::parse(attr)?.retain([
+			"aliases",
+			"blocking",
+			"deny_unknown_params",
+			"idempotent",
+			"meta",
+			"name",
+			"param_kind",
+			"version",
+			"with_extensions",
+			"with_session",
+		])?;
 
 		let aliases = parse_aliases(aliases)?;
 		let blocking = optional(blocking, Argument::flag)?.is_some();
+		let deny_unknown_params = optional(deny_unknown_params, Argument::flag)?.is_some();
+		let idempotent = optional(idempotent, Argument::flag)?.is_some();
+		let meta = parse_meta(meta)?;
 		let name = name?.string()?;
 		let param_kind = parse_param_kind(param_kind)?;
+		let version = optional(version, Argument::value::<syn::LitInt>)?.map(|v| v.base10_parse()).transpose()?;
 		let with_extensions = optional(with_extensions, Argument::flag)?.is_some();
+		let with_session = optional(with_session, Argument::flag)?.is_some();
 
 		let docs = extract_doc_comments(&method.attrs);
 		let deprecated = match find_attr(&method.attrs, "deprecated") {
 			Some(attr) => quote!(#attr),
 			None => quote!(),
 		};
+		let deprecation = find_attr(&method.attrs, "deprecated").map(parse_deprecated).transpose()?;
 
 		if blocking && method.sig.asyncness.is_some() {
 			return Err(syn::Error::new(method.sig.span(), "Blocking method must be synchronous"));
@@ -150,13 +203,19 @@ impl RpcMethod {
 			aliases,
 			blocking,
 			name,
+			version,
 			params,
 			param_kind,
 			returns,
 			signature: method,
 			docs,
 			deprecated,
+			deprecation,
 			with_extensions,
+			with_session,
+			idempotent,
+			deny_unknown_params,
+			meta,
 		})
 	}
 }
@@ -180,29 +239,66 @@ pub struct RpcSubscription {
 	pub aliases: Vec<String>,
 	pub unsubscribe_aliases: Vec<String>,
 	pub with_extensions: bool,
+	pub with_session: bool,
+	/// The method returns `impl Stream<Item = ...>` which is piped into the subscription
+	/// instead of taking a `PendingSubscriptionSink`.
+	pub returns_stream: bool,
+	/// Function returning the `PipeFromStreamConfig` used to pipe the stream into the subscription.
+	pub pipe_config: Option<syn::Path>,
+	/// The metadata of the subscription which the server inserts into the extensions of its requests.
+	pub meta: Vec<MetaEntry>,
 }
 
 impl RpcSubscription {
 	pub fn from_item(attr: syn::Attribute, mut sub: syn::TraitItemFn) -> syn::Result<Self> {
-		let [aliases, item, name, param_kind, unsubscribe, unsubscribe_aliases, with_extensions] =
-			AttributeMeta::parse(attr)?.retain([
-				"aliases",
-				"item",
-				"name",
-				"param_kind",
-				"unsubscribe",
-				"unsubscribe_aliases",
-				"with_extensions",
-			])?;
+		let [
+			aliases,
+			item,
+			meta,
+			name,
+			param_kind,
+			pipe_config,
+			unsubscribe,
+			unsubscribe_aliases,
+			with_extensions,
+			with_session,
+		] = AttributeMeta::parse(attr)?.retain([
+			"aliases",
+			"item",
+			"meta",
+			"name",
+			"param_kind",
+			"pipe_config",
+			"unsubscribe",
+			"unsubscribe_aliases",
+			"with_extensions",
+			"with_session",
+		])?;
 
 		let aliases = parse_aliases(aliases)?;
+		let meta = parse_meta(meta)?;
 		let map = name?.value::<NameMapping>()?;
 		let name = map.name;
 		let notif_name_override = map.mapped;
-		let item = item?.value()?;
+		let stream_item = stream_item(&sub.sig.output);
+		let returns_stream = stream_item.is_some();
+		// The `item` may be omitted if it can be inferred from the returned stream.
+		let item = match (item, stream_item) {
+			(Err(_), Some(item)) => item,
+			(item, _) => item?.value()?,
+		};
 		let param_kind = parse_param_kind(param_kind)?;
+		let pipe_config = optional(pipe_config, Argument::value::<syn::Path>)?;
 		let unsubscribe_aliases = parse_aliases(unsubscribe_aliases)?;
 		let with_extensions = optional(with_extensions, Argument::flag)?.is_some();
+		let with_session = optional(with_session, Argument::flag)?.is_some();
+
+		if let (Some(path), false) = (&pipe_config, returns_stream) {
+			return Err(syn::Error::new_spanned(
+				path,
+				"`pipe_config` is only supported by subscriptions returning `impl Stream<Item = T>`",
+			));
+		}
 
 		let docs = extract_doc_comments(&sub.attrs);
 		let unsubscribe = match parse_subscribe(unsubscribe)? {
@@ -242,11 +338,15 @@ impl RpcSubscription {
 			aliases,
 			docs,
 			with_extensions,
+			with_session,
+			returns_stream,
+			pipe_config,
+			meta,
 		})
 	}
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct RpcDescription {
 	/// Path to the `jsonrpsee` client types part.
 	pub(crate) jsonrpsee_client_path: Option<TokenStream2>,
@@ -260,6 +360,14 @@ pub struct RpcDescription {
 	/// Assuming that trait to which attribute is applied is named `Foo`, the generated
 	/// client trait will have `FooClient` name.
 	pub(crate) needs_client: bool,
+	/// Switch denoting that a mock implementing the client trait must be generated.
+	/// Assuming that trait to which attribute is applied is named `Foo`, the generated
+	/// mock will have `FooMock` name.
+	pub(crate) needs_mock: bool,
+	/// Switch denoting that TypeScript bindings must be generated.
+	/// Assuming that trait to which attribute is applied is named `Foo`, the generated
+	/// function returning the bindings will have `foo_typescript` name.
+	pub(crate) needs_typescript: bool,
 	/// Optional prefix for RPC namespace.
 	pub(crate) namespace: Option<String>,
 	/// Optional separator between namespace and method name. Defaults to `_`.
@@ -274,19 +382,35 @@ pub struct RpcDescription {
 	pub(crate) client_bounds: Option<Punctuated<syn::WherePredicate, Token![,]>>,
 	/// Optional user defined trait bounds for the server implementation.
 	pub(crate) server_bounds: Option<Punctuated<syn::WherePredicate, Token![,]>>,
+	/// Optional error type of the RPC API which the client decodes from the JSON-RPC errors.
+	pub(crate) error_ty: Option<syn::Type>,
 }
 
 impl RpcDescription {
 	pub fn from_item(attr: Attribute, mut item: syn::ItemTrait) -> syn::Result<Self> {
-		let [client, server, namespace, namespace_separator, client_bounds, server_bounds] =
-			AttributeMeta::parse(attr)?.retain([
-				"client",
-				"server",
-				"namespace",
-				"namespace_separator",
-				"client_bounds",
-				"server_bounds",
-			])?;
+		let [
+			client,
+			server,
+			namespace,
+			namespace_separator,
+			client_bounds,
+			server_bounds,
+			error,
+			mock,
+			typescript,
+			version_style,
+		] = AttributeMeta::parse(attr)?.retain([
+			"client",
+			"server",
+			"namespace",
+			"namespace_separator",
+			"client_bounds",
+			"server_bounds",
+			"error",
+			"mock",
+			"typescript",
+			"version_style",
+		])?;
 
 		let needs_server = optional(server, Argument::flag)?.is_some();
 		let needs_client = optional(client, Argument::flag)?.is_some();
@@ -294,6 +418,10 @@ impl RpcDescription {
 		let namespace_separator = optional(namespace_separator, Argument::string)?;
 		let client_bounds = optional(client_bounds, Argument::group)?;
 		let server_bounds = optional(server_bounds, Argument::group)?;
+		let error_ty = optional(error, Argument::value::<syn::Type>)?;
+		let needs_mock = optional(mock, Argument::flag)?.is_some();
+		let needs_typescript = optional(typescript, Argument::flag)?.is_some();
+		let version_style = parse_version_style(version_style)?;
 		if !needs_server && !needs_client {
 			return Err(syn::Error::new_spanned(&item.ident, "Either 'server' or 'client' attribute must be applied"));
 		}
@@ -305,6 +433,14 @@ impl RpcDescription {
 			));
 		}
 
+		if error_ty.is_some() && !needs_client {
+			return Err(syn::Error::new_spanned(&item.ident, "Attribute 'client' must be specified with 'error'"));
+		}
+
+		if needs_mock && !needs_client {
+			return Err(syn::Error::new_spanned(&item.ident, "Attribute 'client' must be specified with 'mock'"));
+		}
+
 		if server_bounds.is_some() && !needs_server {
 			return Err(syn::Error::new_spanned(
 				&item.ident,
@@ -340,7 +476,15 @@ impl RpcDescription {
 				if let Some(attr) = find_attr(&method.attrs, "method") {
 					is_method = true;
 
-					let method_data = RpcMethod::from_item(attr.clone(), method.clone())?;
+					let mut method_data = RpcMethod::from_item(attr.clone(), method.clone())?;
+
+					if let Some(version) = method_data.version {
+						let sep = namespace_separator.as_deref().unwrap_or("_");
+						method_data.name = match version_style {
+							VersionStyle::Suffix => format!("{}_v{version}", method_data.name),
+							VersionStyle::Namespace => format!("v{version}{sep}{}", method_data.name),
+						};
+					}
 
 					methods.push(method_data);
 				}
@@ -363,8 +507,15 @@ impl RpcDescription {
 						"Methods must have either 'method' or 'subscription' attribute",
 					));
 				}
+			} else if let syn::TraitItem::Type(assoc) = entry {
+				if !assoc.generics.params.is_empty() || assoc.default.is_some() {
+					return Err(syn::Error::new_spanned(
+						assoc,
+						"Associated types of RPC traits must not have generics or a default",
+					));
+				}
 			} else {
-				return Err(syn::Error::new_spanned(entry, "Only methods allowed in RPC traits"));
+				return Err(syn::Error::new_spanned(entry, "Only methods and associated types allowed in RPC traits"));
 			}
 		}
 
@@ -377,6 +528,8 @@ impl RpcDescription {
 			jsonrpsee_server_path,
 			needs_server,
 			needs_client,
+			needs_mock,
+			needs_typescript,
 			namespace,
 			namespace_separator,
 			trait_def: item,
@@ -384,19 +537,70 @@ impl RpcDescription {
 			subscriptions,
 			client_bounds,
 			server_bounds,
+			error_ty,
 		})
 	}
 
 	pub fn render(self) -> Result<TokenStream2, syn::Error> {
 		let server_impl = if self.needs_server { self.render_server()? } else { TokenStream2::new() };
-		let client_impl = if self.needs_client { self.render_client()? } else { TokenStream2::new() };
+
+		// The client side is implemented for any client, thus it takes the associated types as type parameters.
+		let this = self.with_assoc_types_as_params();
+		let client_impl = if this.needs_client { this.render_client()? } else { TokenStream2::new() };
+		let mock_impl = if this.needs_mock { this.render_mock()? } else { TokenStream2::new() };
+		let typescript_impl = if this.needs_typescript { this.render_typescript() } else { TokenStream2::new() };
 
 		Ok(quote! {
 			#server_impl
 			#client_impl
+			#mock_impl
+			#typescript_impl
 		})
 	}
 
+	/// Returns the description where the associated types of the trait are appended to its
+	/// type parameters and `Self::Foo` is replaced by `Foo`.
+	fn with_assoc_types_as_params(&self) -> Cow<'_, Self> {
+		let assoc_types: Vec<_> = crate::helpers::assoc_types(&self.trait_def).cloned().collect();
+		if assoc_types.is_empty() {
+			return Cow::Borrowed(self);
+		}
+
+		let mut this = self.clone();
+		let mut replace = ReplaceSelfAssoc { idents: assoc_types.iter().map(|assoc| assoc.ident.clone()).collect() };
+
+		this.trait_def.items.retain(|item| !matches!(item, syn::TraitItem::Type(_)));
+		for assoc in assoc_types {
+			this.trait_def.generics.params.push(syn::GenericParam::Type(syn::TypeParam {
+				attrs: Vec::new(),
+				ident: assoc.ident,
+				colon_token: assoc.colon_token,
+				bounds: assoc.bounds,
+				eq_token: None,
+				default: None,
+			}));
+		}
+		replace.visit_item_trait_mut(&mut this.trait_def);
+
+		for method in &mut this.methods {
+			replace.visit_trait_item_fn_mut(&mut method.signature);
+			method.params.iter_mut().for_each(|arg| replace.visit_rpc_fn_arg_mut(arg));
+			if let Some(returns) = &mut method.returns {
+				replace.visit_type_mut(returns);
+			}
+		}
+		for sub in &mut this.subscriptions {
+			replace.visit_trait_item_fn_mut(&mut sub.signature);
+			sub.params.iter_mut().for_each(|arg| replace.visit_rpc_fn_arg_mut(arg));
+			replace.visit_type_mut(&mut sub.item);
+		}
+		for bounds in [&mut this.client_bounds, &mut this.server_bounds].into_iter().flatten() {
+			bounds.iter_mut().for_each(|predicate| replace.visit_where_predicate_mut(predicate));
+		}
+
+		Cow::Owned(this)
+	}
+
 	/// Formats the identifier as a path relative to the resolved
 	/// `jsonrpsee` client path.
 	pub(crate) fn jrps_client_item(&self, item: impl quote::ToTokens) -> TokenStream2 {
@@ -426,6 +630,63 @@ impl RpcDescription {
 	}
 }
 
+/// Replaces the paths to the associated types of the trait, such as `Self::Hash`, by the type parameters
+/// which they are turned into.
+struct ReplaceSelfAssoc {
+	idents: Vec<syn::Ident>,
+}
+
+impl ReplaceSelfAssoc {
+	fn visit_rpc_fn_arg_mut(&mut self, arg: &mut RpcFnArg) {
+		self.visit_type_mut(&mut arg.ty);
+		if let Some(default) = &mut arg.default {
+			self.visit_expr_mut(default);
+		}
+	}
+}
+
+impl VisitMut for ReplaceSelfAssoc {
+	fn visit_path_mut(&mut self, path: &mut syn::Path) {
+		let is_assoc = path.leading_colon.is_none()
+			&& path.segments.len() >= 2
+			&& path.segments[0].ident == "Self"
+			&& path.segments[0].arguments.is_none()
+			&& self.idents.contains(&path.segments[1].ident);
+
+		if is_assoc {
+			path.segments = path.segments.clone().into_iter().skip(1).collect();
+		}
+		syn::visit_mut::visit_path_mut(self, path);
+	}
+}
+
+/// Parses the `since` and `note` of `#[deprecated]`, `#[deprecated = "note"]`
+/// or `#[deprecated(since = "..", note = "..")]`.
+fn parse_deprecated(attr: &Attribute) -> syn::Result<(Option<String>, Option<String>)> {
+	match &attr.meta {
+		syn::Meta::Path(_) => Ok((None, None)),
+		syn::Meta::NameValue(meta) => match &meta.value {
+			syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(note), .. }) => Ok((None, Some(note.value()))),
+			value => Err(syn::Error::new_spanned(value, "Expected a string literal")),
+		},
+		syn::Meta::List(_) => {
+			let mut since = None;
+			let mut note = None;
+			attr.parse_nested_meta(|meta| {
+				if meta.path.is_ident("since") {
+					since = Some(meta.value()?.parse::<syn::LitStr>()?.value());
+				} else if meta.path.is_ident("note") {
+					note = Some(meta.value()?.parse::<syn::LitStr>()?.value());
+				} else {
+					return Err(meta.error("Unknown argument, expected `since` or `note`"));
+				}
+				Ok(())
+			})?;
+			Ok((since, note))
+		}
+	}
+}
+
 fn parse_aliases(arg: Result<Argument, MissingArgument>) -> syn::Result<Vec<String>> {
 	let aliases = optional(arg, Argument::value::<Aliases>)?;
 
@@ -36,6 +36,8 @@ use syn::visit::{self, Visit};
 pub(crate) struct FindSubscriptionParams {
 	pub(crate) generic_sub_params: HashSet<Ident>,
 	pub(crate) all_type_params: HashSet<Ident>,
+	/// Associated types of the trait, referred to as `Self::Foo`.
+	pub(crate) assoc_types: HashSet<Ident>,
 }
 
 /// Visitor for the entire `RPC trait`.
@@ -111,11 +113,12 @@ impl FindSubscriptionParams {
 	/// generic parameters on the RPC trait as input in order to determine
 	/// whether a given ident is a generic type param or not when traversing
 	/// one or more types in `FindSubscriptionParams::visit`.
-	pub fn new(all_type_params: HashSet<Ident>) -> Self {
-		Self { generic_sub_params: HashSet::new(), all_type_params }
+	pub fn new(all_type_params: HashSet<Ident>, assoc_types: HashSet<Ident>) -> Self {
+		Self { generic_sub_params: HashSet::new(), all_type_params, assoc_types }
 	}
 
-	/// Visit path, if it's a leaf path and generic type param then add it as a subscription param.
+	/// Visit path, if it's a leaf path and generic type param or an associated type then add it as a
+	/// subscription param.
 	fn visit_path(&mut self, path: &syn::Path) {
 		if path.leading_colon.is_none() && path.segments.len() == 1 {
 			let id = &path.segments[0].ident;
@@ -123,6 +126,12 @@ impl FindSubscriptionParams {
 				self.generic_sub_params.insert(id.clone());
 			}
 		}
+		if path.leading_colon.is_none() && path.segments.len() == 2 && path.segments[0].ident == "Self" {
+			let id = &path.segments[1].ident;
+			if self.assoc_types.contains(id) {
+				self.generic_sub_params.insert(id.clone());
+			}
+		}
 		for segment in &path.segments {
 			self.visit_path_segment(segment);
 		}
@@ -235,7 +244,7 @@ mod tests {
 		exp.insert(id);
 		let generics = exp.clone();
 
-		assert_eq!(exp, FindSubscriptionParams::new(generics).visit(&[t]));
+		assert_eq!(exp, FindSubscriptionParams::new(generics, HashSet::new()).visit(&[t]));
 	}
 
 	#[test]
@@ -254,7 +263,7 @@ mod tests {
 		exp.insert(parse_quote!(B));
 		exp.insert(parse_quote!(C));
 
-		assert_eq!(exp, FindSubscriptionParams::new(generics).visit(&[t]));
+		assert_eq!(exp, FindSubscriptionParams::new(generics, HashSet::new()).visit(&[t]));
 	}
 
 	#[test]
@@ -272,6 +281,21 @@ mod tests {
 		exp.insert(parse_quote!(A));
 		exp.insert(parse_quote!(B));
 
-		assert_eq!(exp, FindSubscriptionParams::new(generics).visit(&[t]));
+		assert_eq!(exp, FindSubscriptionParams::new(generics, HashSet::new()).visit(&[t]));
+	}
+
+	#[test]
+	fn assoc_types() {
+		let t: Type = parse_quote!(Vec<(Self::Hash, A, Hash, Other::Block)>);
+
+		let mut assoc_types: HashSet<syn::Ident> = HashSet::new();
+		let mut exp = HashSet::new();
+
+		assoc_types.insert(parse_quote!(Hash));
+		assoc_types.insert(parse_quote!(Block));
+
+		exp.insert(parse_quote!(Hash));
+
+		assert_eq!(exp, FindSubscriptionParams::new(HashSet::new(), assoc_types).visit(&[t]));
 	}
 }
@@ -0,0 +1,53 @@
+use jsonrpsee::core::{RpcResult, SubscriptionResult};
+use jsonrpsee::proc_macros::rpc;
+use jsonrpsee::server::{MetaValue, MethodMeta, PendingSubscriptionSink};
+
+#[rpc(server, namespace = "myapi")]
+pub trait Rpc {
+	/// The metadata is attached to `myapi_getTemp` and its alias `getTemp`.
+	#[method(name = "getTemp", aliases = ["getTemp"], meta(rate_limit = 10, auth = "admin", weight = -0.5, cached))]
+	async fn temp(&self) -> RpcResult<u16>;
+
+	#[method(name = "getFood")]
+	async fn food(&self) -> RpcResult<String>;
+
+	/// The metadata is not attached to the unsubscribe method.
+	#[subscription(name = "subscribeTemp", item = u16, aliases = ["subTemp"], unsubscribe = "unsubscribeTemp", meta(public))]
+	async fn sub(&self) -> SubscriptionResult;
+}
+
+pub struct RpcServerImpl;
+
+#[jsonrpsee::core::async_trait]
+impl RpcServer for RpcServerImpl {
+	async fn temp(&self) -> RpcResult<u16> {
+		Ok(42)
+	}
+
+	async fn food(&self) -> RpcResult<String> {
+		Ok("pizza".to_string())
+	}
+
+	async fn sub(&self, pending: PendingSubscriptionSink) -> SubscriptionResult {
+		let _sink = pending.accept().await?;
+		Ok(())
+	}
+}
+
+fn main() {
+	let module = RpcServerImpl.into_rpc();
+
+	let meta = MethodMeta::new()
+		.with("rate_limit", MetaValue::Int(10))
+		.with("auth", "admin")
+		.with("weight", MetaValue::Float(-0.5))
+		.with("cached", true);
+	assert_eq!(module.method_meta("myapi_getTemp"), Some(&meta));
+	assert_eq!(module.method_meta("getTemp"), Some(&meta));
+	assert!(module.method_meta("myapi_getFood").is_none());
+
+	let meta = MethodMeta::new().with("public", true);
+	assert_eq!(module.method_meta("myapi_subscribeTemp"), Some(&meta));
+	assert_eq!(module.method_meta("subTemp"), Some(&meta));
+	assert!(module.method_meta("myapi_unsubscribeTemp").is_none());
+}
@@ -0,0 +1,67 @@
+//! Example of an RPC API with its own error type.
+
+use jsonrpsee::PendingSubscriptionSink;
+use jsonrpsee::core::client::ClientError;
+use jsonrpsee::core::{RpcResult, SubscriptionResult, async_trait};
+use jsonrpsee::proc_macros::{RpcError, rpc};
+use jsonrpsee::ws_client::WsClient;
+
+#[derive(Debug, RpcError)]
+pub enum MyError {
+	#[rpc_error(code = 1, message = "Not found")]
+	NotFound,
+	#[rpc_error(code = -1, message = "Out of range")]
+	OutOfRange(u8, u8),
+	#[rpc_error(code = 2)]
+	Invalid { reason: String },
+}
+
+#[rpc(client, server, error = MyError)]
+pub trait Rpc {
+	#[method(name = "foo")]
+	async fn async_method(&self, param_a: u8) -> Result<u16, MyError>;
+
+	#[method(name = "bar")]
+	fn sync_method(&self) -> RpcResult<u16>;
+
+	#[method(name = "notif")]
+	fn notif(&self);
+
+	#[subscription(name = "subscribe", item = String)]
+	async fn sub(&self) -> SubscriptionResult;
+}
+
+pub struct RpcServerImpl;
+
+#[async_trait]
+impl RpcServer for RpcServerImpl {
+	async fn async_method(&self, param_a: u8) -> Result<u16, MyError> {
+		match param_a {
+			0 => Err(MyError::NotFound),
+			1 => Err(MyError::OutOfRange(2, 3)),
+			2 => Err(MyError::Invalid { reason: "two".into() }),
+			n => Ok(n.into()),
+		}
+	}
+
+	fn sync_method(&self) -> RpcResult<u16> {
+		Ok(10)
+	}
+
+	fn notif(&self) {}
+
+	async fn sub(&self, pending: PendingSubscriptionSink) -> SubscriptionResult {
+		pending.accept().await?;
+		Ok(())
+	}
+}
+
+#[allow(dead_code)]
+async fn client(client: &WsClient) {
+	let _: Result<u16, ClientError<MyError>> = client.async_method(1).await;
+	let _: Result<u16, ClientError<MyError>> = client.sync_method().await;
+	let _: Result<(), jsonrpsee::core::client::Error> = client.notif().await;
+	let _ = client.sub().await.map_err(|e: ClientError<MyError>| e.rpc().is_some());
+}
+
+fn main() {}
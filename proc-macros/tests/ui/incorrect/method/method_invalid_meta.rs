@@ -0,0 +1,17 @@
+use jsonrpsee::proc_macros::rpc;
+
+// Meta values must be boolean, integer, float or string literals.
+#[rpc(client, server)]
+pub trait InvalidMeta {
+	#[method(name = "foo", meta(limit = b'a'))]
+	async fn async_method(&self) -> jsonrpsee::core::RpcResult<u8>;
+}
+
+// Meta keys must be unique.
+#[rpc(client, server)]
+pub trait DuplicateMeta {
+	#[method(name = "foo", meta(limit = 1, limit = 2))]
+	async fn async_method(&self) -> jsonrpsee::core::RpcResult<u8>;
+}
+
+fn main() {}
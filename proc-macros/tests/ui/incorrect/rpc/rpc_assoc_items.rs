@@ -1,6 +1,6 @@
 use jsonrpsee::proc_macros::rpc;
 
-// Associated items are forbidden.
+// Associated constants are forbidden.
 #[rpc(client, server)]
 pub trait AssociatedConst {
 	const WOO: usize;
@@ -9,9 +9,10 @@ pub trait AssociatedConst {
 	async fn async_method(&self) -> jsonrpsee::core::RpcResult<u8>;
 }
 
+// Generic associated types are forbidden.
 #[rpc(client, server)]
-pub trait AssociatedType {
-	type Woo;
+pub trait GenericAssociatedType {
+	type Woo<T>;
 
 	#[method(name = "foo")]
 	async fn async_method(&self) -> jsonrpsee::core::RpcResult<u8>;
@@ -0,0 +1,11 @@
+use jsonrpsee::proc_macros::RpcError;
+
+#[derive(RpcError)]
+pub enum DuplicateCode {
+	#[rpc_error(code = 1000)]
+	First,
+	#[rpc_error(code = 1000)]
+	Second,
+}
+
+fn main() {}
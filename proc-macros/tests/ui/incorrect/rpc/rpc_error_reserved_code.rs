@@ -0,0 +1,9 @@
+use jsonrpsee::proc_macros::RpcError;
+
+#[derive(RpcError)]
+pub enum ReservedCode {
+	#[rpc_error(code = -32601)]
+	MethodNotFound,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use jsonrpsee::proc_macros::rpc;
+
+#[rpc(server, error = jsonrpsee::types::ErrorObjectOwned)]
+pub trait ErrorWithoutClient {
+	#[method(name = "foo")]
+	fn method(&self) -> Result<u8, jsonrpsee::types::ErrorObjectOwned>;
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use jsonrpsee::proc_macros::rpc;
+
+#[rpc(server, mock)]
+pub trait MockWithoutClient {
+	#[method(name = "foo")]
+	fn method(&self) -> Result<u8, jsonrpsee::types::ErrorObjectOwned>;
+}
+
+fn main() {}
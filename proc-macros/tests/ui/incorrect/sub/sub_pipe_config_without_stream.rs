@@ -0,0 +1,15 @@
+use jsonrpsee::proc_macros::rpc;
+use jsonrpsee::server::PipeFromStreamConfig;
+
+fn config() -> PipeFromStreamConfig {
+	PipeFromStreamConfig::default()
+}
+
+// `pipe_config` is only supported by subscriptions returning a stream.
+#[rpc(client, server)]
+pub trait PipeConfigWithoutStream {
+	#[subscription(name = "sub", item = String, pipe_config = config)]
+	async fn sub(&self) -> jsonrpsee::core::SubscriptionResult;
+}
+
+fn main() {}
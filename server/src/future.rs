@@ -30,27 +30,29 @@ use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll};
 
-use futures_util::{Future, Stream, StreamExt};
+use futures_util::{Future, FutureExt, Stream, StreamExt};
 use pin_project::pin_project;
 use tokio::sync::{OwnedSemaphorePermit, Semaphore, TryAcquireError, watch};
 use tokio::time::Interval;
 use tokio_stream::wrappers::BroadcastStream;
 
+use crate::shutdown::{Drain, ShutdownConfig, ShutdownProgress};
+
 /// Create channel to determine whether
 /// the server shall continue to run or not.
 pub fn stop_channel() -> (StopHandle, ServerHandle) {
-	let (tx, rx) = tokio::sync::watch::channel(());
+	let (tx, rx) = tokio::sync::watch::channel(StopSignal::Running);
 	(StopHandle::new(rx), ServerHandle::new(tx))
 }
 
 /// Represent a stop handle which is a wrapper over a `multi-consumer receiver`
 /// and cloning [`StopHandle`] will get a separate instance of the underlying receiver.
 #[derive(Debug, Clone)]
-pub struct StopHandle(watch::Receiver<()>);
+pub struct StopHandle(watch::Receiver<StopSignal>);
 
 impl StopHandle {
 	/// Create a new stop handle.
-	pub(crate) fn new(rx: watch::Receiver<()>) -> Self {
+	pub(crate) fn new(rx: watch::Receiver<StopSignal>) -> Self {
 		Self(rx)
 	}
 
@@ -59,6 +61,25 @@ impl StopHandle {
 	pub async fn shutdown(mut self) {
 		let _ = self.0.changed().await;
 	}
+
+	/// Returns the state of the graceful shutdown if the server is draining its connections.
+	pub(crate) fn drain(&self) -> Option<Arc<Drain>> {
+		match &*self.0.borrow() {
+			StopSignal::Drain(drain) => Some(drain.clone()),
+			_ => None,
+		}
+	}
+}
+
+/// Signal sent from the [`ServerHandle`] to the [`StopHandle`]s.
+#[derive(Debug, Clone)]
+pub(crate) enum StopSignal {
+	/// The server is running.
+	Running,
+	/// The server is stopped.
+	Stop,
+	/// The server is stopped and drains its connections.
+	Drain(Arc<Drain>),
 }
 
 /// Error when the server has already been stopped.
@@ -71,17 +92,54 @@ pub struct AlreadyStoppedError;
 /// When all [`StopHandle`]'s have been `dropped` or `stop` has been called
 /// the server will be stopped.
 #[derive(Debug, Clone)]
-pub struct ServerHandle(Arc<watch::Sender<()>>);
+pub struct ServerHandle(Arc<watch::Sender<StopSignal>>);
 
 impl ServerHandle {
 	/// Create a new server handle.
-	pub(crate) fn new(tx: watch::Sender<()>) -> Self {
+	pub(crate) fn new(tx: watch::Sender<StopSignal>) -> Self {
 		Self(Arc::new(tx))
 	}
 
 	/// Tell the server to stop without waiting for the server to stop.
 	pub fn stop(&self) -> Result<(), AlreadyStoppedError> {
-		self.0.send(()).map_err(|_| AlreadyStoppedError)
+		self.0.send(StopSignal::Stop).map_err(|_| AlreadyStoppedError)
+	}
+
+	/// Stop the server gracefully and drain the open connections.
+	///
+	/// The shutdown happens in stages:
+	///
+	/// 1. The server stops accepting new connections.
+	/// 2. New calls are rejected with a shutdown error while the in-flight calls are completed.
+	/// 3. The active subscriptions are closed with a close notification.
+	/// 4. The WebSocket connections are closed with a close frame.
+	/// 5. Connections that are still open once the deadline has elapsed are closed forcibly.
+	///
+	/// The returned [`ShutdownProgress`] reports the progress of the shutdown.
+	///
+	/// Returns an error if the server has already been stopped or is being stopped.
+	pub fn graceful_shutdown(&self, config: ShutdownConfig) -> Result<ShutdownProgress, AlreadyStoppedError> {
+		if self.is_stopped() {
+			return Err(AlreadyStoppedError);
+		}
+
+		let (drain, events) = Drain::new(config);
+		let deadline = drain.deadline;
+		let mut drain = Some(Arc::new(drain));
+
+		self.0.send_if_modified(|signal| match signal {
+			StopSignal::Running => {
+				*signal = StopSignal::Drain(drain.take().expect("only taken once; qed"));
+				true
+			}
+			StopSignal::Stop | StopSignal::Drain(_) => false,
+		});
+
+		if drain.is_some() {
+			return Err(AlreadyStoppedError);
+		}
+
+		Ok(ShutdownProgress::new(events, deadline, self.clone().stopped().boxed()))
 	}
 
 	/// Wait for the server to stop.
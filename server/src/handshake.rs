@@ -0,0 +1,215 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Hook to accept or reject WebSocket connections during the handshake.
+
+use std::net::SocketAddr;
+
+use futures_util::future::BoxFuture;
+use http::header::SEC_WEBSOCKET_PROTOCOL;
+use http::{Extensions, HeaderMap, HeaderValue, StatusCode, Uri};
+use jsonrpsee_core::server::ConnectionId;
+
+use crate::{HttpBody, HttpRequest, HttpResponse, LOG_TARGET};
+
+/// Hook which is invoked before a WebSocket upgrade request is accepted and
+/// registered with [`ServerConfigBuilder::set_ws_handshake_hook`](crate::ServerConfigBuilder::set_ws_handshake_hook).
+///
+/// The hook may reject the connection with a custom HTTP response, attach data to the connection
+/// which is available to the method handlers via their [`Extensions`] and choose one of the
+/// subprotocols requested by the client.
+///
+/// ```
+/// use futures_util::future::{BoxFuture, FutureExt};
+/// use jsonrpsee_server::{HandshakeAccept, HandshakeReject, HandshakeRequest, ServerConfig, WsHandshakeHook};
+/// use http::StatusCode;
+///
+/// #[derive(Debug, Clone)]
+/// struct ApiKey(String);
+///
+/// #[derive(Debug)]
+/// struct RequireApiKey;
+///
+/// impl WsHandshakeHook for RequireApiKey {
+///     fn on_handshake(&self, req: HandshakeRequest) -> BoxFuture<'static, Result<HandshakeAccept, HandshakeReject>> {
+///         async move {
+///             let Some(key) = req.headers().get("x-api-key").and_then(|v| v.to_str().ok()) else {
+///                 return Err(HandshakeReject::new(StatusCode::UNAUTHORIZED, "missing API key"));
+///             };
+///
+///             let accept = HandshakeAccept::new().with_data(ApiKey(key.to_owned()));
+///
+///             match req.protocols().find(|p| *p == "jsonrpc") {
+///                 Some(protocol) => Ok(accept.with_protocol(protocol)),
+///                 None => Ok(accept),
+///             }
+///         }
+///         .boxed()
+///     }
+/// }
+///
+/// let config = ServerConfig::builder().set_ws_handshake_hook(RequireApiKey).build();
+/// ```
+pub trait WsHandshakeHook: Send + Sync + std::fmt::Debug + 'static {
+	/// Decide whether the WebSocket upgrade request is accepted.
+	fn on_handshake(&self, request: HandshakeRequest) -> BoxFuture<'static, Result<HandshakeAccept, HandshakeReject>>;
+}
+
+/// The WebSocket upgrade request passed to the [`WsHandshakeHook`].
+#[derive(Debug, Clone)]
+pub struct HandshakeRequest {
+	uri: Uri,
+	headers: HeaderMap,
+	remote_addr: Option<SocketAddr>,
+	conn_id: ConnectionId,
+}
+
+impl HandshakeRequest {
+	/// URI of the request.
+	pub fn uri(&self) -> &Uri {
+		&self.uri
+	}
+
+	/// Headers of the request.
+	pub fn headers(&self) -> &HeaderMap {
+		&self.headers
+	}
+
+	/// Remote address of the connection.
+	///
+	/// This is `None` when the connection isn't accepted by [`Server`](crate::Server),
+	/// such as when [`ws::connect`](crate::ws::connect) is used.
+	pub fn remote_addr(&self) -> Option<SocketAddr> {
+		self.remote_addr
+	}
+
+	/// Connection ID.
+	pub fn conn_id(&self) -> ConnectionId {
+		self.conn_id
+	}
+
+	/// The subprotocols requested by the client in the `Sec-WebSocket-Protocol` headers, in order of preference.
+	pub fn protocols(&self) -> impl Iterator<Item = &str> {
+		self.headers
+			.get_all(SEC_WEBSOCKET_PROTOCOL)
+			.iter()
+			.filter_map(|v| v.to_str().ok())
+			.flat_map(|v| v.split(','))
+			.map(str::trim)
+			.filter(|p| !p.is_empty())
+	}
+}
+
+/// Accept the WebSocket upgrade request.
+#[derive(Debug, Default)]
+pub struct HandshakeAccept {
+	extensions: Extensions,
+	protocol: Option<String>,
+}
+
+impl HandshakeAccept {
+	/// Accept the connection.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Attach data to the connection which is inserted into the [`Extensions`] of every call.
+	///
+	/// Data of the same type that was attached before is replaced.
+	pub fn with_data<T: Clone + Send + Sync + 'static>(mut self, data: T) -> Self {
+		self.extensions.insert(data);
+		self
+	}
+
+	/// Respond with a subprotocol in the `Sec-WebSocket-Protocol` header.
+	///
+	/// The subprotocol must be one of the [`HandshakeRequest::protocols`] requested by the client
+	/// otherwise the connection is rejected with `500 Internal Server Error`.
+	pub fn with_protocol(mut self, protocol: impl Into<String>) -> Self {
+		self.protocol = Some(protocol.into());
+		self
+	}
+}
+
+/// Reject the WebSocket upgrade request with a HTTP response.
+#[derive(Debug, Clone)]
+pub struct HandshakeReject {
+	status: StatusCode,
+	body: String,
+}
+
+impl HandshakeReject {
+	/// Reject the connection with the given status code and body.
+	pub fn new(status: StatusCode, body: impl Into<String>) -> Self {
+		Self { status, body: body.into() }
+	}
+
+	fn into_response(self) -> HttpResponse {
+		let mut rp = HttpResponse::new(HttpBody::from(self.body));
+		*rp.status_mut() = self.status;
+		rp
+	}
+}
+
+/// Run the hook for an upgrade request that soketto has accepted.
+///
+/// On success the attached data is inserted into the extensions of the request and the
+/// chosen subprotocol into the headers of the response, otherwise the rejection is returned.
+pub(crate) async fn run_hook<B>(
+	hook: &dyn WsHandshakeHook,
+	request: &mut HttpRequest<B>,
+	response: &mut http::Response<()>,
+	remote_addr: Option<SocketAddr>,
+	conn_id: ConnectionId,
+) -> Result<(), HttpResponse> {
+	let handshake_request =
+		HandshakeRequest { uri: request.uri().clone(), headers: request.headers().clone(), remote_addr, conn_id };
+
+	let accept = match hook.on_handshake(handshake_request.clone()).await {
+		Ok(accept) => accept,
+		Err(reject) => {
+			tracing::debug!(target: LOG_TARGET, "WS handshake rejected: {}", reject.status);
+			return Err(reject.into_response());
+		}
+	};
+
+	if let Some(protocol) = accept.protocol {
+		let value =
+			HeaderValue::from_str(&protocol).ok().filter(|_| handshake_request.protocols().any(|p| p == protocol));
+
+		let Some(value) = value else {
+			tracing::warn!(target: LOG_TARGET, "WS handshake hook chose subprotocol `{protocol}` which the client didn't request");
+			return Err(HandshakeReject::new(StatusCode::INTERNAL_SERVER_ERROR, "Invalid WebSocket subprotocol")
+				.into_response());
+		};
+
+		response.headers_mut().insert(SEC_WEBSOCKET_PROTOCOL, value);
+	}
+
+	request.extensions_mut().extend(accept.extensions);
+
+	Ok(())
+}
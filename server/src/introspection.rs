@@ -0,0 +1,397 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Introspection of the live connections and subscriptions of a server.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use crate::HttpBody;
+use http_body_util::BodyExt;
+use jsonrpsee_core::server::{ConnectionId, Methods, RpcModule};
+use jsonrpsee_types::{ErrorObjectOwned, SubscriptionId};
+use serde::{Deserialize, Serialize};
+use tokio_util::sync::CancellationToken;
+
+/// Transport protocol of a connection.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+	/// HTTP.
+	Http,
+	/// WebSocket.
+	WebSocket,
+}
+
+/// Snapshot of a live connection.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionDetails {
+	/// Connection ID.
+	pub conn_id: ConnectionId,
+	/// Remote address of the connection if known.
+	pub remote_addr: Option<SocketAddr>,
+	/// When the connection was established.
+	pub connected_at: SystemTime,
+	/// Transport protocol of the connection.
+	pub protocol: Protocol,
+	/// Number of bytes received from the connection.
+	///
+	/// Only the payloads are counted, i.e. the HTTP request bodies or the WebSocket messages
+	/// without the HTTP headers and the WebSocket framing.
+	pub bytes_in: u64,
+	/// Number of bytes sent to the connection.
+	///
+	/// Only the payloads are counted, i.e. the HTTP response bodies or the WebSocket messages
+	/// without the HTTP headers and the WebSocket framing.
+	pub bytes_out: u64,
+	/// Number of calls that are currently being processed.
+	pub in_flight_calls: usize,
+	/// Active subscriptions of the connection.
+	pub subscriptions: Vec<SubscriptionDetails>,
+}
+
+/// Snapshot of an active subscription.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubscriptionDetails {
+	/// Name of the subscribe method.
+	pub method: String,
+	/// Subscription ID.
+	#[serde(deserialize_with = "deserialize_owned_id")]
+	pub id: SubscriptionId<'static>,
+}
+
+fn deserialize_owned_id<'de, D: serde::Deserializer<'de>>(d: D) -> Result<SubscriptionId<'static>, D::Error> {
+	SubscriptionId::deserialize(d).map(SubscriptionId::into_owned)
+}
+
+/// Handle to inspect and manage the live connections of a server.
+///
+/// The handle is registered with [`ServerBuilder::set_introspection`](crate::ServerBuilder::set_introspection)
+/// and is cheap to clone.
+///
+/// HTTP connections are listed while they have a request in-flight and
+/// WebSocket connections for as long as they are open.
+///
+/// ```
+/// use jsonrpsee_server::{Introspection, RpcModule, Server};
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let introspection = Introspection::new();
+///     let server = Server::builder()
+///         .set_introspection(introspection.clone())
+///         .build("127.0.0.1:0")
+///         .await
+///         .unwrap();
+///
+///     let mut module = RpcModule::new(());
+///     module.merge(introspection.clone().into_rpc()).unwrap();
+///     let _handle = server.start(module);
+///
+///     assert!(introspection.connections().is_empty());
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Introspection {
+	connections: Arc<Mutex<HashMap<ConnectionId, Arc<TrackedConnection>>>>,
+}
+
+impl Introspection {
+	/// Create a new introspection handle.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Returns the live connections.
+	pub fn connections(&self) -> Vec<ConnectionDetails> {
+		let mut connections: Vec<_> =
+			self.connections.lock().expect("Mutex not poisoned; qed").values().map(|c| c.details()).collect();
+		connections.sort_by_key(|c| c.conn_id.0);
+		connections
+	}
+
+	/// Returns the live connection with the given ID.
+	pub fn connection(&self, conn_id: ConnectionId) -> Option<ConnectionDetails> {
+		self.get(conn_id).map(|c| c.details())
+	}
+
+	/// Returns the active subscriptions of the connection with the given ID.
+	pub fn subscriptions(&self, conn_id: ConnectionId) -> Vec<SubscriptionDetails> {
+		self.get(conn_id).map(|c| c.subscriptions()).unwrap_or_default()
+	}
+
+	/// Forcibly close a WebSocket connection.
+	///
+	/// Returns `false` if no such WebSocket connection exists.
+	pub fn close_connection(&self, conn_id: ConnectionId) -> bool {
+		match self.get(conn_id) {
+			Some(c) if c.protocol == Protocol::WebSocket => {
+				c.close.cancel();
+				true
+			}
+			_ => false,
+		}
+	}
+
+	/// Forcibly close a subscription as if the client had unsubscribed.
+	///
+	/// The client is sent a subscription error notification which tells it that the subscription was closed.
+	///
+	/// Returns `false` if no such subscription exists.
+	pub async fn close_subscription(&self, conn_id: ConnectionId, sub_id: &SubscriptionId<'_>) -> bool {
+		let Some(conn) = self.get(conn_id) else {
+			return false;
+		};
+
+		if !conn.subscriptions.lock().expect("Mutex not poisoned; qed").contains_key(sub_id) {
+			return false;
+		}
+
+		conn.methods.close_subscription(conn_id, sub_id, "Subscription closed by the server".into()).await
+	}
+
+	/// Convert the handle into a [`RpcModule`] with the admin methods:
+	///
+	/// - `admin_connections` returns the live connections.
+	/// - `admin_subscriptions(conn_id)` returns the active subscriptions of a connection.
+	/// - `admin_closeConnection(conn_id)` forcibly closes a WebSocket connection.
+	/// - `admin_closeSubscription(conn_id, sub_id)` forcibly closes a subscription.
+	///
+	/// These methods expose information about all clients and should only be
+	/// served to trusted clients.
+	pub fn into_rpc(self) -> RpcModule<Self> {
+		let mut module = RpcModule::new(self);
+
+		module
+			.register_method("admin_connections", |_, introspection, _| introspection.connections())
+			.expect("Method names are unique; qed");
+		module
+			.register_method("admin_subscriptions", |params, introspection, _| {
+				let conn_id: ConnectionId = params.one()?;
+				Ok::<_, ErrorObjectOwned>(introspection.subscriptions(conn_id))
+			})
+			.expect("Method names are unique; qed");
+		module
+			.register_method("admin_closeConnection", |params, introspection, _| {
+				let conn_id: ConnectionId = params.one()?;
+				Ok::<_, ErrorObjectOwned>(introspection.close_connection(conn_id))
+			})
+			.expect("Method names are unique; qed");
+		module
+			.register_async_method("admin_closeSubscription", |params, introspection, _| async move {
+				let (conn_id, sub_id): (ConnectionId, SubscriptionId) = params.parse()?;
+				Ok::<_, ErrorObjectOwned>(introspection.close_subscription(conn_id, &sub_id).await)
+			})
+			.expect("Method names are unique; qed");
+
+		module
+	}
+
+	fn get(&self, conn_id: ConnectionId) -> Option<Arc<TrackedConnection>> {
+		self.connections.lock().expect("Mutex not poisoned; qed").get(&conn_id).cloned()
+	}
+
+	/// Track a WebSocket connection until the returned registration is dropped.
+	pub(crate) fn register_ws(
+		&self,
+		conn_id: ConnectionId,
+		remote_addr: Option<SocketAddr>,
+		methods: Methods,
+	) -> Registration {
+		let conn = Arc::new(TrackedConnection::new(conn_id, remote_addr, Protocol::WebSocket, methods));
+		self.connections.lock().expect("Mutex not poisoned; qed").insert(conn_id, conn.clone());
+		Registration { introspection: self.clone(), conn }
+	}
+
+	/// Track a HTTP request until the returned registration is dropped.
+	///
+	/// Requests on the same connection share the same entry which
+	/// is removed once the last request has been answered.
+	pub(crate) fn register_http(
+		&self,
+		conn_id: ConnectionId,
+		remote_addr: Option<SocketAddr>,
+		methods: Methods,
+	) -> Registration {
+		let mut connections = self.connections.lock().expect("Mutex not poisoned; qed");
+		let conn = connections
+			.entry(conn_id)
+			.or_insert_with(|| Arc::new(TrackedConnection::new(conn_id, remote_addr, Protocol::Http, methods)))
+			.clone();
+		conn.in_flight.fetch_add(1, Ordering::Relaxed);
+		Registration { introspection: self.clone(), conn }
+	}
+}
+
+/// Statistics and control of a tracked connection.
+#[derive(Debug)]
+pub(crate) struct TrackedConnection {
+	conn_id: ConnectionId,
+	remote_addr: Option<SocketAddr>,
+	connected_at: SystemTime,
+	protocol: Protocol,
+	bytes_in: AtomicU64,
+	bytes_out: AtomicU64,
+	in_flight: AtomicUsize,
+	subscriptions: Mutex<HashMap<SubscriptionId<'static>, &'static str>>,
+	methods: Methods,
+	close: CancellationToken,
+}
+
+impl TrackedConnection {
+	fn new(conn_id: ConnectionId, remote_addr: Option<SocketAddr>, protocol: Protocol, methods: Methods) -> Self {
+		Self {
+			conn_id,
+			remote_addr,
+			connected_at: SystemTime::now(),
+			protocol,
+			bytes_in: AtomicU64::new(0),
+			bytes_out: AtomicU64::new(0),
+			in_flight: AtomicUsize::new(0),
+			subscriptions: Mutex::default(),
+			methods,
+			close: CancellationToken::new(),
+		}
+	}
+
+	fn details(&self) -> ConnectionDetails {
+		ConnectionDetails {
+			conn_id: self.conn_id,
+			remote_addr: self.remote_addr,
+			connected_at: self.connected_at,
+			protocol: self.protocol,
+			bytes_in: self.bytes_in.load(Ordering::Relaxed),
+			bytes_out: self.bytes_out.load(Ordering::Relaxed),
+			in_flight_calls: self.in_flight.load(Ordering::Relaxed),
+			subscriptions: self.subscriptions(),
+		}
+	}
+
+	fn subscriptions(&self) -> Vec<SubscriptionDetails> {
+		self.subscriptions
+			.lock()
+			.expect("Mutex not poisoned; qed")
+			.iter()
+			.map(|(id, method)| SubscriptionDetails { method: method.to_string(), id: id.clone() })
+			.collect()
+	}
+
+	pub(crate) fn record_in(&self, bytes: usize) {
+		self.bytes_in.fetch_add(bytes as u64, Ordering::Relaxed);
+	}
+
+	pub(crate) fn record_out(&self, bytes: usize) {
+		self.bytes_out.fetch_add(bytes as u64, Ordering::Relaxed);
+	}
+
+	/// Count a call as in-flight until the returned guard is dropped.
+	pub(crate) fn call_started(self: &Arc<Self>) -> InFlightCall {
+		self.in_flight.fetch_add(1, Ordering::Relaxed);
+		InFlightCall(self.clone())
+	}
+
+	pub(crate) fn add_subscription(&self, method: &'static str, id: SubscriptionId<'static>) {
+		self.subscriptions.lock().expect("Mutex not poisoned; qed").insert(id, method);
+	}
+
+	pub(crate) fn remove_subscription(&self, id: &SubscriptionId<'static>) {
+		self.subscriptions.lock().expect("Mutex not poisoned; qed").remove(id);
+	}
+
+	/// A future that resolves when the connection was closed by [`Introspection::close_connection`].
+	pub(crate) async fn terminated(&self) {
+		self.close.cancelled().await
+	}
+
+	pub(crate) fn is_terminated(&self) -> bool {
+		self.close.is_cancelled()
+	}
+}
+
+/// Decrements the number of in-flight calls when dropped.
+#[derive(Debug)]
+pub(crate) struct InFlightCall(Arc<TrackedConnection>);
+
+impl Drop for InFlightCall {
+	fn drop(&mut self) {
+		self.0.in_flight.fetch_sub(1, Ordering::Relaxed);
+	}
+}
+
+/// Keeps a connection tracked until dropped.
+#[derive(Debug)]
+pub(crate) struct Registration {
+	introspection: Introspection,
+	conn: Arc<TrackedConnection>,
+}
+
+impl Registration {
+	pub(crate) fn connection(&self) -> &Arc<TrackedConnection> {
+		&self.conn
+	}
+
+	/// Count the bytes of an HTTP request body while it's read.
+	pub(crate) fn count_request(&self, body: HttpBody) -> HttpBody {
+		let conn = self.conn.clone();
+		HttpBody::new(body.map_frame(move |frame| {
+			if let Some(data) = frame.data_ref() {
+				conn.record_in(data.len());
+			}
+			frame
+		}))
+	}
+
+	/// Count the bytes of an HTTP response body while it's written.
+	///
+	/// The connection stays registered until the response body has been dropped.
+	pub(crate) fn count_response(self, body: HttpBody) -> HttpBody {
+		HttpBody::new(body.map_frame(move |frame| {
+			if let Some(data) = frame.data_ref() {
+				self.conn.record_out(data.len());
+			}
+			frame
+		}))
+	}
+}
+
+impl Drop for Registration {
+	fn drop(&mut self) {
+		let mut connections = self.introspection.connections.lock().expect("Mutex not poisoned; qed");
+
+		let remove = match self.conn.protocol {
+			Protocol::WebSocket => true,
+			Protocol::Http => self.conn.in_flight.fetch_sub(1, Ordering::Relaxed) == 1,
+		};
+
+		if remove && connections.get(&self.conn.conn_id).is_some_and(|c| Arc::ptr_eq(c, &self.conn)) {
+			connections.remove(&self.conn.conn_id);
+		}
+	}
+}
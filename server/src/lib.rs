@@ -32,24 +32,33 @@
 #![cfg_attr(docsrs, feature(doc_cfg))]
 
 mod future;
+mod handshake;
+mod introspection;
+mod observer;
 mod server;
+mod shutdown;
 mod transport;
 mod utils;
 
 pub mod middleware;
+pub mod pubsub;
 
 #[cfg(test)]
 mod tests;
 
 pub use future::{AlreadyStoppedError, ConnectionGuard, ConnectionPermit, ServerHandle, StopHandle, stop_channel};
+pub use handshake::{HandshakeAccept, HandshakeReject, HandshakeRequest, WsHandshakeHook};
+pub use introspection::{ConnectionDetails, Introspection, Protocol, SubscriptionDetails};
 pub use jsonrpsee_core::error::RegisterMethodError;
 pub use jsonrpsee_core::server::*;
 pub use jsonrpsee_core::{id_providers::*, traits::IdProvider};
 pub use jsonrpsee_types as types;
+pub use observer::{CloseReason, ConnectionInfo, ConnectionObserver};
 pub use server::{
 	BatchRequestConfig, Builder as ServerBuilder, ConnectionState, PingConfig, Server, ServerConfig,
 	ServerConfigBuilder, TowerService, TowerServiceBuilder, TowerServiceNoHttp,
 };
+pub use shutdown::{ShutdownConfig, ShutdownEvent, ShutdownProgress};
 pub use tracing;
 
 pub use jsonrpsee_core::http_helpers::{Body as HttpBody, Request as HttpRequest, Response as HttpResponse};
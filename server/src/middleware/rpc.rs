@@ -32,12 +32,15 @@ pub use jsonrpsee_core::server::MethodResponse;
 use std::sync::Arc;
 
 use crate::ConnectionId;
+use crate::introspection::TrackedConnection;
+use crate::observer::ConnectionEvents;
 use jsonrpsee_core::server::{
-	BatchResponseBuilder, BoundedSubscriptions, MethodCallback, MethodSink, Methods, SubscriptionState,
+	BatchResponseBuilder, BoundedSubscriptions, DeprecationNotice, MethodCallback, MethodMeta, MethodSink, Methods,
+	SubscriptionObserver, SubscriptionState,
 };
 use jsonrpsee_core::traits::IdProvider;
-use jsonrpsee_types::ErrorObject;
 use jsonrpsee_types::error::{ErrorCode, reject_too_many_subscriptions};
+use jsonrpsee_types::{ErrorObject, SubscriptionId};
 
 /// JSON-RPC service middleware.
 #[derive(Clone, Debug)]
@@ -46,6 +49,16 @@ pub struct RpcService {
 	methods: Methods,
 	max_response_body_size: usize,
 	cfg: RpcServiceCfg,
+	deprecation_policy: DeprecationPolicy,
+}
+
+/// How calls to deprecated methods are handled.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct DeprecationPolicy {
+	/// Deprecated methods are rejected as if they weren't registered.
+	pub(crate) disabled: bool,
+	/// A `DeprecationNotice` is inserted into the extensions of the response.
+	pub(crate) notice: bool,
 }
 
 /// Configuration of the RpcService.
@@ -58,6 +71,8 @@ pub(crate) enum RpcServiceCfg {
 		bounded_subscriptions: BoundedSubscriptions,
 		sink: MethodSink,
 		id_provider: Arc<dyn IdProvider>,
+		events: ConnectionEvents,
+		tracked: Option<Arc<TrackedConnection>>,
 		_pending_calls: tokio::sync::mpsc::Sender<()>,
 	},
 }
@@ -69,8 +84,9 @@ impl RpcService {
 		max_response_body_size: usize,
 		conn_id: ConnectionId,
 		cfg: RpcServiceCfg,
+		deprecation_policy: DeprecationPolicy,
 	) -> Self {
-		Self { methods, max_response_body_size, conn_id, cfg }
+		Self { methods, max_response_body_size, conn_id, cfg, deprecation_policy }
 	}
 }
 
@@ -83,16 +99,32 @@ impl RpcServiceT for RpcService {
 		let conn_id = self.conn_id;
 		let max_response_body_size = self.max_response_body_size;
 
-		let Request { id, method, params, extensions, .. } = req;
+		let Request { id, method, params, mut extensions, .. } = req;
 		let params = jsonrpsee_types::Params::new(params.as_ref().map(|p| serde_json::value::RawValue::get(p)));
 
-		match self.methods.method_with_name(&method) {
+		let mut method = self.methods.method_with_name(&method);
+
+		if let Some((name, _)) = method {
+			if let Some(deprecated) = self.methods.deprecated_method(name) {
+				if self.deprecation_policy.disabled {
+					method = None;
+				} else {
+					deprecated.record_call();
+					if self.deprecation_policy.notice {
+						extensions
+							.insert(DeprecationNotice { method: name, deprecation: deprecated.deprecation().clone() });
+					}
+				}
+			}
+		}
+
+		match method {
 			None => {
 				let rp =
 					MethodResponse::error(id, ErrorObject::from(ErrorCode::MethodNotFound)).with_extensions(extensions);
 				ResponseFuture::ready(rp)
 			}
-			Some((_name, method)) => match method {
+			Some((name, method)) => match method {
 				MethodCallback::Async(callback) => {
 					let params = params.into_owned();
 					let id = id.into_owned();
@@ -109,6 +141,8 @@ impl RpcServiceT for RpcService {
 						bounded_subscriptions,
 						sink,
 						id_provider,
+						events,
+						tracked,
 						_pending_calls,
 					} = self.cfg.clone()
 					else {
@@ -119,8 +153,12 @@ impl RpcServiceT for RpcService {
 					};
 
 					if let Some(p) = bounded_subscriptions.acquire() {
+						let observer = (events.is_enabled() || tracked.is_some()).then(|| {
+							Arc::new(SubscriptionEvents { method: name, events, tracked })
+								as Arc<dyn SubscriptionObserver>
+						});
 						let conn_state =
-							SubscriptionState { conn_id, id_provider: &*id_provider.clone(), subscription_permit: p };
+							SubscriptionState { conn_id, id_provider: &*id_provider, subscription_permit: p, observer };
 
 						let fut = (callback)(id.clone(), params, sink, conn_state, extensions);
 						ResponseFuture::future(fut)
@@ -194,3 +232,78 @@ impl RpcServiceT for RpcService {
 		async move { MethodResponse::notification().with_extensions(n.extensions) }
 	}
 }
+
+/// Service which inserts the [`MethodMeta`] of the called methods into the extensions
+/// of the requests before they are processed by the RPC middleware.
+#[derive(Clone, Debug)]
+pub(crate) struct MethodMetaService<S> {
+	methods: Methods,
+	service: S,
+}
+
+impl<S> MethodMetaService<S> {
+	/// Create a new service.
+	pub(crate) fn new(methods: Methods, service: S) -> Self {
+		Self { methods, service }
+	}
+
+	fn method_meta(&self, method: &str) -> Option<MethodMeta> {
+		self.methods.method_meta(method).cloned()
+	}
+}
+
+impl<S: RpcServiceT> RpcServiceT for MethodMetaService<S> {
+	type BatchResponse = S::BatchResponse;
+	type MethodResponse = S::MethodResponse;
+	type NotificationResponse = S::NotificationResponse;
+
+	fn call<'a>(&self, mut req: Request<'a>) -> impl Future<Output = Self::MethodResponse> + Send + 'a {
+		if let Some(meta) = self.method_meta(req.method_name()) {
+			req.extensions_mut().insert(meta);
+		}
+		self.service.call(req)
+	}
+
+	fn batch<'a>(&self, mut batch: Batch<'a>) -> impl Future<Output = Self::BatchResponse> + Send + 'a {
+		for entry in batch.iter_mut().flatten() {
+			if let Some(meta) = self.method_meta(entry.method_name()) {
+				entry.extensions_mut().insert(meta);
+			}
+		}
+		self.service.batch(batch)
+	}
+
+	fn notification<'a>(
+		&self,
+		mut n: Notification<'a>,
+	) -> impl Future<Output = Self::NotificationResponse> + Send + 'a {
+		if let Some(meta) = self.method_meta(n.method_name()) {
+			n.extensions_mut().insert(meta);
+		}
+		self.service.notification(n)
+	}
+}
+
+/// Emits the subscription events of a connection and tracks its subscriptions for the introspection.
+#[derive(Debug)]
+struct SubscriptionEvents {
+	method: &'static str,
+	events: ConnectionEvents,
+	tracked: Option<Arc<TrackedConnection>>,
+}
+
+impl SubscriptionObserver for SubscriptionEvents {
+	fn on_open(&self, sub_id: &SubscriptionId<'static>) {
+		if let Some(tracked) = &self.tracked {
+			tracked.add_subscription(self.method, sub_id.clone());
+		}
+		self.events.subscription_opened(self.method);
+	}
+
+	fn on_close(&self, sub_id: &SubscriptionId<'static>) {
+		if let Some(tracked) = &self.tracked {
+			tracked.remove_subscription(sub_id);
+		}
+		self.events.subscription_closed(self.method);
+	}
+}
@@ -0,0 +1,209 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Hooks to observe the lifecycle of connections.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use http::HeaderMap;
+use jsonrpsee_core::server::ConnectionId;
+
+/// Observer of connection lifecycle events which is registered with
+/// [`ServerBuilder::set_connection_observer`](crate::ServerBuilder::set_connection_observer).
+///
+/// All callbacks have a default implementation that does nothing, so only the
+/// events of interest need to be implemented.
+///
+/// The callbacks are invoked inline by the connection tasks and must not block.
+///
+/// Like the `max_connections` limit of the server, the events concern the connections
+/// in the JSON-RPC sense: a WebSocket connection or a single HTTP request. Thus, for HTTP
+/// `on_accept` and `on_close` are called once per request rather than once per TCP connection,
+/// which may carry several requests. Every accepted connection is eventually closed, such that
+/// the example below counts the open WebSocket connections and the HTTP requests in progress.
+///
+/// ```
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use jsonrpsee_server::{CloseReason, ConnectionInfo, ConnectionObserver, Server};
+///
+/// #[derive(Debug, Default)]
+/// struct OpenConnections(AtomicUsize);
+///
+/// impl ConnectionObserver for OpenConnections {
+///     fn on_accept(&self, _conn: &ConnectionInfo) {
+///         self.0.fetch_add(1, Ordering::Relaxed);
+///     }
+///
+///     fn on_close(&self, _conn: &ConnectionInfo, _reason: &CloseReason) {
+///         self.0.fetch_sub(1, Ordering::Relaxed);
+///     }
+/// }
+///
+/// let builder = Server::builder().set_connection_observer(OpenConnections::default());
+/// ```
+pub trait ConnectionObserver: Send + Sync + std::fmt::Debug + 'static {
+	/// A connection was accepted.
+	///
+	/// For HTTP this is called for every request and for WebSocket once before the upgrade.
+	/// It's followed by a call to [`ConnectionObserver::on_close`] once the connection is closed.
+	fn on_accept(&self, _conn: &ConnectionInfo) {}
+
+	/// A connection was rejected because the maximum number of connections was reached.
+	fn on_reject(&self, _conn: &ConnectionInfo) {}
+
+	/// The server accepted a WebSocket upgrade request and responds with `101 Switching Protocols`.
+	fn on_ws_upgrade(&self, _conn: &ConnectionInfo) {}
+
+	/// The WebSocket handshake was completed and the connection is ready to process calls.
+	fn on_handshake_complete(&self, _conn: &ConnectionInfo) {}
+
+	/// The client didn't respond to a WebSocket ping within the inactivity limit.
+	///
+	/// `missed` is the number of consecutive missed pings.
+	fn on_ping_failure(&self, _conn: &ConnectionInfo, _missed: usize) {}
+
+	/// A connection that was accepted has been closed.
+	///
+	/// For HTTP this is called once the request has been answered.
+	fn on_close(&self, _conn: &ConnectionInfo, _reason: &CloseReason) {}
+
+	/// A subscription was opened on the connection.
+	fn on_subscription_opened(&self, _conn: &ConnectionInfo, _method: &str) {}
+
+	/// A subscription that was opened on the connection has been closed.
+	fn on_subscription_closed(&self, _conn: &ConnectionInfo, _method: &str) {}
+}
+
+/// Information about the connection passed to the [`ConnectionObserver`].
+#[derive(Debug, Clone)]
+pub struct ConnectionInfo {
+	remote_addr: Option<SocketAddr>,
+	conn_id: ConnectionId,
+	headers: HeaderMap,
+}
+
+impl ConnectionInfo {
+	/// Remote address of the connection.
+	///
+	/// This is `None` when the connection isn't accepted by [`Server`](crate::Server),
+	/// such as when the server is used as a [`TowerService`](crate::TowerService).
+	pub fn remote_addr(&self) -> Option<SocketAddr> {
+		self.remote_addr
+	}
+
+	/// Connection ID.
+	pub fn conn_id(&self) -> ConnectionId {
+		self.conn_id
+	}
+
+	/// Headers of the HTTP request that opened the connection.
+	pub fn headers(&self) -> &HeaderMap {
+		&self.headers
+	}
+}
+
+/// Reason why a connection was closed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CloseReason {
+	/// The HTTP request has been answered.
+	Completed,
+	/// The WebSocket upgrade request was rejected by the [`WsHandshakeHook`](crate::WsHandshakeHook).
+	HandshakeRejected,
+	/// The client closed the WebSocket connection.
+	ClosedByClient,
+	/// The server was stopped.
+	ServerStopped,
+	/// The client didn't respond to the WebSocket pings.
+	PingTimeout,
+	/// Sending to the client failed or the client didn't read the messages fast enough.
+	SendFailed,
+	/// The connection was closed with [`Introspection::close_connection`](crate::Introspection::close_connection).
+	Terminated,
+	/// The connection was terminated because of a transport error.
+	Error(String),
+}
+
+/// Emits the events of a single connection to the [`ConnectionObserver`] if one is registered.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ConnectionEvents(Option<(Arc<dyn ConnectionObserver>, Arc<ConnectionInfo>)>);
+
+impl ConnectionEvents {
+	/// Create the events of a connection.
+	///
+	/// The headers are only copied if an observer is registered.
+	pub(crate) fn new(
+		observer: Option<&Arc<dyn ConnectionObserver>>,
+		remote_addr: Option<SocketAddr>,
+		conn_id: ConnectionId,
+		headers: &HeaderMap,
+	) -> Self {
+		Self(observer.map(|o| (o.clone(), Arc::new(ConnectionInfo { remote_addr, conn_id, headers: headers.clone() }))))
+	}
+
+	/// Whether an observer is registered.
+	pub(crate) fn is_enabled(&self) -> bool {
+		self.0.is_some()
+	}
+
+	fn emit(&self, f: impl FnOnce(&dyn ConnectionObserver, &ConnectionInfo)) {
+		if let Some((observer, info)) = &self.0 {
+			f(&**observer, info);
+		}
+	}
+
+	pub(crate) fn accept(&self) {
+		self.emit(|o, c| o.on_accept(c));
+	}
+
+	pub(crate) fn reject(&self) {
+		self.emit(|o, c| o.on_reject(c));
+	}
+
+	pub(crate) fn ws_upgrade(&self) {
+		self.emit(|o, c| o.on_ws_upgrade(c));
+	}
+
+	pub(crate) fn handshake_complete(&self) {
+		self.emit(|o, c| o.on_handshake_complete(c));
+	}
+
+	pub(crate) fn ping_failure(&self, missed: usize) {
+		self.emit(|o, c| o.on_ping_failure(c, missed));
+	}
+
+	pub(crate) fn close(&self, reason: CloseReason) {
+		self.emit(|o, c| o.on_close(c, &reason));
+	}
+
+	pub(crate) fn subscription_opened(&self, method: &str) {
+		self.emit(|o, c| o.on_subscription_opened(c, method));
+	}
+
+	pub(crate) fn subscription_closed(&self, method: &str) {
+		self.emit(|o, c| o.on_subscription_closed(c, method));
+	}
+}
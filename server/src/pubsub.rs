@@ -0,0 +1,369 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! TCP based reference broker for the [`PubSubBackend`] trait which makes it possible
+//! to share the topics of [`PubSubHub`](jsonrpsee_core::server::PubSubHub)s between servers.
+//!
+//! The broker and [`TcpBackend`] exchange newline-delimited JSON frames:
+//!
+//! - `{"op":"subscribe","topic":"<topic>"}` and `{"op":"unsubscribe","topic":"<topic>"}` manage the
+//!   topics of a connection.
+//! - `{"op":"publish","topic":"<topic>","message":<message>}` publishes a message on a topic.
+//! - `{"op":"message","topic":"<topic>","message":<message>}` is sent by the broker to all connections
+//!   subscribed to the topic.
+//!
+//! This is a reference implementation without persistence, authentication or reconnects.
+//! Messages to connections that can't keep up are dropped.
+
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use futures_util::future::{self, BoxFuture, FutureExt};
+use futures_util::stream::{BoxStream, StreamExt};
+use jsonrpsee_core::server::{PubSubBackend, PubSubError};
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_util::codec::{FramedRead, LinesCodec};
+
+use crate::LOG_TARGET;
+
+/// Max number of messages buffered per connection or subscription before messages are dropped.
+const BUFFER_CAPACITY: usize = 1024;
+/// Max length of a frame in bytes, connections which send longer frames are closed.
+const MAX_FRAME_LEN: usize = 10 * 1024 * 1024;
+
+type Subscribers<T> = Mutex<HashMap<String, HashMap<u64, mpsc::Sender<T>>>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Op {
+	Subscribe,
+	Unsubscribe,
+	Publish,
+	Message,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Frame<'a> {
+	op: Op,
+	#[serde(borrow)]
+	topic: Cow<'a, str>,
+	#[serde(borrow, default, skip_serializing_if = "Option::is_none")]
+	message: Option<&'a RawValue>,
+}
+
+impl Frame<'_> {
+	fn to_line(&self) -> String {
+		let mut line = serde_json::to_string(self).expect("Serialize infallible; qed");
+		line.push('\n');
+		line
+	}
+}
+
+fn read_lines<R: AsyncRead>(reader: R) -> FramedRead<R, LinesCodec> {
+	FramedRead::new(reader, LinesCodec::new_with_max_length(MAX_FRAME_LEN))
+}
+
+async fn write_lines<W: AsyncWrite + Unpin, L: AsRef<str>>(mut writer: W, mut rx: mpsc::Receiver<L>) {
+	while let Some(line) = rx.recv().await {
+		if writer.write_all(line.as_ref().as_bytes()).await.is_err() {
+			break;
+		}
+	}
+}
+
+/// Remove the subscriber `id` from `topic` and returns whether the topic has no subscribers left.
+fn remove_subscriber<T>(subscribers: &Subscribers<T>, topic: &str, id: u64) -> bool {
+	let mut subscribers = subscribers.lock().expect("Mutex not poisoned; qed");
+
+	let Some(s) = subscribers.get_mut(topic) else {
+		return false;
+	};
+
+	s.remove(&id);
+	if s.is_empty() {
+		subscribers.remove(topic);
+		true
+	} else {
+		false
+	}
+}
+
+/// TCP broker that forwards the messages published on a topic to all connections subscribed to it.
+///
+/// ```no_run
+/// use jsonrpsee_server::pubsub::{TcpBackend, TcpBroker};
+/// use jsonrpsee_server::PubSubHub;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let broker = TcpBroker::bind("127.0.0.1:0").await.unwrap();
+///     let addr = broker.local_addr().unwrap();
+///     tokio::spawn(broker.run());
+///
+///     // Each server connects its hub to the broker.
+///     let hub = PubSubHub::with_backend(TcpBackend::connect(addr).await.unwrap());
+/// }
+/// ```
+#[derive(Debug)]
+pub struct TcpBroker {
+	listener: TcpListener,
+}
+
+#[derive(Debug, Default)]
+struct BrokerState {
+	topics: Subscribers<Arc<str>>,
+	next_id: AtomicU64,
+}
+
+impl TcpBroker {
+	/// Bind the broker to `addr`.
+	pub async fn bind(addr: impl ToSocketAddrs) -> io::Result<Self> {
+		Ok(Self { listener: TcpListener::bind(addr).await? })
+	}
+
+	/// Get the address the broker is listening on.
+	pub fn local_addr(&self) -> io::Result<SocketAddr> {
+		self.listener.local_addr()
+	}
+
+	/// Run the broker until the future is dropped or accepting a connection fails.
+	pub async fn run(self) -> io::Result<()> {
+		let state = Arc::new(BrokerState::default());
+
+		loop {
+			let (socket, remote_addr) = self.listener.accept().await?;
+			tracing::debug!(target: LOG_TARGET, "Pub/sub broker accepted connection from {remote_addr}");
+			tokio::spawn(handle_broker_connection(socket, state.clone()));
+		}
+	}
+}
+
+async fn handle_broker_connection(socket: TcpStream, state: Arc<BrokerState>) {
+	let id = state.next_id.fetch_add(1, Ordering::Relaxed);
+	let (reader, writer) = socket.into_split();
+	let (tx, rx) = mpsc::channel::<Arc<str>>(BUFFER_CAPACITY);
+	let mut subscribed = HashSet::new();
+
+	tokio::spawn(write_lines(writer, rx));
+
+	let mut lines = read_lines(reader);
+
+	while let Some(line) = lines.next().await {
+		let line = match line {
+			Ok(line) => line,
+			Err(e) => {
+				tracing::debug!(target: LOG_TARGET, "Pub/sub broker closed connection: {e}");
+				break;
+			}
+		};
+
+		let frame: Frame = match serde_json::from_str(&line) {
+			Ok(frame) => frame,
+			Err(e) => {
+				tracing::debug!(target: LOG_TARGET, "Pub/sub broker received invalid frame: {e}");
+				continue;
+			}
+		};
+
+		match frame.op {
+			Op::Subscribe => {
+				let topic = frame.topic.into_owned();
+				state
+					.topics
+					.lock()
+					.expect("Mutex not poisoned; qed")
+					.entry(topic.clone())
+					.or_default()
+					.insert(id, tx.clone());
+				subscribed.insert(topic);
+			}
+			Op::Unsubscribe => {
+				remove_subscriber(&state.topics, &frame.topic, id);
+				subscribed.remove(frame.topic.as_ref());
+			}
+			Op::Publish => {
+				let topic = frame.topic.as_ref();
+				// The message is serialized once for all subscribers.
+				let line: Arc<str> =
+					Frame { op: Op::Message, topic: Cow::Borrowed(topic), message: frame.message }.to_line().into();
+				let topics = state.topics.lock().expect("Mutex not poisoned; qed");
+
+				for subscriber in topics.get(topic).into_iter().flat_map(HashMap::values) {
+					if subscriber.try_send(line.clone()).is_err() {
+						tracing::debug!(target: LOG_TARGET, "Pub/sub broker dropped message on topic `{topic}` to slow connection");
+					}
+				}
+			}
+			Op::Message => (),
+		}
+	}
+
+	for topic in subscribed {
+		remove_subscriber(&state.topics, &topic, id);
+	}
+}
+
+/// [`PubSubBackend`] that publishes and subscribes via a [`TcpBroker`].
+///
+/// All topics share one connection to the broker. Once the connection is closed all
+/// subscription streams are terminated and publishing fails.
+///
+/// Publishing waits until there is room in the queue of frames to the broker, such that
+/// messages are not dropped when publishing faster than the broker connection can keep up.
+#[derive(Debug, Clone)]
+pub struct TcpBackend {
+	inner: Arc<BackendInner>,
+}
+
+#[derive(Debug)]
+struct BackendInner {
+	tx: mpsc::Sender<String>,
+	subscribers: Subscribers<Box<RawValue>>,
+	next_id: AtomicU64,
+}
+
+impl BackendInner {
+	fn send_frame(&self, op: Op, topic: &str, message: Option<&RawValue>) -> Result<(), PubSubError> {
+		let line = Frame { op, topic: Cow::Borrowed(topic), message }.to_line();
+
+		self.tx.try_send(line).map_err(|e| match e {
+			mpsc::error::TrySendError::Full(_) => PubSubError::Backend("Too many pending frames to the broker".into()),
+			mpsc::error::TrySendError::Closed(_) => broker_closed(),
+		})
+	}
+}
+
+fn broker_closed() -> PubSubError {
+	PubSubError::Backend("The connection to the broker is closed".into())
+}
+
+/// Unsubscribes from the topic once the subscription stream is dropped.
+struct SubscriptionGuard {
+	inner: Arc<BackendInner>,
+	topic: String,
+	id: u64,
+}
+
+impl Drop for SubscriptionGuard {
+	fn drop(&mut self) {
+		if remove_subscriber(&self.inner.subscribers, &self.topic, self.id) {
+			let _ = self.inner.send_frame(Op::Unsubscribe, &self.topic, None);
+		}
+	}
+}
+
+impl TcpBackend {
+	/// Connect to the [`TcpBroker`] at `addr`.
+	pub async fn connect(addr: impl ToSocketAddrs) -> io::Result<Self> {
+		let (reader, writer) = TcpStream::connect(addr).await?.into_split();
+		let (tx, rx) = mpsc::channel(BUFFER_CAPACITY);
+		let inner = Arc::new(BackendInner { tx, subscribers: Default::default(), next_id: AtomicU64::new(0) });
+
+		let write = tokio::spawn(write_lines(writer, rx));
+
+		let backend = inner.clone();
+		tokio::spawn(async move {
+			let mut lines = read_lines(reader);
+
+			while let Some(line) = lines.next().await {
+				let line = match line {
+					Ok(line) => line,
+					Err(e) => {
+						tracing::debug!(target: LOG_TARGET, "Pub/sub backend closed connection to broker: {e}");
+						break;
+					}
+				};
+
+				let (topic, message) = match serde_json::from_str::<Frame>(&line) {
+					Ok(Frame { op: Op::Message, topic, message: Some(message) }) => (topic, message),
+					Ok(_) => continue,
+					Err(e) => {
+						tracing::debug!(target: LOG_TARGET, "Pub/sub backend received invalid frame: {e}");
+						continue;
+					}
+				};
+
+				let subscribers = backend.subscribers.lock().expect("Mutex not poisoned; qed");
+				for subscriber in subscribers.get(topic.as_ref()).into_iter().flat_map(HashMap::values) {
+					if subscriber.try_send(message.to_owned()).is_err() {
+						tracing::debug!(target: LOG_TARGET, "Pub/sub backend dropped message on topic `{topic}` to slow subscriber");
+					}
+				}
+			}
+
+			// Close the connection such that publishing fails and terminate all subscription streams.
+			write.abort();
+			backend.subscribers.lock().expect("Mutex not poisoned; qed").clear();
+		});
+
+		Ok(Self { inner })
+	}
+}
+
+impl PubSubBackend for TcpBackend {
+	fn publish(&self, topic: &str, message: Box<RawValue>) -> BoxFuture<'static, Result<(), PubSubError>> {
+		let line = Frame { op: Op::Publish, topic: Cow::Borrowed(topic), message: Some(&message) }.to_line();
+		let tx = self.inner.tx.clone();
+
+		async move { tx.send(line).await.map_err(|_| broker_closed()) }.boxed()
+	}
+
+	fn subscribe(&self, topic: &str) -> BoxFuture<'static, Result<BoxStream<'static, Box<RawValue>>, PubSubError>> {
+		let id = self.inner.next_id.fetch_add(1, Ordering::Relaxed);
+		let (tx, rx) = mpsc::channel(BUFFER_CAPACITY);
+
+		let first = {
+			let mut subscribers = self.inner.subscribers.lock().expect("Mutex not poisoned; qed");
+			let s = subscribers.entry(topic.to_owned()).or_default();
+			s.insert(id, tx);
+			s.len() == 1
+		};
+
+		let guard = SubscriptionGuard { inner: self.inner.clone(), topic: topic.to_owned(), id };
+
+		if first {
+			if let Err(e) = self.inner.send_frame(Op::Subscribe, topic, None) {
+				return future::ready(Err(e)).boxed();
+			}
+		}
+
+		let stream = ReceiverStream::new(rx).map(move |msg| {
+			let _ = &guard;
+			msg
+		});
+
+		future::ready(Ok(stream.boxed())).boxed()
+	}
+}
@@ -32,8 +32,13 @@ use std::sync::atomic::AtomicU32;
 use std::task::Poll;
 use std::time::Duration;
 
-use crate::future::{ConnectionGuard, ServerHandle, SessionClose, SessionClosedFuture, StopHandle, session_close};
-use crate::middleware::rpc::{RpcService, RpcServiceCfg};
+use crate::future::{
+	ConnectionGuard, ServerHandle, SessionClose, SessionClosedFuture, StopHandle, StopSignal, session_close,
+};
+use crate::handshake::{self, WsHandshakeHook};
+use crate::introspection::Introspection;
+use crate::middleware::rpc::{DeprecationPolicy, MethodMetaService, RpcService, RpcServiceCfg};
+use crate::observer::{CloseReason, ConnectionEvents, ConnectionObserver};
 use crate::transport::ws::BackgroundTaskParams;
 use crate::transport::{http, ws};
 use crate::utils::deserialize_with_ext;
@@ -46,7 +51,9 @@ use hyper_util::rt::{TokioExecutor, TokioIo};
 use jsonrpsee_core::id_providers::RandomIntegerIdProvider;
 use jsonrpsee_core::middleware::{Batch, BatchEntry, BatchEntryErr, RpcServiceBuilder, RpcServiceT};
 use jsonrpsee_core::server::helpers::prepare_error;
-use jsonrpsee_core::server::{BoundedSubscriptions, ConnectionId, MethodResponse, MethodSink, Methods};
+use jsonrpsee_core::server::{
+	BoundedSubscriptions, BufferBudget, ConnectionId, MethodResponse, MethodSink, Methods, Session,
+};
 use jsonrpsee_core::traits::IdProvider;
 use jsonrpsee_core::{BoxError, JsonRawValue, TEN_MB_SIZE_BYTES};
 use jsonrpsee_types::error::{
@@ -110,7 +117,7 @@ where
 	/// This will run on the tokio runtime until the server is stopped or the `ServerHandle` is dropped.
 	pub fn start(mut self, methods: impl Into<Methods>) -> ServerHandle {
 		let methods = methods.into();
-		let (stop_tx, stop_rx) = watch::channel(());
+		let (stop_tx, stop_rx) = watch::channel(StopSignal::Running);
 
 		let stop_handle = StopHandle::new(stop_rx);
 
@@ -190,6 +197,10 @@ pub struct ServerConfig {
 	pub(crate) enable_ws: bool,
 	/// Number of messages that server is allowed to `buffer` until backpressure kicks in.
 	pub(crate) message_buffer_capacity: u32,
+	/// Maximum number of bytes buffered per connection before it's closed.
+	pub(crate) message_buffer_byte_limit: Option<u32>,
+	/// Maximum number of bytes buffered per subscription until backpressure kicks in.
+	pub(crate) subscription_buffer_byte_limit: Option<u32>,
 	/// Ping settings.
 	pub(crate) ping_config: Option<PingConfig>,
 	/// ID provider.
@@ -200,6 +211,33 @@ pub struct ServerConfig {
 	pub(crate) keep_alive: Option<std::time::Duration>,
 	/// `KEEP_ALIVE_TIMEOUT` duration.
 	pub(crate) keep_alive_timeout: Duration,
+	/// Observer of connection lifecycle events.
+	pub(crate) observer: Option<Arc<dyn ConnectionObserver>>,
+	/// Introspection of the live connections.
+	pub(crate) introspection: Option<Introspection>,
+	/// Hook invoked before a WebSocket upgrade request is accepted.
+	pub(crate) ws_handshake_hook: Option<Arc<dyn WsHandshakeHook>>,
+	/// How calls to deprecated methods are handled.
+	pub(crate) deprecation_policy: DeprecationPolicy,
+}
+
+impl ServerConfig {
+	/// Create the sink of a WebSocket connection.
+	pub(crate) fn method_sink(&self, tx: mpsc::Sender<Box<JsonRawValue>>) -> MethodSink {
+		let sink = MethodSink::new(tx);
+
+		if self.message_buffer_byte_limit.is_none() && self.subscription_buffer_byte_limit.is_none() {
+			return sink;
+		}
+
+		let limit = self.message_buffer_byte_limit.unwrap_or(u32::MAX);
+		let mut budget = BufferBudget::new(limit as usize);
+		if let Some(limit) = self.subscription_buffer_byte_limit {
+			budget = budget.with_subscription_limit(limit as usize);
+		}
+
+		sink.with_budget(budget)
+	}
 }
 
 /// The builder to configure and create a JSON-RPC server configuration.
@@ -223,6 +261,10 @@ pub struct ServerConfigBuilder {
 	enable_ws: bool,
 	/// Number of messages that server is allowed to `buffer` until backpressure kicks in.
 	message_buffer_capacity: u32,
+	/// Maximum number of bytes buffered per connection before it's closed.
+	message_buffer_byte_limit: Option<u32>,
+	/// Maximum number of bytes buffered per subscription until backpressure kicks in.
+	subscription_buffer_byte_limit: Option<u32>,
 	/// Ping settings.
 	ping_config: Option<PingConfig>,
 	/// ID provider.
@@ -233,6 +275,10 @@ pub struct ServerConfigBuilder {
 	keep_alive: Option<std::time::Duration>,
 	/// `KEEP_ALIVE_TIMEOUT` duration.
 	keep_alive_timeout: std::time::Duration,
+	/// Hook invoked before a WebSocket upgrade request is accepted.
+	ws_handshake_hook: Option<Arc<dyn WsHandshakeHook>>,
+	/// How calls to deprecated methods are handled.
+	deprecation_policy: DeprecationPolicy,
 }
 
 /// Builder for [`TowerService`].
@@ -368,12 +414,16 @@ impl Default for ServerConfigBuilder {
 			enable_http: true,
 			enable_ws: true,
 			message_buffer_capacity: 1024,
+			message_buffer_byte_limit: None,
+			subscription_buffer_byte_limit: None,
 			ping_config: None,
 			id_provider: Arc::new(RandomIntegerIdProvider),
 			tcp_no_delay: true,
 			keep_alive: None,
 			//same as `hyper` default
 			keep_alive_timeout: Duration::from_secs(20),
+			ws_handshake_hook: None,
+			deprecation_policy: DeprecationPolicy::default(),
 		}
 	}
 }
@@ -469,6 +519,36 @@ impl ServerConfigBuilder {
 		self
 	}
 
+	/// Limit the number of bytes that are buffered per connection.
+	///
+	/// Unlike [`ServerConfigBuilder::set_message_buffer_capacity`] this takes the size of the
+	/// messages into account. If a message doesn't fit into the buffer the client is regarded as
+	/// a slow consumer: the buffered messages are discarded, an error with the code
+	/// [`SLOW_CONSUMER_CODE`](jsonrpsee_types::error::SLOW_CONSUMER_CODE) is sent to the client
+	/// and the connection is closed with a WebSocket close frame. This happens as soon as the message
+	/// is rejected, not only once the next buffered message is sent out.
+	///
+	/// A single message is always buffered regardless of its size if the buffer is empty.
+	///
+	/// Default: unlimited.
+	pub fn set_message_buffer_byte_limit(mut self, bytes: u32) -> Self {
+		self.message_buffer_byte_limit = Some(bytes);
+		self
+	}
+
+	/// Limit the number of bytes that each subscription may buffer on a connection.
+	///
+	/// Once the limit is reached [`SubscriptionSink::send`](jsonrpsee_core::server::SubscriptionSink::send)
+	/// waits and [`SubscriptionSink::try_send`](jsonrpsee_core::server::SubscriptionSink::try_send) fails
+	/// until the buffered messages of the subscription have been sent out. This prevents a single subscription
+	/// from exceeding the [byte limit of the connection](ServerConfigBuilder::set_message_buffer_byte_limit).
+	///
+	/// Default: unlimited.
+	pub fn set_subscription_buffer_byte_limit(mut self, bytes: u32) -> Self {
+		self.subscription_buffer_byte_limit = Some(bytes);
+		self
+	}
+
 	/// Enable WebSocket ping/pong on the server.
 	///
 	/// Default: pings are disabled.
@@ -541,6 +621,33 @@ impl ServerConfigBuilder {
 		self
 	}
 
+	/// Register a hook which is invoked before a WebSocket upgrade request is accepted.
+	///
+	/// See [`WsHandshakeHook`] for what the hook may do.
+	pub fn set_ws_handshake_hook<H: WsHandshakeHook>(mut self, hook: H) -> Self {
+		self.ws_handshake_hook = Some(Arc::new(hook));
+		self
+	}
+
+	/// Reject the calls to deprecated methods as if the methods weren't registered.
+	///
+	/// See [`Methods::deprecate`](jsonrpsee_core::server::Methods::deprecate) for how methods are deprecated.
+	///
+	/// Default: deprecated methods are enabled.
+	pub fn disable_deprecated_methods(mut self) -> Self {
+		self.deprecation_policy.disabled = true;
+		self
+	}
+
+	/// Insert a [`DeprecationNotice`](jsonrpsee_core::server::DeprecationNotice) into the extensions
+	/// of the responses of calls to deprecated methods which is available to the RPC middleware.
+	///
+	/// Default: disabled.
+	pub fn enable_deprecation_notice(mut self) -> Self {
+		self.deprecation_policy.notice = true;
+		self
+	}
+
 	/// Build the [`ServerConfig`].
 	pub fn build(self) -> ServerConfig {
 		ServerConfig {
@@ -553,11 +660,17 @@ impl ServerConfigBuilder {
 			enable_http: self.enable_http,
 			enable_ws: self.enable_ws,
 			message_buffer_capacity: self.message_buffer_capacity,
+			message_buffer_byte_limit: self.message_buffer_byte_limit,
+			subscription_buffer_byte_limit: self.subscription_buffer_byte_limit,
 			ping_config: self.ping_config,
 			id_provider: self.id_provider,
 			tcp_no_delay: self.tcp_no_delay,
 			keep_alive: self.keep_alive,
 			keep_alive_timeout: self.keep_alive_timeout,
+			observer: None,
+			introspection: None,
+			ws_handshake_hook: self.ws_handshake_hook,
+			deprecation_policy: self.deprecation_policy,
 		}
 	}
 }
@@ -609,6 +722,8 @@ impl<RpcMiddleware, HttpMiddleware> TowerServiceBuilder<RpcMiddleware, HttpMiddl
 				conn_id,
 				conn_guard: self.conn_guard,
 				server_cfg: self.server_cfg,
+				session: Session::new(),
+				remote_addr: None,
 			},
 			on_session_close: None,
 		};
@@ -659,7 +774,24 @@ impl<RpcMiddleware, HttpMiddleware> TowerServiceBuilder<RpcMiddleware, HttpMiddl
 impl<HttpMiddleware, RpcMiddleware> Builder<HttpMiddleware, RpcMiddleware> {
 	/// Configure the [`ServerConfig`].
 	pub fn set_config(mut self, cfg: ServerConfig) -> Self {
-		self.server_cfg = cfg;
+		let observer = self.server_cfg.observer.take();
+		let introspection = self.server_cfg.introspection.take();
+		self.server_cfg = ServerConfig { observer, introspection, ..cfg };
+		self
+	}
+
+	/// Register an observer that is notified about the lifecycle of every connection.
+	///
+	/// See [`ConnectionObserver`] for the events that are emitted.
+	pub fn set_connection_observer<O: ConnectionObserver>(mut self, observer: O) -> Self {
+		self.server_cfg.observer = Some(Arc::new(observer));
+		self
+	}
+
+	/// Register an [`Introspection`] handle which lists the live connections and
+	/// subscriptions of the server and may close them.
+	pub fn set_introspection(mut self, introspection: Introspection) -> Self {
+		self.server_cfg.introspection = Some(introspection);
 		self
 	}
 
@@ -924,6 +1056,10 @@ struct ServiceData {
 	conn_guard: ConnectionGuard,
 	/// ServerConfig
 	server_cfg: ServerConfig,
+	/// Session state of the connection.
+	session: Session,
+	/// Remote address of the connection if known.
+	remote_addr: Option<SocketAddr>,
 }
 
 /// jsonrpsee tower service
@@ -1025,10 +1161,25 @@ where
 
 		tracing::trace!(target: LOG_TARGET, "{:?}", request);
 
+		// New requests are rejected while the open connections are drained.
+		if stop_handle.drain().is_some() {
+			return async { Ok(http::response::shutting_down()) }.boxed();
+		}
+
+		let events = ConnectionEvents::new(
+			self.inner.server_cfg.observer.as_ref(),
+			self.inner.remote_addr,
+			conn_id.into(),
+			request.headers(),
+		);
+
 		let Some(conn_permit) = conn_guard.try_acquire() else {
+			events.reject();
 			return async move { Ok(http::response::too_many_requests()) }.boxed();
 		};
 
+		events.accept();
+
 		let conn = ConnectionState::new(stop_handle.clone(), conn_id, conn_permit);
 
 		let max_conns = conn_guard.max_connections();
@@ -1038,6 +1189,7 @@ where
 		let req_ext = request.extensions_mut();
 		req_ext.insert::<ConnectionGuard>(conn_guard.clone());
 		req_ext.insert::<ConnectionId>(conn.conn_id.into());
+		req_ext.insert::<Session>(self.inner.session.clone());
 
 		let is_upgrade_request = is_upgrade_request(&request);
 
@@ -1046,22 +1198,30 @@ where
 
 			let mut server = soketto::handshake::http::Server::new();
 
-			let response = match server.receive_request(&request) {
-				Ok(response) => {
+			match server.receive_request(&request) {
+				Ok(mut response) => {
 					let (tx, rx) = mpsc::channel(this.server_cfg.message_buffer_capacity as usize);
-					let sink = MethodSink::new(tx);
+					let sink = this.server_cfg.method_sink(tx);
 
 					// On each method call the `pending_calls` is cloned
 					// then when all pending_calls are dropped
 					// a graceful shutdown can occur.
 					let (pending_calls, pending_calls_completed) = mpsc::channel::<()>(1);
 
+					let registration = this
+						.server_cfg
+						.introspection
+						.as_ref()
+						.map(|i| i.register_ws(conn_id.into(), this.remote_addr, this.methods.clone()));
+
 					let cfg = RpcServiceCfg::CallsAndSubscriptions {
 						bounded_subscriptions: BoundedSubscriptions::new(
 							this.server_cfg.max_subscriptions_per_connection,
 						),
 						id_provider: this.server_cfg.id_provider.clone(),
 						sink: sink.clone(),
+						events: events.clone(),
+						tracked: registration.as_ref().map(|r| r.connection().clone()),
 						_pending_calls: pending_calls,
 					};
 
@@ -1070,56 +1230,86 @@ where
 						this.server_cfg.max_response_body_size as usize,
 						this.conn_id.into(),
 						cfg,
+						this.server_cfg.deprecation_policy,
 					);
 
-					let rpc_service = self.rpc_middleware.service(rpc_service);
-
-					tokio::spawn(
-						async move {
-							let extensions = request.extensions().clone();
-
-							let upgraded = match hyper::upgrade::on(request).await {
-								Ok(u) => u,
-								Err(e) => {
-									tracing::debug!(target: LOG_TARGET, "Could not upgrade connection: {}", e);
-									return;
-								}
-							};
-
-							let io = TokioIo::new(upgraded);
-
-							let stream = BufReader::new(BufWriter::new(io.compat()));
-							let mut ws_builder = server.into_builder(stream);
-							ws_builder.set_max_message_size(this.server_cfg.max_request_body_size as usize);
-							let (sender, receiver) = ws_builder.finish();
-
-							let params = BackgroundTaskParams {
-								server_cfg: this.server_cfg,
-								conn,
-								ws_sender: sender,
-								ws_receiver: receiver,
-								rpc_service,
-								sink,
-								rx,
-								pending_calls_completed,
-								on_session_close,
-								extensions,
-							};
-
-							ws::background_task(params).await;
+					let rpc_service =
+						MethodMetaService::new(this.methods.clone(), self.rpc_middleware.service(rpc_service));
+
+					let hook = this.server_cfg.ws_handshake_hook.clone();
+
+					async move {
+						if let Some(hook) = hook {
+							if let Err(rp) = handshake::run_hook(
+								&*hook,
+								&mut request,
+								&mut response,
+								this.remote_addr,
+								conn_id.into(),
+							)
+							.await
+							{
+								events.close(CloseReason::HandshakeRejected);
+								return Ok(rp);
+							}
 						}
-						.in_current_span(),
-					);
 
-					response.map(|()| HttpBody::empty())
+						events.ws_upgrade();
+
+						tokio::spawn(
+							async move {
+								let extensions = request.extensions().clone();
+
+								let upgraded = match hyper::upgrade::on(request).await {
+									Ok(u) => u,
+									Err(e) => {
+										tracing::debug!(target: LOG_TARGET, "Could not upgrade connection: {}", e);
+										events.close(CloseReason::Error(e.to_string()));
+										return;
+									}
+								};
+
+								let io = TokioIo::new(upgraded);
+
+								let stream = BufReader::new(BufWriter::new(io.compat()));
+								let mut ws_builder = server.into_builder(stream);
+								ws_builder.set_max_message_size(this.server_cfg.max_request_body_size as usize);
+								let (sender, receiver) = ws_builder.finish();
+
+								events.handshake_complete();
+
+								let params = BackgroundTaskParams {
+									server_cfg: this.server_cfg,
+									conn,
+									ws_sender: sender,
+									ws_receiver: receiver,
+									rpc_service,
+									sink,
+									rx,
+									pending_calls_completed,
+									on_session_close,
+									extensions,
+									events,
+									registration,
+									methods: this.methods.clone(),
+								};
+
+								ws::background_task(params).await;
+							}
+							.in_current_span(),
+						);
+
+						Ok(response.map(|()| HttpBody::empty()))
+					}
+					.boxed()
 				}
 				Err(e) => {
 					tracing::debug!(target: LOG_TARGET, "Could not upgrade connection: {}", e);
-					HttpResponse::new(HttpBody::from(format!("Could not upgrade connection: {e}")))
+					events.close(CloseReason::Completed);
+					async move { Ok(HttpResponse::new(HttpBody::from(format!("Could not upgrade connection: {e}")))) }
+						.boxed()
 				}
-			};
-
-			async { Ok(response) }.boxed()
+			}
 		} else if self.inner.server_cfg.enable_http && !is_upgrade_request {
 			let this = &self.inner;
 			let max_response_size = this.server_cfg.max_response_body_size;
@@ -1127,23 +1317,44 @@ where
 			let methods = this.methods.clone();
 			let batch_config = this.server_cfg.batch_requests_config;
 
+			let registration = this
+				.server_cfg
+				.introspection
+				.as_ref()
+				.map(|i| i.register_http(conn_id.into(), this.remote_addr, methods.clone()));
+
 			let rpc_service = self.rpc_middleware.service(RpcService::new(
-				methods,
+				methods.clone(),
 				max_response_size as usize,
 				this.conn_id.into(),
 				RpcServiceCfg::OnlyCalls,
+				this.server_cfg.deprecation_policy,
 			));
+			let rpc_service = MethodMetaService::new(methods, rpc_service);
 
 			Box::pin(async move {
+				let request = match &registration {
+					Some(r) => request.map(|body| r.count_request(body)),
+					None => request,
+				};
+
 				let rp = http::call_with_service(request, batch_config, max_request_size, rpc_service).await;
+
+				let rp = match registration {
+					Some(r) => rp.map(|body| r.count_response(body)),
+					None => rp,
+				};
+
 				// NOTE: The `conn guard` must be held until the response is processed
 				// to respect the `max_connections` limit.
 				drop(conn);
+				events.close(CloseReason::Completed);
 				Ok(rp)
 			})
 		} else {
 			// NOTE: the `conn guard` is dropped when this function which is fine
 			// because it doesn't rely on any async operations.
+			events.close(CloseReason::Completed);
 			Box::pin(async { Ok(http::response::denied()) })
 		}
 	}
@@ -1163,7 +1374,7 @@ struct ProcessConnection<'a, HttpMiddleware, RpcMiddleware> {
 }
 
 #[instrument(name = "connection", skip_all, fields(remote_addr = %params.remote_addr, conn_id = %params.conn_id), level = "INFO")]
-fn process_connection<'a, RpcMiddleware, HttpMiddleware, Body>(params: ProcessConnection<HttpMiddleware, RpcMiddleware>)
+fn process_connection<RpcMiddleware, HttpMiddleware, Body>(params: ProcessConnection<HttpMiddleware, RpcMiddleware>)
 where
 	HttpMiddleware: Layer<TowerServiceNoHttp<RpcMiddleware>> + Send + 'static,
 	<HttpMiddleware as Layer<TowerServiceNoHttp<RpcMiddleware>>>::Service:
@@ -1183,7 +1394,7 @@ where
 		stop_handle,
 		drop_on_completion,
 		methods,
-		..
+		remote_addr,
 	} = params;
 
 	if let Err(e) = socket.set_nodelay(server_cfg.tcp_no_delay) {
@@ -1201,6 +1412,8 @@ where
 			stop_handle: stop_handle.clone(),
 			conn_id,
 			conn_guard: conn_guard.clone(),
+			session: Session::new(),
+			remote_addr: Some(remote_addr),
 		},
 		rpc_middleware,
 		on_session_close: None,
@@ -1218,7 +1431,7 @@ where
 		builder.http2().keep_alive_interval(keep_alive).keep_alive_timeout(keep_alive_timeout);
 
 		let conn = builder.serve_connection_with_upgrades(io, service);
-		let stopped = stop_handle.shutdown();
+		let stopped = stop_handle.clone().shutdown();
 
 		tokio::pin!(stopped, conn);
 
@@ -1228,7 +1441,16 @@ where
 				// NOTE: the connection should continue to be polled until shutdown can finish.
 				// Thus, both lines below are needed and not a nit.
 				conn.as_mut().graceful_shutdown();
-				conn.await
+
+				match stop_handle.drain() {
+					// The in-flight requests are dropped if they are not completed before the deadline.
+					Some(drain) => {
+						let res = tokio::time::timeout_at(drain.deadline, conn).await;
+						drain.connection_closed(conn_id.into(), res.is_err());
+						res.unwrap_or(Ok(()))
+					}
+					None => conn.await,
+				}
 			}
 		};
 
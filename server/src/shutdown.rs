@@ -0,0 +1,188 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Graceful shutdown of the server.
+
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures_util::future::BoxFuture;
+use futures_util::{FutureExt, Stream, StreamExt};
+use jsonrpsee_core::SubscriptionError;
+use jsonrpsee_core::server::ConnectionId;
+use jsonrpsee_types::error::SHUTTING_DOWN_MSG;
+use tokio::sync::mpsc;
+use tokio::time::{Instant, Sleep};
+
+/// Configuration of a graceful shutdown started by [`ServerHandle::graceful_shutdown`](crate::ServerHandle::graceful_shutdown).
+#[derive(Debug, Clone)]
+pub struct ShutdownConfig {
+	pub(crate) deadline: Duration,
+	pub(crate) subscription_close_reason: SubscriptionError,
+}
+
+impl Default for ShutdownConfig {
+	fn default() -> Self {
+		Self { deadline: Duration::from_secs(30), subscription_close_reason: SHUTTING_DOWN_MSG.into() }
+	}
+}
+
+impl ShutdownConfig {
+	/// Create a new shutdown configuration.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Configure the time after which the connections that are still open are closed forcibly.
+	///
+	/// Default: 30 seconds.
+	pub fn deadline(mut self, deadline: Duration) -> Self {
+		self.deadline = deadline;
+		self
+	}
+
+	/// Configure the error that is sent in the close notification of the active subscriptions.
+	///
+	/// Default: `"The server is shutting down"`.
+	pub fn subscription_close_reason(mut self, reason: impl Into<SubscriptionError>) -> Self {
+		self.subscription_close_reason = reason.into();
+		self
+	}
+}
+
+/// Progress of a graceful shutdown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShutdownEvent {
+	/// The server stopped accepting connections and rejects new calls.
+	Draining,
+	/// A connection was closed.
+	ConnectionClosed {
+		/// Connection ID.
+		conn_id: ConnectionId,
+		/// Whether the connection was closed forcibly because the deadline elapsed.
+		forced: bool,
+	},
+	/// The deadline elapsed and the connections that are still open are closed forcibly.
+	DeadlineElapsed,
+	/// All connections have been closed.
+	Completed,
+}
+
+/// Stream of [`ShutdownEvent`]s which ends after [`ShutdownEvent::Completed`].
+#[must_use = "Streams do nothing unless polled"]
+pub struct ShutdownProgress {
+	started: bool,
+	events: mpsc::UnboundedReceiver<ShutdownEvent>,
+	deadline: Option<Pin<Box<Sleep>>>,
+	stopped: Option<BoxFuture<'static, ()>>,
+}
+
+impl std::fmt::Debug for ShutdownProgress {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("ShutdownProgress").field("completed", &self.stopped.is_none()).finish()
+	}
+}
+
+impl ShutdownProgress {
+	pub(crate) fn new(
+		events: mpsc::UnboundedReceiver<ShutdownEvent>,
+		deadline: Instant,
+		stopped: BoxFuture<'static, ()>,
+	) -> Self {
+		Self {
+			started: false,
+			events,
+			deadline: Some(Box::pin(tokio::time::sleep_until(deadline))),
+			stopped: Some(stopped),
+		}
+	}
+
+	/// Wait until all connections have been closed.
+	pub async fn completed(self) {
+		self.for_each(|_| async {}).await
+	}
+}
+
+impl Stream for ShutdownProgress {
+	type Item = ShutdownEvent;
+
+	fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+		if !self.started {
+			self.started = true;
+			return Poll::Ready(Some(ShutdownEvent::Draining));
+		}
+
+		if let Poll::Ready(Some(event)) = self.events.poll_recv(cx) {
+			return Poll::Ready(Some(event));
+		}
+
+		let Some(stopped) = self.stopped.as_mut() else {
+			return Poll::Ready(None);
+		};
+
+		if stopped.poll_unpin(cx).is_ready() {
+			// The connections report that they are closed before the server is stopped
+			// but the event may have been sent after the channel was polled above.
+			if let Ok(event) = self.events.try_recv() {
+				return Poll::Ready(Some(event));
+			}
+
+			self.stopped = None;
+			return Poll::Ready(Some(ShutdownEvent::Completed));
+		}
+
+		if let Some(deadline) = self.deadline.as_mut() {
+			if deadline.poll_unpin(cx).is_ready() {
+				self.deadline = None;
+				return Poll::Ready(Some(ShutdownEvent::DeadlineElapsed));
+			}
+		}
+
+		Poll::Pending
+	}
+}
+
+/// State of a graceful shutdown which is shared with the connections.
+#[derive(Debug)]
+pub(crate) struct Drain {
+	pub(crate) config: ShutdownConfig,
+	pub(crate) deadline: Instant,
+	events: mpsc::UnboundedSender<ShutdownEvent>,
+}
+
+impl Drain {
+	pub(crate) fn new(config: ShutdownConfig) -> (Self, mpsc::UnboundedReceiver<ShutdownEvent>) {
+		let (tx, rx) = mpsc::unbounded_channel();
+		let deadline = Instant::now() + config.deadline;
+		(Self { config, deadline, events: tx }, rx)
+	}
+
+	/// Report that a connection was closed.
+	pub(crate) fn connection_closed(&self, conn_id: ConnectionId, forced: bool) {
+		let _ = self.events.send(ShutdownEvent::ConnectionClosed { conn_id, forced });
+	}
+}
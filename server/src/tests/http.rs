@@ -727,3 +727,132 @@ async fn http_extensions_from_rpc_batch_response_overwrite() {
 	handle.stop().unwrap();
 	handle.stopped().await;
 }
+
+#[tokio::test]
+async fn http_identical_concurrent_calls_are_coalesced() {
+	use jsonrpsee_core::middleware::layer::CoalesceLayer;
+	use std::sync::atomic::{AtomicUsize, Ordering};
+
+	init_logger();
+
+	let server = ServerBuilder::default()
+		.set_rpc_middleware(RpcServiceBuilder::new().layer(CoalesceLayer::new(["slow_count"])))
+		.build("127.0.0.1:0")
+		.await
+		.unwrap();
+	let mut module = RpcModule::new(AtomicUsize::new(0));
+	module
+		.register_async_method("slow_count", |_, ctx, _| async move {
+			tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+			ctx.fetch_add(1, Ordering::SeqCst) + 1
+		})
+		.unwrap();
+	module.register_method("count", |_, ctx, _| ctx.fetch_add(1, Ordering::SeqCst) + 1).unwrap();
+	let addr = server.local_addr().unwrap();
+	let uri = to_http_uri(addr);
+	let handle = server.start(module);
+
+	let calls = (1..=5).map(|id| {
+		let req = format!(r#"{{"jsonrpc":"2.0","method":"slow_count","params":[1],"id":{id}}}"#);
+		http_request(req.into(), uri.clone())
+	});
+	let responses = futures_util::future::join_all(calls).with_default_timeout().await.unwrap();
+
+	for (id, response) in (1..=5).zip(responses) {
+		assert_eq!(response.unwrap().body, ok_response(1.into(), Id::Num(id)));
+	}
+
+	// Calls with different params are not coalesced.
+	let calls = [
+		r#"{"jsonrpc":"2.0","method":"slow_count","params":[1],"id":1}"#,
+		r#"{"jsonrpc":"2.0","method":"slow_count","params":[2],"id":2}"#,
+	]
+	.map(|req| http_request(req.into(), uri.clone()));
+	let responses: Vec<_> = futures_util::future::join_all(calls).with_default_timeout().await.unwrap();
+	let mut results: Vec<u64> = responses
+		.into_iter()
+		.map(|r| serde_json::from_str::<JsonValue>(&r.unwrap().body).unwrap()["result"].as_u64().unwrap())
+		.collect();
+	results.sort();
+	assert_eq!(results, vec![2, 3]);
+
+	// Methods that didn't opt-in are not coalesced.
+	let req = r#"{"jsonrpc":"2.0","method":"count","id":1}"#;
+	let response = http_request(req.into(), uri.clone()).with_default_timeout().await.unwrap().unwrap();
+	assert_eq!(response.body, ok_response(4.into(), Id::Num(1)));
+
+	handle.stop().unwrap();
+	handle.stopped().await;
+}
+
+#[tokio::test]
+async fn http_coalesced_responses_respect_the_size_limit() {
+	use jsonrpsee_core::middleware::layer::CoalesceLayer;
+
+	init_logger();
+
+	let coalesce = CoalesceLayer::new(["slow"]).max_response_body_size(100);
+	let server = ServerBuilder::default()
+		.set_rpc_middleware(RpcServiceBuilder::new().layer(coalesce))
+		.build("127.0.0.1:0")
+		.await
+		.unwrap();
+	let mut module = RpcModule::new(());
+	module
+		.register_async_method("slow", |_, _, _| async move {
+			tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+			"ok"
+		})
+		.unwrap();
+	let addr = server.local_addr().unwrap();
+	let uri = to_http_uri(addr);
+	let handle = server.start(module);
+
+	// The response to the coalesced call doesn't fit with its longer `Id`.
+	let long_id = "x".repeat(100);
+	let calls = [
+		r#"{"jsonrpc":"2.0","method":"slow","id":1}"#.to_string(),
+		format!(r#"{{"jsonrpc":"2.0","method":"slow","id":"{long_id}"}}"#),
+	];
+	let first = http_request(calls[0].clone().into(), uri.clone());
+	let second = async {
+		tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+		http_request(calls[1].clone().into(), uri.clone()).await
+	};
+	let (first, second) = futures_util::future::join(first, second).with_default_timeout().await.unwrap();
+
+	assert_eq!(first.unwrap().body, ok_response("ok".into(), Id::Num(1)));
+	assert_eq!(second.unwrap().body, oversized_response(Id::Str(long_id), 100));
+
+	handle.stop().unwrap();
+	handle.stopped().await;
+}
+
+#[tokio::test]
+async fn introspection_counts_http_body_bytes() {
+	use crate::Introspection;
+
+	init_logger();
+
+	let introspection = Introspection::new();
+	let server = ServerBuilder::default().set_introspection(introspection.clone()).build("127.0.0.1:0").await.unwrap();
+	let mut module = RpcModule::new(introspection);
+	module
+		.register_method("bytes_in", |_, introspection, _| {
+			let connections = introspection.connections();
+			assert_eq!(connections.len(), 1);
+			connections[0].bytes_in
+		})
+		.unwrap();
+	let addr = server.local_addr().unwrap();
+	let uri = to_http_uri(addr);
+	let handle = server.start(module);
+
+	// The request body has been read completely when the call is executed.
+	let req = r#"{"jsonrpc":"2.0","method":"bytes_in","id":1}"#;
+	let response = http_request(req.into(), uri).with_default_timeout().await.unwrap().unwrap();
+	assert_eq!(response.body, ok_response(req.len().into(), Id::Num(1)));
+
+	handle.stop().unwrap();
+	handle.stopped().await;
+}
@@ -1,4 +1,5 @@
 mod helpers;
 mod http;
+mod pubsub;
 mod shared;
 mod ws;
@@ -0,0 +1,101 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use std::time::Duration;
+
+use crate::pubsub::{TcpBackend, TcpBroker};
+use crate::tests::helpers::init_logger;
+use crate::{PubSubHub, RpcModule, Subscription};
+use jsonrpsee_core::EmptyServerParams;
+
+async fn subscribe(hub: PubSubHub, topic: &'static str) -> Subscription {
+	let mut module = RpcModule::new(hub);
+	module
+		.register_subscription("sub", "sub", "unsub", move |_, pending, hub, _| async move {
+			let sink = pending.accept().await?;
+			hub.subscribe(topic, sink);
+			Ok(())
+		})
+		.unwrap();
+
+	module.subscribe("sub", EmptyServerParams::new(), 1024).await.unwrap()
+}
+
+#[tokio::test]
+async fn tcp_broker_shares_topics_between_hubs() {
+	init_logger();
+
+	let broker = TcpBroker::bind("127.0.0.1:0").await.unwrap();
+	let addr = broker.local_addr().unwrap();
+	tokio::spawn(broker.run());
+
+	let hub_a = PubSubHub::with_backend(TcpBackend::connect(addr).await.unwrap());
+	let hub_b = PubSubHub::with_backend(TcpBackend::connect(addr).await.unwrap());
+
+	let mut sub_a = subscribe(hub_a.clone(), "newHeads").await;
+	let mut sub_b = subscribe(hub_b.clone(), "newHeads").await;
+
+	// The subscription on the broker is established in the background,
+	// publish until the subscriber connected to the other hub receives a message.
+	let publisher = hub_a.clone();
+	let publish = tokio::spawn(async move {
+		for n in 0_usize.. {
+			assert_eq!(publisher.publish("newHeads", &n).await.unwrap(), 1);
+			tokio::time::sleep(Duration::from_millis(20)).await;
+		}
+	});
+
+	let (first, _) =
+		tokio::time::timeout(Duration::from_secs(10), sub_b.next::<usize>()).await.unwrap().unwrap().unwrap();
+	let (second, _) = sub_b.next::<usize>().await.unwrap().unwrap();
+	assert_eq!(second, first + 1);
+	publish.abort();
+
+	// The local subscriber gets each message exactly once.
+	for exp in 0..=second {
+		let (n, _) = sub_a.next::<usize>().await.unwrap().unwrap();
+		assert_eq!(n, exp);
+	}
+}
+
+#[tokio::test]
+async fn tcp_broker_closes_connection_on_too_long_frame() {
+	use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+	init_logger();
+
+	let broker = TcpBroker::bind("127.0.0.1:0").await.unwrap();
+	let addr = broker.local_addr().unwrap();
+	tokio::spawn(broker.run());
+
+	let mut socket = tokio::net::TcpStream::connect(addr).await.unwrap();
+	// Longer than the max frame length of 10 MiB and without a line break.
+	let _ = socket.write_all(&vec![b'a'; 10 * 1024 * 1024 + 1]).await;
+
+	let mut buf = [0; 1];
+	let read = tokio::time::timeout(Duration::from_secs(10), socket.read(&mut buf)).await.unwrap();
+	assert!(!matches!(read, Ok(n) if n > 0));
+}
@@ -808,6 +808,58 @@ async fn ws_server_backpressure_works() {
 	assert!(seen_item_after_backpressure);
 }
 
+#[tokio::test]
+async fn ws_server_closes_slow_consumers() {
+	init_logger();
+
+	let (done_tx, mut done_rx) = tokio::sync::mpsc::channel::<usize>(1);
+
+	let config = ServerConfig::builder().set_message_buffer_byte_limit(1024 * 1024).build();
+	let server = ServerBuilder::with_config(config).build("127.0.0.1:0").with_default_timeout().await.unwrap().unwrap();
+
+	let mut module = RpcModule::new(done_tx);
+	module
+		.register_subscription("subscribe_big", "big", "unsubscribe_big", |_, pending, done_tx, _| async move {
+			let sink = pending.accept().await?;
+			let item = serde_json::value::to_raw_value(&"x".repeat(64 * 1024)).unwrap();
+			let mut sent = 0;
+
+			// The client doesn't read anything, eventually the buffer limit is exceeded.
+			while sink.send(item.clone()).await.is_ok() {
+				sent += 1;
+			}
+
+			done_tx.send(sent).await.unwrap();
+			Ok(())
+		})
+		.unwrap();
+	let addr = server.local_addr().unwrap();
+	let _server_handle = server.start(module);
+
+	let mut client = WebSocketTestClient::new(addr).with_default_timeout().await.unwrap().unwrap();
+	client.send(r#"{"jsonrpc":"2.0","method":"subscribe_big","params":[],"id":1}"#).await.unwrap();
+
+	let sent = done_rx.recv().with_default_timeout().await.unwrap().unwrap();
+	let mut received = 0;
+
+	// Read what was sent out before the limit was exceeded.
+	let last = loop {
+		let msg = client.receive().with_default_timeout().await.unwrap().unwrap();
+		if msg.contains(r#""method":"big""#) {
+			received += 1;
+		} else if !msg.contains(r#""id":1"#) {
+			break msg;
+		}
+	};
+
+	assert!(received < sent);
+	assert_eq!(
+		last,
+		r#"{"jsonrpc":"2.0","id":null,"error":{"code":-32012,"message":"The connection was closed because the client was too slow to read the responses","data":"Exceeded message buffer limit of 1048576 bytes"}}"#
+	);
+	assert!(client.receive().with_default_timeout().await.unwrap().is_err());
+}
+
 #[tokio::test]
 async fn notif_is_ignored() {
 	init_logger();
@@ -924,3 +976,364 @@ async fn server_with_infinite_call(
 
 	(server.start(module), addr)
 }
+
+#[tokio::test]
+async fn ws_session_state_is_kept_per_connection() {
+	use jsonrpsee_core::middleware::{Batch, Notification, Request, RpcServiceBuilder, RpcServiceT};
+	use jsonrpsee_core::server::Session;
+
+	#[derive(Clone, Default)]
+	struct CallCount(usize);
+
+	#[derive(Clone)]
+	struct User(String);
+
+	#[derive(Clone)]
+	struct CountCalls<S>(S);
+
+	impl<S: RpcServiceT + Send + Sync + Clone + 'static> RpcServiceT for CountCalls<S> {
+		type BatchResponse = S::BatchResponse;
+		type MethodResponse = S::MethodResponse;
+		type NotificationResponse = S::NotificationResponse;
+
+		fn call<'a>(&self, req: Request<'a>) -> impl Future<Output = Self::MethodResponse> + Send + 'a {
+			Session::from_extensions(req.extensions()).update(|c: &mut CallCount| c.0 += 1);
+			self.0.call(req)
+		}
+
+		fn batch<'a>(&self, batch: Batch<'a>) -> impl Future<Output = Self::BatchResponse> + Send + 'a {
+			self.0.batch(batch)
+		}
+
+		fn notification<'a>(
+			&self,
+			n: Notification<'a>,
+		) -> impl Future<Output = Self::NotificationResponse> + Send + 'a {
+			self.0.notification(n)
+		}
+	}
+
+	init_logger();
+
+	let server = ServerBuilder::default()
+		.set_rpc_middleware(RpcServiceBuilder::new().layer_fn(CountCalls))
+		.build("127.0.0.1:0")
+		.with_default_timeout()
+		.await
+		.unwrap()
+		.unwrap();
+
+	let mut module = RpcModule::new(());
+	module
+		.register_method("login", |params, _, ext| {
+			let name: String = params.one().unwrap();
+			Session::from_extensions(ext).insert(User(name));
+		})
+		.unwrap();
+	module
+		.register_async_method("whoami", |_, _, ext| async move {
+			let session = Session::from_extensions(&ext);
+			let calls = session.get::<CallCount>().unwrap_or_default().0;
+			format!("{} {calls}", session.get::<User>().map_or("anonymous".to_string(), |u| u.0))
+		})
+		.unwrap();
+	let addr = server.local_addr().unwrap();
+	let _server_handle = server.start(module);
+
+	let mut alice = WebSocketTestClient::new(addr).with_default_timeout().await.unwrap().unwrap();
+	let mut bob = WebSocketTestClient::new(addr).with_default_timeout().await.unwrap().unwrap();
+
+	alice.send_request_text(call("login", vec!["alice"], Id::Num(1))).with_default_timeout().await.unwrap().unwrap();
+
+	let rp = alice
+		.send_request_text(call("whoami", Vec::<()>::new(), Id::Num(2)))
+		.with_default_timeout()
+		.await
+		.unwrap()
+		.unwrap();
+	assert_eq!(rp, ok_response("alice 2".into(), Id::Num(2)));
+
+	let rp = bob
+		.send_request_text(call("whoami", Vec::<()>::new(), Id::Num(1)))
+		.with_default_timeout()
+		.await
+		.unwrap()
+		.unwrap();
+	assert_eq!(rp, ok_response("anonymous 1".into(), Id::Num(1)));
+}
+
+#[tokio::test]
+async fn connection_observer_works() {
+	use crate::{CloseReason, ConnectionInfo, ConnectionObserver};
+	use std::sync::{Arc, Mutex};
+
+	#[derive(Debug, Clone, Default)]
+	struct Recorder(Arc<Mutex<Vec<String>>>);
+
+	impl Recorder {
+		fn push(&self, conn: &ConnectionInfo, event: String) {
+			assert!(conn.remote_addr().is_some());
+			assert!(conn.headers().contains_key("host"));
+			self.0.lock().unwrap().push(event);
+		}
+
+		fn events(&self) -> Vec<String> {
+			self.0.lock().unwrap().clone()
+		}
+	}
+
+	impl ConnectionObserver for Recorder {
+		fn on_accept(&self, conn: &ConnectionInfo) {
+			self.push(conn, "accept".into());
+		}
+
+		fn on_reject(&self, conn: &ConnectionInfo) {
+			self.push(conn, "reject".into());
+		}
+
+		fn on_ws_upgrade(&self, conn: &ConnectionInfo) {
+			self.push(conn, "ws_upgrade".into());
+		}
+
+		fn on_handshake_complete(&self, conn: &ConnectionInfo) {
+			self.push(conn, "handshake_complete".into());
+		}
+
+		fn on_close(&self, conn: &ConnectionInfo, reason: &CloseReason) {
+			self.push(conn, format!("close {reason:?}"));
+		}
+
+		fn on_subscription_opened(&self, conn: &ConnectionInfo, method: &str) {
+			self.push(conn, format!("subscription_opened {method}"));
+		}
+
+		fn on_subscription_closed(&self, conn: &ConnectionInfo, method: &str) {
+			self.push(conn, format!("subscription_closed {method}"));
+		}
+	}
+
+	init_logger();
+
+	let recorder = Recorder::default();
+	let server = ServerBuilder::default()
+		.set_config(ServerConfig::builder().max_connections(1).build())
+		.set_connection_observer(recorder.clone())
+		.build("127.0.0.1:0")
+		.with_default_timeout()
+		.await
+		.unwrap()
+		.unwrap();
+
+	let mut module = RpcModule::new(());
+	module
+		.register_subscription("subscribe_hello", "hello", "unsubscribe_hello", |_, pending, _, _| async move {
+			let sink = pending.accept().await?;
+			sink.closed().await;
+			Ok(())
+		})
+		.unwrap();
+	let addr = server.local_addr().unwrap();
+	let _server_handle = server.start(module);
+
+	let mut client = WebSocketTestClient::new(addr).with_default_timeout().await.unwrap().unwrap();
+
+	// Only one connection is allowed.
+	assert!(WebSocketTestClient::new(addr).with_default_timeout().await.unwrap().is_err());
+
+	let rp = client
+		.send_request_text(call("subscribe_hello", Vec::<()>::new(), Id::Num(1)))
+		.with_default_timeout()
+		.await
+		.unwrap()
+		.unwrap();
+	let sub_id: u64 = deser_call(rp);
+	client
+		.send_request_text(call("unsubscribe_hello", vec![sub_id], Id::Num(2)))
+		.with_default_timeout()
+		.await
+		.unwrap()
+		.unwrap();
+	client.close().with_default_timeout().await.unwrap().unwrap();
+
+	let expected = [
+		"accept",
+		"ws_upgrade",
+		"handshake_complete",
+		"reject",
+		"subscription_opened subscribe_hello",
+		"subscription_closed subscribe_hello",
+		"close ClosedByClient",
+	];
+
+	for _ in 0..50 {
+		if recorder.events().len() == expected.len() {
+			break;
+		}
+		tokio::time::sleep(Duration::from_millis(10)).await;
+	}
+
+	assert_eq!(recorder.events(), expected);
+}
+
+#[tokio::test]
+async fn introspection_works() {
+	use crate::{ConnectionDetails, Introspection, Protocol};
+
+	init_logger();
+
+	let introspection = Introspection::new();
+	let server = ServerBuilder::default()
+		.set_introspection(introspection.clone())
+		.build("127.0.0.1:0")
+		.with_default_timeout()
+		.await
+		.unwrap()
+		.unwrap();
+
+	let mut module = RpcModule::new(());
+	module
+		.register_subscription("subscribe_hello", "hello", "unsubscribe_hello", |_, pending, _, _| async move {
+			let sink = pending.accept().await?;
+			sink.closed().await;
+			Ok(())
+		})
+		.unwrap();
+	module.merge(introspection.clone().into_rpc()).unwrap();
+	let addr = server.local_addr().unwrap();
+	let _server_handle = server.start(module);
+
+	let mut client = WebSocketTestClient::new(addr).with_default_timeout().await.unwrap().unwrap();
+	let rp = client
+		.send_request_text(call("subscribe_hello", Vec::<()>::new(), Id::Num(1)))
+		.with_default_timeout()
+		.await
+		.unwrap()
+		.unwrap();
+	let sub_id: u64 = deser_call(rp);
+
+	let rp = client
+		.send_request_text(call("admin_connections", Vec::<()>::new(), Id::Num(2)))
+		.with_default_timeout()
+		.await
+		.unwrap()
+		.unwrap();
+	let connections: Vec<ConnectionDetails> = deser_call(rp);
+	assert_eq!(connections.len(), 1);
+
+	let conn = &connections[0];
+	assert_eq!(conn.protocol, Protocol::WebSocket);
+	assert!(conn.remote_addr.is_some());
+	assert!(conn.bytes_in > 0);
+	assert!(conn.bytes_out > 0);
+	assert_eq!(conn.in_flight_calls, 1);
+	assert_eq!(conn.subscriptions.len(), 1);
+	assert_eq!(conn.subscriptions[0].method, "subscribe_hello");
+	assert_eq!(conn.subscriptions[0].id, SubscriptionId::Num(sub_id));
+
+	let conn_id = conn.conn_id;
+	assert!(introspection.close_subscription(conn_id, &SubscriptionId::Num(sub_id)).await);
+	assert!(!introspection.close_subscription(conn_id, &SubscriptionId::Num(sub_id)).await);
+
+	// The client is notified that the subscription was closed.
+	let notif: JsonValue =
+		serde_json::from_str(&client.receive().with_default_timeout().await.unwrap().unwrap()).unwrap();
+	assert_eq!(notif["method"], "hello");
+	assert_eq!(notif["params"]["subscription"], sub_id);
+	assert_eq!(notif["params"]["error"], "Subscription closed by the server");
+
+	for _ in 0..50 {
+		if introspection.subscriptions(conn_id).is_empty() {
+			break;
+		}
+		tokio::time::sleep(Duration::from_millis(10)).await;
+	}
+	assert!(introspection.subscriptions(conn_id).is_empty());
+
+	assert!(introspection.close_connection(conn_id));
+	assert!(client.receive().with_default_timeout().await.unwrap().is_err());
+
+	for _ in 0..50 {
+		if introspection.connections().is_empty() {
+			break;
+		}
+		tokio::time::sleep(Duration::from_millis(10)).await;
+	}
+	assert!(introspection.connections().is_empty());
+	assert!(!introspection.close_connection(conn_id));
+}
+
+#[tokio::test]
+async fn graceful_shutdown_drains_connections() {
+	use crate::{ShutdownConfig, ShutdownEvent};
+	use futures_util::StreamExt;
+	use jsonrpsee_types::error::SHUTTING_DOWN_CODE;
+
+	init_logger();
+
+	let server = ServerBuilder::default().build("127.0.0.1:0").with_default_timeout().await.unwrap().unwrap();
+
+	let mut module = RpcModule::new(());
+	module
+		.register_async_method("slow_call", |_, _, _| async move {
+			tokio::time::sleep(Duration::from_millis(500)).await;
+			"done"
+		})
+		.unwrap();
+	module
+		.register_subscription("subscribe_hello", "hello", "unsubscribe_hello", |_, pending, _, _| async move {
+			let sink = pending.accept().await?;
+			sink.closed().await;
+			Ok(())
+		})
+		.unwrap();
+	let addr = server.local_addr().unwrap();
+	let server_handle = server.start(module);
+
+	let mut client = WebSocketTestClient::new(addr).with_default_timeout().await.unwrap().unwrap();
+	let rp = client
+		.send_request_text(call("subscribe_hello", Vec::<()>::new(), Id::Num(1)))
+		.with_default_timeout()
+		.await
+		.unwrap()
+		.unwrap();
+	let sub_id: u64 = deser_call(rp);
+
+	client.send(call("slow_call", Vec::<()>::new(), Id::Num(2))).with_default_timeout().await.unwrap().unwrap();
+	tokio::time::sleep(Duration::from_millis(100)).await;
+
+	let progress = server_handle
+		.graceful_shutdown(ShutdownConfig::new().deadline(Duration::from_secs(10)).subscription_close_reason("bye"))
+		.unwrap();
+	assert!(server_handle.graceful_shutdown(ShutdownConfig::new()).is_err());
+	tokio::time::sleep(Duration::from_millis(100)).await;
+
+	// New calls are rejected while the pending calls are completed.
+	let rp = client
+		.send_request_text(call("slow_call", Vec::<()>::new(), Id::Num(3)))
+		.with_default_timeout()
+		.await
+		.unwrap()
+		.unwrap();
+	let rp: JsonValue = serde_json::from_str(&rp).unwrap();
+	assert_eq!(rp["id"], 3);
+	assert_eq!(rp["error"]["code"], SHUTTING_DOWN_CODE);
+
+	let rp = client.receive().with_default_timeout().await.unwrap().unwrap();
+	assert_eq!(rp, ok_response("done".into(), Id::Num(2)));
+
+	// The subscription is closed with the configured reason.
+	let notif = client.receive().with_default_timeout().await.unwrap().unwrap();
+	let notif: JsonValue = serde_json::from_str(&notif).unwrap();
+	assert_eq!(notif["method"], "hello");
+	assert_eq!(notif["params"]["subscription"], sub_id);
+	assert_eq!(notif["params"]["error"], "bye");
+
+	assert!(client.receive().with_default_timeout().await.unwrap().is_err());
+
+	let events: Vec<ShutdownEvent> = progress.collect().with_default_timeout().await.unwrap();
+	assert_eq!(events.first(), Some(&ShutdownEvent::Draining));
+	assert_eq!(events.last(), Some(&ShutdownEvent::Completed));
+	assert!(events.iter().any(|e| matches!(e, ShutdownEvent::ConnectionClosed { forced: false, .. })));
+	assert!(!events.contains(&ShutdownEvent::DeadlineElapsed));
+	assert!(server_handle.is_stopped());
+}
@@ -1,6 +1,6 @@
 use crate::{
 	BatchRequestConfig, ConnectionState, HttpRequest, HttpResponse, LOG_TARGET,
-	middleware::rpc::{RpcService, RpcServiceCfg},
+	middleware::rpc::{MethodMetaService, RpcService, RpcServiceCfg},
 	server::{ServerConfig, handle_rpc_call},
 };
 use http::Method;
@@ -50,14 +50,19 @@ where
 			NotificationResponse = MethodResponse,
 		> + Send,
 {
-	let ServerConfig { max_response_body_size, batch_requests_config, max_request_body_size, .. } = server_cfg;
+	let ServerConfig {
+		max_response_body_size, batch_requests_config, max_request_body_size, deprecation_policy, ..
+	} = server_cfg;
 
+	let methods: Methods = methods.into();
 	let rpc_service = rpc_service.service(RpcService::new(
-		methods.into(),
+		methods.clone(),
 		max_response_body_size as usize,
 		conn.conn_id.into(),
 		RpcServiceCfg::OnlyCalls,
+		deprecation_policy,
 	));
+	let rpc_service = MethodMetaService::new(methods, rpc_service);
 
 	let rp = call_with_service(request, batch_requests_config, max_request_body_size, rpc_service).await;
 
@@ -115,7 +120,7 @@ where
 /// HTTP response helpers.
 pub mod response {
 	use jsonrpsee_core::server::MethodResponse;
-	use jsonrpsee_types::error::{ErrorCode, reject_too_big_request};
+	use jsonrpsee_types::error::{ErrorCode, reject_shutting_down, reject_too_big_request};
 	use jsonrpsee_types::{ErrorObject, ErrorObjectOwned, Id, Response, ResponsePayload};
 
 	use crate::{HttpBody, HttpResponse};
@@ -213,6 +218,14 @@ pub mod response {
 		from_template(hyper::StatusCode::TOO_MANY_REQUESTS, "Too many connections. Please try again later.", TEXT)
 	}
 
+	/// Create a json response for requests that are rejected because the server is shutting down (503)
+	pub fn shutting_down() -> HttpResponse {
+		let rp = Response::new(ResponsePayload::<()>::error(reject_shutting_down()), Id::Null);
+		let error = serde_json::to_string(&rp).expect("JSON serialization infallible; qed");
+
+		from_template(hyper::StatusCode::SERVICE_UNAVAILABLE, error, JSON)
+	}
+
 	/// Create a response for when the server denied the request.
 	pub fn denied() -> HttpResponse {
 		from_template(hyper::StatusCode::FORBIDDEN, HttpBody::default(), TEXT)
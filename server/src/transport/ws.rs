@@ -2,8 +2,12 @@ use std::sync::Arc;
 use std::time::Instant;
 
 use crate::future::{IntervalStream, SessionClose};
-use crate::middleware::rpc::{RpcService, RpcServiceCfg};
+use crate::handshake;
+use crate::introspection::{Registration, TrackedConnection};
+use crate::middleware::rpc::{MethodMetaService, RpcService, RpcServiceCfg};
+use crate::observer::{CloseReason, ConnectionEvents};
 use crate::server::{ConnectionState, ServerConfig, handle_rpc_call};
+use crate::shutdown::Drain;
 use crate::{HttpBody, HttpRequest, HttpResponse, LOG_TARGET, PingConfig};
 
 use futures_util::future::{self, Either};
@@ -12,9 +16,9 @@ use futures_util::{Future, StreamExt, TryStreamExt};
 use hyper::upgrade::Upgraded;
 use hyper_util::rt::TokioIo;
 use jsonrpsee_core::middleware::{RpcServiceBuilder, RpcServiceT};
-use jsonrpsee_core::server::{BoundedSubscriptions, MethodResponse, MethodSink, Methods};
-use jsonrpsee_types::Id;
-use jsonrpsee_types::error::{ErrorCode, reject_too_big_request};
+use jsonrpsee_core::server::{BoundedSubscriptions, BufferBudget, MethodResponse, MethodSink, Methods, Session};
+use jsonrpsee_types::error::{ErrorCode, reject_shutting_down, reject_slow_consumer, reject_too_big_request};
+use jsonrpsee_types::{Id, InvalidRequest, Response, ResponsePayload};
 use serde_json::value::RawValue;
 use soketto::connection::Error as SokettoError;
 use soketto::data::ByteSlice125;
@@ -59,6 +63,9 @@ pub(crate) struct BackgroundTaskParams<S> {
 	pub(crate) pending_calls_completed: mpsc::Receiver<()>,
 	pub(crate) on_session_close: Option<SessionClose>,
 	pub(crate) extensions: http::Extensions,
+	pub(crate) events: ConnectionEvents,
+	pub(crate) registration: Option<Registration>,
+	pub(crate) methods: Methods,
 }
 
 pub(crate) async fn background_task<S>(params: BackgroundTaskParams<S>)
@@ -82,13 +89,18 @@ where
 		pending_calls_completed,
 		mut on_session_close,
 		extensions,
+		events,
+		registration,
+		methods,
 	} = params;
 	let ServerConfig { ping_config, batch_requests_config, max_request_body_size, .. } = server_cfg;
+	let tracked = registration.as_ref().map(|r| r.connection().clone());
 
 	let (conn_tx, conn_rx) = oneshot::channel();
 
 	// Spawn another task that sends out the responses on the Websocket.
-	let send_task_handle = tokio::spawn(send_task(rx, ws_sender, ping_config, conn_rx));
+	let send_task_handle =
+		tokio::spawn(send_task(rx, ws_sender, ping_config, conn_rx, sink.budget().cloned(), tracked.clone()));
 
 	let stopped = conn.stop_handle.clone().shutdown();
 	let rpc_service = Arc::new(rpc_service);
@@ -107,13 +119,28 @@ where
 			Err(e) => Some((Err(e), receiver)),
 		}
 	})
+	// The send task has closed the connection or the connection was terminated.
+	.take_until({
+		let sink = sink.clone();
+		let tracked = tracked.clone();
+		async move {
+			match tracked {
+				Some(tracked) => tokio::select! {
+					_ = sink.closed() => (),
+					_ = tracked.terminated() => (),
+				},
+				None => sink.closed().await,
+			}
+		}
+	})
 	.fuse();
 
 	tokio::pin!(ws_stream);
 
 	let result = loop {
-		let data = match try_recv(&mut ws_stream, stopped, ping_config, &mut missed_pings).await {
+		let data = match try_recv(&mut ws_stream, stopped, ping_config, &mut missed_pings, &events).await {
 			Receive::ConnectionClosed => break Ok(Shutdown::ConnectionClosed),
+			Receive::Inactive => break Ok(Shutdown::Inactive),
 			Receive::Stopped => break Ok(Shutdown::Stopped),
 			Receive::Ok(data, stop) => {
 				stopped = stop;
@@ -150,8 +177,13 @@ where
 		let rpc_service = rpc_service.clone();
 		let sink = sink.clone();
 		let extensions = extensions.clone();
+		let in_flight = tracked.as_ref().map(|c| {
+			c.record_in(data.len());
+			c.call_started()
+		});
 
 		tokio::spawn(async move {
+			let _in_flight = in_flight;
 			let first_non_whitespace = data.iter().enumerate().take(128).find(|(_, byte)| !byte.is_ascii_whitespace());
 
 			let (idx, is_single) = match first_non_whitespace {
@@ -189,9 +221,41 @@ where
 	// **NOTE** Do not return early in this function. This `await` needs to run to guarantee
 	// proper drop behaviour.
 	drop(rpc_service);
-	graceful_shutdown(result, pending_calls_completed, ws_stream, conn_tx, send_task_handle).await;
+
+	let close_reason = match &result {
+		Ok(Shutdown::Stopped) => CloseReason::ServerStopped,
+		Ok(Shutdown::Inactive) => CloseReason::PingTimeout,
+		Ok(Shutdown::ConnectionClosed) if tracked.as_ref().is_some_and(|c| c.is_terminated()) => {
+			CloseReason::Terminated
+		}
+		// The send task closes the sink when it fails to send or the client is too slow.
+		Ok(Shutdown::ConnectionClosed) if sink.is_closed() => CloseReason::SendFailed,
+		Ok(Shutdown::ConnectionClosed) => CloseReason::ClosedByClient,
+		Err(e) => CloseReason::Error(e.to_string()),
+	};
+
+	match (&result, conn.stop_handle.drain()) {
+		(Ok(Shutdown::Stopped), Some(drain)) => {
+			let close_subscriptions =
+				methods.close_subscriptions(conn.conn_id.into(), drain.config.subscription_close_reason.clone());
+			let forced = drain_connection(
+				&drain,
+				&sink,
+				close_subscriptions,
+				pending_calls_completed,
+				ws_stream,
+				conn_tx,
+				send_task_handle,
+			)
+			.await;
+			drain.connection_closed(conn.conn_id.into(), forced);
+		}
+		_ => graceful_shutdown(result, pending_calls_completed, ws_stream, conn_tx, send_task_handle).await,
+	}
 
 	drop(conn);
+	drop(registration);
+	events.close(close_reason);
 
 	if let Some(c) = on_session_close.take() {
 		c.close();
@@ -203,7 +267,9 @@ async fn send_task(
 	rx: mpsc::Receiver<Box<RawValue>>,
 	mut ws_sender: Sender,
 	ping_config: Option<PingConfig>,
-	stop: oneshot::Receiver<()>,
+	stop: oneshot::Receiver<bool>,
+	budget: Option<BufferBudget>,
+	tracked: Option<Arc<TrackedConnection>>,
 ) {
 	let ping_interval = match ping_config {
 		None => IntervalStream::pending(),
@@ -214,12 +280,30 @@ async fn send_task(
 	};
 	let rx = ReceiverStream::new(rx);
 
+	// Stop when the server is stopped or as soon as a message was rejected because the client is too slow.
+	let slow_consumer = budget.clone();
+	let stop = async move {
+		let exceeded = async move {
+			match slow_consumer {
+				Some(budget) => budget.exceeded().await,
+				None => future::pending().await,
+			}
+		};
+		tokio::pin!(exceeded);
+
+		match future::select(stop, exceeded).await {
+			Either::Left((stopped, _)) => Stop::Server { drain: matches!(stopped, Ok(true)) },
+			Either::Right(_) => Stop::SlowConsumer,
+		}
+	};
+
 	tokio::pin!(ping_interval, rx, stop);
 
 	// Received messages from the WebSocket.
 	let mut rx_item = rx.next();
 	let next_ping = ping_interval.next();
 	let mut futs = future::select(next_ping, stop);
+	let mut drain = false;
 
 	loop {
 		// Ensure select is cancel-safe by fetching and storing the `rx_item` that did not finish yet.
@@ -227,12 +311,29 @@ async fn send_task(
 		match future::select(rx_item, futs).await {
 			// Received message.
 			Either::Left((Some(response), not_ready)) => {
+				// The client doesn't read the messages fast enough, discard the
+				// buffered messages and close the connection.
+				if let Some(budget) = budget.as_ref().filter(|b| b.is_exceeded()) {
+					close_slow_consumer(&mut ws_sender, budget).await;
+					break;
+				}
+
+				let len = response.get().len();
+
 				// If websocket message send fail then terminate the connection.
 				if let Err(err) = send_message(&mut ws_sender, response).await {
 					tracing::debug!(target: LOG_TARGET, "WS send error: {}", err);
 					break;
 				}
 
+				if let Some(budget) = &budget {
+					budget.release(len);
+				}
+
+				if let Some(tracked) = &tracked {
+					tracked.record_out(len);
+				}
+
 				rx_item = rx.next();
 				futs = not_ready;
 			}
@@ -253,8 +354,25 @@ async fn send_task(
 				rx_item = next_rx;
 				futs = future::select(ping_interval.next(), stop);
 			}
-			Either::Right((Either::Right((_stopped, _)), _)) => {
-				// server has stopped
+			Either::Right((Either::Right((stopped, _)), _)) => {
+				match stopped {
+					// server has stopped
+					Stop::Server { drain: d } => drain = d,
+					Stop::SlowConsumer => {
+						let budget = budget.as_ref().expect("Only a connection with a budget can exceed it; qed");
+						close_slow_consumer(&mut ws_sender, budget).await;
+					}
+				}
+				break;
+			}
+		}
+	}
+
+	// Flush the messages that were queued before the connection is drained.
+	if drain {
+		rx.close();
+		while let Some(response) = rx.next().await {
+			if send_message(&mut ws_sender, response).await.is_err() {
 				break;
 			}
 		}
@@ -265,8 +383,27 @@ async fn send_task(
 	rx.close();
 }
 
+/// Why the send task was stopped.
+enum Stop {
+	/// The server was stopped, the queued messages are sent out before closing if `drain` is set.
+	Server { drain: bool },
+	/// A message didn't fit into the buffer budget of the connection.
+	SlowConsumer,
+}
+
+/// Tell the client that it was too slow to read the responses, the buffered messages are discarded.
+///
+/// The caller closes the connection with a close frame afterwards.
+async fn close_slow_consumer(ws_sender: &mut Sender, budget: &BufferBudget) {
+	tracing::debug!(target: LOG_TARGET, "WS message buffer limit exceeded; closing the connection");
+	let err = Response::new(ResponsePayload::<()>::error(reject_slow_consumer(budget.max_bytes())), Id::Null);
+	let json = serde_json::value::to_raw_value(&err).expect("valid JSON; qed");
+	_ = send_message(ws_sender, json).await;
+}
+
 enum Receive<S> {
 	ConnectionClosed,
+	Inactive,
 	Stopped,
 	Err(SokettoError, S),
 	Ok(Vec<u8>, S),
@@ -278,6 +415,7 @@ async fn try_recv<T, S>(
 	mut stopped: S,
 	ping_config: Option<PingConfig>,
 	missed_pings: &mut usize,
+	events: &ConnectionEvents,
 ) -> Receive<S>
 where
 	S: Future<Output = ()> + Unpin,
@@ -312,6 +450,7 @@ where
 				if let Some(p) = ping_config {
 					if last_active.elapsed() > p.inactive_limit {
 						*missed_pings += 1;
+						events.ping_failure(*missed_pings);
 
 						if *missed_pings >= p.max_failures {
 							tracing::debug!(
@@ -319,7 +458,7 @@ where
 								"WS ping/pong inactivity limit `{}` exceeded; closing connection",
 								p.max_failures,
 							);
-							break Receive::ConnectionClosed;
+							break Receive::Inactive;
 						}
 					}
 				}
@@ -337,6 +476,7 @@ where
 pub(crate) enum Shutdown {
 	Stopped,
 	ConnectionClosed,
+	Inactive,
 }
 
 /// Enforce a graceful shutdown.
@@ -346,7 +486,7 @@ async fn graceful_shutdown<S>(
 	result: Result<Shutdown, SokettoError>,
 	pending_calls: mpsc::Receiver<()>,
 	ws_stream: S,
-	mut conn_tx: oneshot::Sender<()>,
+	mut conn_tx: oneshot::Sender<bool>,
 	send_task_handle: tokio::task::JoinHandle<()>,
 ) where
 	S: StreamExt<Item = Result<Incoming, SokettoError>> + Unpin,
@@ -369,11 +509,88 @@ async fn graceful_shutdown<S>(
 	}
 
 	// Send a message to close down the "send task".
-	_ = conn_tx.send(());
+	_ = conn_tx.send(false);
 	// Ensure that send task has been closed.
 	_ = send_task_handle.await;
 }
 
+/// Drain the connection when the server is shut down with [`crate::ServerHandle::graceful_shutdown`].
+///
+/// New calls are rejected until the pending calls have been completed, then the subscriptions
+/// are closed and the connection is closed once the queued messages have been sent.
+///
+/// Returns `true` if the connection was closed forcibly because the deadline elapsed.
+async fn drain_connection<S>(
+	drain: &Drain,
+	sink: &MethodSink,
+	close_subscriptions: impl Future<Output = usize>,
+	pending_calls: mpsc::Receiver<()>,
+	ws_stream: S,
+	mut conn_tx: oneshot::Sender<bool>,
+	mut send_task_handle: tokio::task::JoinHandle<()>,
+) -> bool
+where
+	S: StreamExt<Item = Result<Incoming, SokettoError>> + Unpin,
+{
+	let deadline = tokio::time::sleep_until(drain.deadline);
+	tokio::pin!(deadline);
+
+	let pending_calls = ReceiverStream::new(pending_calls).for_each(|_| async {});
+	let reject_calls = ws_stream.try_for_each(|incoming| async move {
+		if let Incoming::Data(data) = incoming {
+			if let Some(rp) = shutting_down_response(&data) {
+				_ = sink.send(rp).await;
+			}
+		}
+		Ok(())
+	});
+
+	tokio::select! {
+		_ = pending_calls => {}
+		res = reject_calls => {
+			if let Err(err) = res {
+				tracing::warn!(target: LOG_TARGET, "Graceful shutdown terminated because of error: `{err}`");
+			}
+		}
+		_ = conn_tx.closed() => {}
+		_ = &mut deadline => {
+			send_task_handle.abort();
+			return true;
+		}
+	}
+
+	tokio::select! {
+		_ = close_subscriptions => {}
+		_ = &mut deadline => {
+			send_task_handle.abort();
+			return true;
+		}
+	}
+
+	// Close the connection once the queued messages have been sent.
+	_ = conn_tx.send(true);
+
+	tokio::select! {
+		_ = &mut send_task_handle => false,
+		_ = &mut deadline => {
+			send_task_handle.abort();
+			true
+		}
+	}
+}
+
+/// The response to a call that is received while the connection is drained.
+///
+/// Returns `None` for notifications because these are not answered.
+fn shutting_down_response(data: &[u8]) -> Option<Box<RawValue>> {
+	let id = match data.iter().find(|b| !b.is_ascii_whitespace()) {
+		Some(b'{') => serde_json::from_slice::<InvalidRequest>(data).ok()?.id,
+		_ => Id::Null,
+	};
+	let rp = Response::new(ResponsePayload::<()>::error(reject_shutting_down()), id);
+	Some(serde_json::value::to_raw_value(&rp).expect("valid JSON; qed"))
+}
+
 /// Low-level API that tries to upgrade the HTTP connection to a WebSocket connection.
 ///
 /// Returns `Ok((http_response, conn_fut))` if the WebSocket connection was successfully established
@@ -419,7 +636,7 @@ async fn graceful_shutdown<S>(
 /// }
 /// ```
 pub async fn connect<L, B>(
-	req: HttpRequest<B>,
+	mut req: HttpRequest<B>,
 	server_cfg: ServerConfig,
 	methods: impl Into<Methods>,
 	conn: ConnectionState,
@@ -436,42 +653,65 @@ where
 		+ 'static,
 {
 	let mut server = soketto::handshake::http::Server::new();
+	let events = ConnectionEvents::new(server_cfg.observer.as_ref(), None, conn.conn_id.into(), req.headers());
+	events.accept();
 
 	match server.receive_request(&req) {
-		Ok(response) => {
+		Ok(mut response) => {
+			if let Some(hook) = &server_cfg.ws_handshake_hook {
+				if let Err(rp) = handshake::run_hook(&**hook, &mut req, &mut response, None, conn.conn_id.into()).await
+				{
+					events.close(CloseReason::HandshakeRejected);
+					return Err(rp);
+				}
+			}
+
 			let (tx, rx) = mpsc::channel(server_cfg.message_buffer_capacity as usize);
-			let sink = MethodSink::new(tx);
+			let sink = server_cfg.method_sink(tx);
 
 			// On each method call the `pending_calls` is cloned
 			// then when all pending_calls are dropped
 			// a graceful shutdown can has occur.
 			let (pending_calls, pending_calls_completed) = mpsc::channel::<()>(1);
 
+			let methods = methods.into();
+			let registration =
+				server_cfg.introspection.as_ref().map(|i| i.register_ws(conn.conn_id.into(), None, methods.clone()));
+
 			let rpc_service_cfg = RpcServiceCfg::CallsAndSubscriptions {
 				bounded_subscriptions: BoundedSubscriptions::new(server_cfg.max_subscriptions_per_connection),
 				id_provider: server_cfg.id_provider.clone(),
 				sink: sink.clone(),
+				events: events.clone(),
+				tracked: registration.as_ref().map(|r| r.connection().clone()),
 				_pending_calls: pending_calls,
 			};
 
 			let rpc_service = RpcService::new(
-				methods.into(),
+				methods.clone(),
 				server_cfg.max_response_body_size as usize,
 				conn.conn_id.into(),
 				rpc_service_cfg,
+				server_cfg.deprecation_policy,
 			);
 
-			let rpc_service = rpc_middleware.service(rpc_service);
+			let rpc_service = MethodMetaService::new(methods.clone(), rpc_middleware.service(rpc_service));
+
+			events.ws_upgrade();
 
 			// Note: This can't possibly be fulfilled until the HTTP response
 			// is returned below, so that's why it's a separate async block
 			let fut = async move {
-				let extensions = req.extensions().clone();
+				let mut extensions = req.extensions().clone();
+				if extensions.get::<Session>().is_none() {
+					extensions.insert(Session::new());
+				}
 
 				let upgraded = match hyper::upgrade::on(req).await {
 					Ok(upgraded) => upgraded,
 					Err(e) => {
 						tracing::debug!(target: LOG_TARGET, "WS upgrade handshake failed: {}", e);
+						events.close(CloseReason::Error(e.to_string()));
 						return;
 					}
 				};
@@ -483,6 +723,8 @@ where
 				ws_builder.set_max_message_size(server_cfg.max_response_body_size as usize);
 				let (sender, receiver) = ws_builder.finish();
 
+				events.handshake_complete();
+
 				let params = BackgroundTaskParams {
 					server_cfg,
 					conn,
@@ -494,6 +736,9 @@ where
 					pending_calls_completed,
 					on_session_close: None,
 					extensions,
+					events,
+					registration,
+					methods,
 				};
 
 				background_task(params).await;
@@ -503,6 +748,7 @@ where
 		}
 		Err(e) => {
 			tracing::debug!(target: LOG_TARGET, "WS upgrade handshake failed: {}", e);
+			events.close(CloseReason::Completed);
 			Err(HttpResponse::new(HttpBody::from(format!("WS upgrade handshake failed: {e}"))))
 		}
 	}
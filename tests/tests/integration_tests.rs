@@ -1600,3 +1600,135 @@ async fn http_connection_guard_works() {
 		assert_eq!(conn_count, 1);
 	}
 }
+
+#[tokio::test]
+async fn hedged_client_takes_first_success() {
+	use jsonrpsee::core::client::{HedgeDelay, HedgedClientBuilder};
+
+	init_logger();
+
+	async fn server_with_latency(latency: Duration, name: &'static str) -> SocketAddr {
+		let server = ServerBuilder::default().build("127.0.0.1:0").await.unwrap();
+		let addr = server.local_addr().unwrap();
+		let mut module = RpcModule::new(());
+		module
+			.register_async_method("whoami", move |_, _, _| async move {
+				tokio::time::sleep(latency).await;
+				name
+			})
+			.unwrap();
+		let handle = server.start(module);
+		tokio::spawn(handle.stopped());
+		addr
+	}
+
+	let slow = server_with_latency(Duration::from_secs(60), "slow").await;
+	let fast = server_with_latency(Duration::ZERO, "fast").await;
+
+	let clients = [slow, fast].map(|addr| HttpClientBuilder::default().build(format!("http://{addr}")).unwrap());
+	let client = HedgedClientBuilder::new()
+		.method_delay("whoami", HedgeDelay::Fixed(Duration::from_millis(50)))
+		.build(clients)
+		.unwrap();
+
+	// Whichever client gets the call first, the fast one wins.
+	for _ in 0..2 {
+		let rp: String =
+			client.request("whoami", rpc_params![]).with_timeout(Duration::from_secs(10)).await.unwrap().unwrap();
+		assert_eq!(rp, "fast");
+	}
+
+	// Hedging is disabled by default, thus the call to the slow server is not duplicated.
+	let clients = [slow, fast].map(|addr| {
+		HttpClientBuilder::default()
+			.request_timeout(Duration::from_millis(200))
+			.build(format!("http://{addr}"))
+			.unwrap()
+	});
+	let client = HedgedClientBuilder::new().build(clients).unwrap();
+
+	let rps: Vec<Result<String, Error>> =
+		vec![client.request("whoami", rpc_params![]).await, client.request("whoami", rpc_params![]).await];
+	assert!(matches!(rps[0], Err(Error::RequestTimeout)));
+	assert_eq!(rps[1].as_ref().unwrap(), "fast");
+}
+
+#[tokio::test]
+async fn ws_handshake_hook_works() {
+	use futures::future::{BoxFuture, FutureExt};
+	use hyper::{Request, StatusCode};
+	use hyper_util::client::legacy::Client;
+	use jsonrpsee::server::{HandshakeAccept, HandshakeReject, HandshakeRequest, WsHandshakeHook};
+
+	#[derive(Debug, Clone)]
+	struct User(String);
+
+	#[derive(Debug)]
+	struct Auth;
+
+	impl WsHandshakeHook for Auth {
+		fn on_handshake(&self, req: HandshakeRequest) -> BoxFuture<'static, Result<HandshakeAccept, HandshakeReject>> {
+			async move {
+				let Some(user) = req.headers().get("x-user").and_then(|v| v.to_str().ok()) else {
+					return Err(HandshakeReject::new(StatusCode::UNAUTHORIZED, "who are you?"));
+				};
+
+				let accept = HandshakeAccept::new().with_data(User(user.to_owned()));
+				match req.protocols().find(|p| *p == "jsonrpc.v2") {
+					Some(p) => Ok(accept.with_protocol(p)),
+					None => Ok(accept),
+				}
+			}
+			.boxed()
+		}
+	}
+
+	init_logger();
+
+	let server = ServerBuilder::default()
+		.set_config(ServerConfig::builder().set_ws_handshake_hook(Auth).build())
+		.build("127.0.0.1:0")
+		.await
+		.unwrap();
+	let mut module = RpcModule::new(());
+	module.register_method("whoami", |_, _, ext| ext.get::<User>().map(|u| u.0.clone()).unwrap_or_default()).unwrap();
+	let addr = server.local_addr().unwrap();
+	let _handle = server.start(module);
+	let uri = format!("ws://{addr}");
+
+	// The data attached by the hook is available to the handlers.
+	let mut headers = hyper::HeaderMap::new();
+	headers.insert("x-user", HeaderValue::from_static("alice"));
+	let client =
+		WsClientBuilder::default().set_headers(headers).build(&uri).with_default_timeout().await.unwrap().unwrap();
+	let rp: String = client.request("whoami", rpc_params![]).await.unwrap();
+	assert_eq!(rp, "alice");
+
+	// The connection is rejected without the header.
+	assert!(WsClientBuilder::default().build(&uri).with_default_timeout().await.unwrap().is_err());
+
+	let http_client = Client::builder(TokioExecutor::new()).build_http();
+	let upgrade_request = |user: Option<&'static str>| {
+		let mut req = Request::builder()
+			.uri(format!("http://{addr}"))
+			.header("connection", "upgrade")
+			.header("upgrade", "websocket")
+			.header("sec-websocket-version", "13")
+			.header("sec-websocket-key", "dGhlIHNhbXBsZSBub25jZQ==")
+			.header("sec-websocket-protocol", "foo, jsonrpc.v2");
+		if let Some(user) = user {
+			req = req.header("x-user", user);
+		}
+		req.body(HttpBody::default()).unwrap()
+	};
+
+	let rp = http_client.request(upgrade_request(None)).await.unwrap();
+	assert_eq!(rp.status(), StatusCode::UNAUTHORIZED);
+	let body = rp.into_body().collect().await.unwrap().to_bytes();
+	assert_eq!(&body[..], b"who are you?");
+
+	// The subprotocol chosen by the hook is returned to the client.
+	let rp = http_client.request(upgrade_request(Some("bob"))).await.unwrap();
+	assert_eq!(rp.status(), StatusCode::SWITCHING_PROTOCOLS);
+	assert_eq!(rp.headers().get("sec-websocket-protocol").unwrap(), "jsonrpc.v2");
+}
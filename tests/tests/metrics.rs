@@ -39,7 +39,7 @@ use jsonrpsee::core::middleware::{Batch, Notification, Request, RpcServiceBuilde
 use jsonrpsee::core::{ClientError, client::ClientT};
 use jsonrpsee::http_client::HttpClientBuilder;
 use jsonrpsee::proc_macros::rpc;
-use jsonrpsee::server::{Server, ServerHandle};
+use jsonrpsee::server::{DeprecationNotice, MetaValue, MethodMeta, Server, ServerConfig, ServerHandle};
 use jsonrpsee::types::{ErrorObject, ErrorObjectOwned, Id};
 use jsonrpsee::ws_client::WsClientBuilder;
 use jsonrpsee::{MethodResponse, RpcModule, rpc_params};
@@ -51,6 +51,10 @@ struct Counter {
 	requests: (u32, u32),
 	/// Mapping method names to (number of calls, ids of successfully completed calls)
 	calls: HashMap<String, (u32, Vec<Id<'static>>)>,
+	/// Names of the deprecated methods that were called.
+	deprecated_calls: Vec<&'static str>,
+	/// Method names and metadata of the calls to methods with metadata.
+	method_meta: Vec<(String, MethodMeta)>,
 }
 
 #[derive(Clone)]
@@ -80,6 +84,9 @@ where
 				n.requests.0 += 1;
 				let entry = n.calls.entry(name.clone()).or_insert((0, Vec::new()));
 				entry.0 += 1;
+				if let Some(meta) = request.extensions().get::<MethodMeta>() {
+					n.method_meta.push((name.clone(), meta.clone()));
+				}
 			}
 
 			let rp = service.call(request).await;
@@ -90,6 +97,9 @@ where
 				if rp.is_success() {
 					n.calls.get_mut(&name).unwrap().1.push(id.into_owned());
 				}
+				if let Some(notice) = rp.extensions().get::<DeprecationNotice>() {
+					n.deprecated_calls.push(notice.method);
+				}
 			}
 
 			rp
@@ -108,7 +118,7 @@ where
 fn test_module() -> RpcModule<()> {
 	#[rpc(server)]
 	pub trait Rpc {
-		#[method(name = "say_hello")]
+		#[method(name = "say_hello", meta(rate_limit = 10, auth = "admin", cached))]
 		async fn hello(&self) -> String {
 			sleep(Duration::from_millis(50)).await;
 			"hello".to_string()
@@ -228,3 +238,72 @@ async fn http_server_logger() {
 	server_handle.stop().unwrap();
 	server_handle.stopped().await;
 }
+
+#[tokio::test]
+async fn deprecation_notice_is_visible_to_middleware() {
+	init_logger();
+
+	let counter = Arc::new(Mutex::new(Counter::default()));
+	let mut module = test_module();
+	module.deprecate("err", Default::default()).unwrap();
+
+	let rpc_middleware = RpcServiceBuilder::new().layer_fn({
+		let counter = counter.clone();
+		move |service| CounterMiddleware { service, counter: counter.clone() }
+	});
+	let server = Server::builder()
+		.set_config(ServerConfig::builder().enable_deprecation_notice().build())
+		.set_rpc_middleware(rpc_middleware)
+		.build("127.0.0.1:0")
+		.await
+		.unwrap();
+	let server_url = format!("ws://{}", server.local_addr().unwrap());
+	let server_handle = server.start(module.clone());
+
+	let client = WsClientBuilder::default().build(&server_url).await.unwrap();
+
+	let res: String = client.request("say_hello", rpc_params![]).await.unwrap();
+	assert_eq!(res, "hello");
+	let res: Result<String, ClientError> = client.request("err", rpc_params![]).await;
+	assert!(res.is_err());
+
+	assert_eq!(counter.lock().unwrap().deprecated_calls, vec!["err"]);
+	assert_eq!(module.deprecated_method("err").unwrap().calls(), 1);
+
+	server_handle.stop().unwrap();
+	server_handle.stopped().await;
+}
+
+#[tokio::test]
+async fn method_meta_is_visible_to_middleware() {
+	init_logger();
+
+	let counter = Arc::new(Mutex::new(Counter::default()));
+	let module = test_module();
+
+	let expected = MethodMeta::new().with("rate_limit", 10).with("auth", "admin").with("cached", true);
+	assert_eq!(module.method_meta("say_hello"), Some(&expected));
+	assert_eq!(expected.get("rate_limit").and_then(MetaValue::as_int), Some(10));
+	assert!(module.method_meta("err").is_none());
+
+	let (ws_addr, ws_handle) = websocket_server(module.clone(), counter.clone()).await.unwrap();
+	let (http_addr, http_handle) = http_server(module, counter.clone()).await.unwrap();
+
+	let ws_client = WsClientBuilder::default().build(&format!("ws://{ws_addr}")).await.unwrap();
+	let http_client = HttpClientBuilder::default().build(format!("http://{http_addr}")).unwrap();
+
+	let res: String = ws_client.request("say_hello", rpc_params![]).await.unwrap();
+	assert_eq!(res, "hello");
+	let res: String = http_client.request("say_hello", rpc_params![]).await.unwrap();
+	assert_eq!(res, "hello");
+	let res: Result<String, ClientError> = ws_client.request("err", rpc_params![]).await;
+	assert!(res.is_err());
+
+	let expected = vec![("say_hello".to_string(), expected.clone()), ("say_hello".to_string(), expected)];
+	assert_eq!(counter.lock().unwrap().method_meta, expected);
+
+	ws_handle.stop().unwrap();
+	ws_handle.stopped().await;
+	http_handle.stop().unwrap();
+	http_handle.stopped().await;
+}
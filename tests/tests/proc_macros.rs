@@ -478,3 +478,772 @@ async fn sync_sub_works() {
 
 	assert_eq!(sub.next().await.unwrap().unwrap(), "hello");
 }
+
+#[tokio::test]
+async fn retry_idempotent_methods_works() {
+	use std::sync::Arc;
+	use std::sync::atomic::{AtomicUsize, Ordering};
+	use std::time::Duration;
+
+	use jsonrpsee::core::middleware::RpcServiceBuilder;
+	use jsonrpsee::core::middleware::layer::RetryLayer;
+	use jsonrpsee::core::params::BatchRequestBuilder;
+	use jsonrpsee::proc_macros::rpc;
+	use jsonrpsee::types::ErrorObjectOwned;
+	use jsonrpsee::types::error::{SERVER_IS_BUSY_CODE, SERVER_IS_BUSY_MSG};
+
+	#[rpc(client, server, namespace = "flaky")]
+	pub trait Flaky {
+		#[method(name = "get", aliases = ["flaky_read"], idempotent)]
+		fn get(&self) -> Result<usize, ErrorObjectOwned>;
+
+		#[method(name = "incr")]
+		fn incr(&self) -> Result<usize, ErrorObjectOwned>;
+	}
+
+	// Every other call fails with "server is busy".
+	struct FlakyImpl(Arc<AtomicUsize>);
+
+	impl FlakyImpl {
+		fn call(&self) -> Result<usize, ErrorObjectOwned> {
+			let n = self.0.fetch_add(1, Ordering::SeqCst);
+			if n % 2 == 0 {
+				Err(ErrorObjectOwned::owned(SERVER_IS_BUSY_CODE, SERVER_IS_BUSY_MSG, None::<()>))
+			} else {
+				Ok(n)
+			}
+		}
+	}
+
+	impl FlakyServer for FlakyImpl {
+		fn get(&self) -> Result<usize, ErrorObjectOwned> {
+			self.call()
+		}
+
+		fn incr(&self) -> Result<usize, ErrorObjectOwned> {
+			self.call()
+		}
+	}
+
+	assert_eq!(FLAKY_IDEMPOTENT_METHODS, &["flaky_get", "flaky_read"]);
+
+	let calls = Arc::new(AtomicUsize::new(0));
+	let server = ServerBuilder::default().build("127.0.0.1:0").await.unwrap();
+	let addr = server.local_addr().unwrap();
+	let _handle = server.start(FlakyImpl(calls.clone()).into_rpc());
+
+	let retry = RetryLayer::new()
+		.backoff(Duration::from_millis(1), Duration::from_millis(10))
+		.idempotent_methods(FLAKY_IDEMPOTENT_METHODS.iter().copied());
+	let client = HttpClientBuilder::default()
+		.set_rpc_middleware(RpcServiceBuilder::new().layer(retry))
+		.build(format!("http://{addr}"))
+		.unwrap();
+
+	// The idempotent method is retried.
+	assert_eq!(client.get().await.unwrap(), 1);
+	assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+	// Other methods are not retried.
+	assert!(matches!(client.incr().await, Err(Error::Call(e)) if e.code() == SERVER_IS_BUSY_CODE));
+	assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+	// Only the failed batch entry of the idempotent method is retried.
+	let mut batch = BatchRequestBuilder::new();
+	batch.insert("flaky_incr", rpc_params![]).unwrap();
+	batch.insert("flaky_get", rpc_params![]).unwrap();
+	let rps = client.batch_request::<usize>(batch).await.unwrap();
+	let rps: Vec<_> = rps.into_iter().map(|rp| rp.map_err(|e| e.code())).collect();
+
+	assert_eq!(rps, vec![Ok(3), Ok(5)]);
+	assert_eq!(calls.load(Ordering::SeqCst), 6);
+}
+
+#[tokio::test]
+async fn stream_subscriptions_work() {
+	use futures::Stream;
+	use jsonrpsee::core::async_trait;
+	use jsonrpsee::proc_macros::rpc;
+
+	#[rpc(client, server, namespace = "stream")]
+	pub trait Streams {
+		#[subscription(name = "count", unsubscribe = "unsubscribe_count")]
+		fn count(&self, n: usize) -> impl Stream<Item = usize> + Send;
+
+		#[subscription(name = "countAsync", unsubscribe = "unsubscribe_countAsync", item = usize)]
+		async fn count_async(&self, n: usize) -> impl Stream<Item = usize> + Send;
+
+		#[subscription(name = "countExt", unsubscribe = "unsubscribe_countExt", with_extensions)]
+		fn count_ext(&self) -> impl Stream<Item = String> + Send;
+
+		#[subscription(name = "words", unsubscribe = "unsubscribe_words", pipe_config = words_config)]
+		fn words(&self) -> impl Stream<Item = String> + Send;
+	}
+
+	fn words_config() -> jsonrpsee::server::PipeFromStreamConfig {
+		let done = serde_json::value::to_raw_value(&"done").unwrap();
+		jsonrpsee::server::PipeFromStreamConfig::new()
+			.close_response(jsonrpsee::server::SubscriptionCloseResponse::Notif(done.into()))
+	}
+
+	struct StreamsImpl;
+
+	#[async_trait]
+	impl StreamsServer for StreamsImpl {
+		fn count(&self, n: usize) -> impl Stream<Item = usize> + Send {
+			futures::stream::iter(0..n)
+		}
+
+		async fn count_async(&self, n: usize) -> impl Stream<Item = usize> + Send {
+			futures::stream::iter(0..n)
+		}
+
+		fn count_ext(&self, ext: &jsonrpsee::Extensions) -> impl Stream<Item = String> + Send {
+			let conn_id = ext.get::<jsonrpsee::ConnectionId>().map(|id| id.0).unwrap_or_default();
+			futures::stream::iter([conn_id.to_string()])
+		}
+
+		fn words(&self) -> impl Stream<Item = String> + Send {
+			futures::stream::iter(["hello".to_string()])
+		}
+	}
+
+	init_logger();
+
+	let server = ServerBuilder::default().build("127.0.0.1:0").await.unwrap();
+	let addr = server.local_addr().unwrap();
+	let _handle = server.start(StreamsImpl.into_rpc());
+	let client = WsClientBuilder::default().build(format!("ws://{addr}")).await.unwrap();
+
+	let mut sub = client.count(3).await.unwrap();
+	for exp in 0..3 {
+		assert_eq!(sub.next().await.unwrap().unwrap(), exp);
+	}
+
+	let mut sub = client.count_async(2).await.unwrap();
+	for exp in 0..2 {
+		assert_eq!(sub.next().await.unwrap().unwrap(), exp);
+	}
+
+	let mut sub = client.count_ext().await.unwrap();
+	assert_eq!(sub.next().await.unwrap().unwrap(), "0");
+
+	// The close response is configured with `pipe_config`.
+	let mut sub = client.words().await.unwrap();
+	assert_eq!(sub.next().await.unwrap().unwrap(), "hello");
+	assert_eq!(sub.next().await.unwrap().unwrap(), "done");
+}
+
+#[tokio::test]
+async fn session_injection_works() {
+	use jsonrpsee::core::{SubscriptionResult, async_trait};
+	use jsonrpsee::proc_macros::rpc;
+	use jsonrpsee::types::ErrorObjectOwned;
+	use jsonrpsee::{PendingSubscriptionSink, Session};
+
+	#[derive(Clone)]
+	struct User(String);
+
+	#[rpc(client, server, namespace = "session")]
+	pub trait Login {
+		#[method(name = "login", with_session)]
+		fn login(&self, name: String) -> Result<(), ErrorObjectOwned>;
+
+		#[method(name = "whoami", with_extensions, with_session)]
+		async fn whoami(&self) -> Result<Option<String>, ErrorObjectOwned>;
+
+		#[subscription(name = "subscribeUser", unsubscribe = "unsubscribeUser", item = String, with_session)]
+		async fn subscribe_user(&self) -> SubscriptionResult;
+	}
+
+	struct LoginImpl;
+
+	#[async_trait]
+	impl LoginServer for LoginImpl {
+		fn login(&self, session: Session, name: String) -> Result<(), ErrorObjectOwned> {
+			session.insert(User(name));
+			Ok(())
+		}
+
+		async fn whoami(
+			&self,
+			ext: &jsonrpsee::Extensions,
+			session: Session,
+		) -> Result<Option<String>, ErrorObjectOwned> {
+			assert!(ext.get::<Session>().is_some());
+			Ok(session.get::<User>().map(|u| u.0))
+		}
+
+		async fn subscribe_user(&self, pending: PendingSubscriptionSink, session: Session) -> SubscriptionResult {
+			let sink = pending.accept().await?;
+			let user = session.get::<User>().map_or("anonymous".to_string(), |u| u.0);
+			sink.send(serde_json::value::to_raw_value(&user)?).await?;
+			Ok(())
+		}
+	}
+
+	init_logger();
+
+	let server = ServerBuilder::default().build("127.0.0.1:0").await.unwrap();
+	let addr = server.local_addr().unwrap();
+	let _handle = server.start(LoginImpl.into_rpc());
+
+	let alice = WsClientBuilder::default().build(format!("ws://{addr}")).await.unwrap();
+	let bob = WsClientBuilder::default().build(format!("ws://{addr}")).await.unwrap();
+
+	alice.login("alice".to_string()).await.unwrap();
+	assert_eq!(alice.whoami().await.unwrap(), Some("alice".to_string()));
+	assert_eq!(bob.whoami().await.unwrap(), None);
+
+	let mut sub = alice.subscribe_user().await.unwrap();
+	assert_eq!(sub.next().await.unwrap().unwrap(), "alice");
+}
+
+#[tokio::test]
+async fn typed_errors_work() {
+	use jsonrpsee::core::async_trait;
+	use jsonrpsee::core::client::ClientError;
+	use jsonrpsee::proc_macros::{RpcError, rpc};
+	use jsonrpsee::types::ErrorObjectOwned;
+
+	#[derive(Debug, PartialEq, RpcError)]
+	pub enum BankError {
+		#[rpc_error(code = 1000, message = "Unknown account")]
+		UnknownAccount(String),
+		#[rpc_error(code = 1001, message = "Insufficient funds")]
+		InsufficientFunds { balance: u64, required: u64 },
+		#[rpc_error(code = 1002)]
+		Frozen,
+	}
+
+	impl std::fmt::Display for BankError {
+		fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+			write!(f, "{self:?}")
+		}
+	}
+
+	#[rpc(client, server, error = BankError)]
+	pub trait Bank {
+		#[method(name = "withdraw")]
+		async fn withdraw(&self, account: String, amount: u64) -> Result<u64, BankError>;
+
+		#[method(name = "balance")]
+		async fn balance(&self, account: String) -> Result<u64, ErrorObjectOwned>;
+	}
+
+	struct BankImpl;
+
+	#[async_trait]
+	impl BankServer for BankImpl {
+		async fn withdraw(&self, account: String, amount: u64) -> Result<u64, BankError> {
+			match account.as_str() {
+				"alice" if amount <= 10 => Ok(10 - amount),
+				"alice" => Err(BankError::InsufficientFunds { balance: 10, required: amount }),
+				"bob" => Err(BankError::Frozen),
+				_ => Err(BankError::UnknownAccount(account)),
+			}
+		}
+
+		async fn balance(&self, _account: String) -> Result<u64, ErrorObjectOwned> {
+			Err(ErrorObjectOwned::owned(-32050, "Unavailable", None::<()>))
+		}
+	}
+
+	init_logger();
+
+	let server = ServerBuilder::default().build("127.0.0.1:0").await.unwrap();
+	let addr = server.local_addr().unwrap();
+	let _handle = server.start(BankImpl.into_rpc());
+	let client = HttpClientBuilder::default().build(format!("http://{addr}")).unwrap();
+
+	assert_eq!(client.withdraw("alice".into(), 4).await.unwrap(), 6);
+	assert!(matches!(
+		client.withdraw("alice".into(), 20).await,
+		Err(ClientError::Rpc(BankError::InsufficientFunds { balance: 10, required: 20 }))
+	));
+	assert!(matches!(client.withdraw("bob".into(), 1).await, Err(ClientError::Rpc(BankError::Frozen))));
+	assert!(matches!(
+		client.withdraw("carol".into(), 1).await,
+		Err(ClientError::Rpc(BankError::UnknownAccount(account))) if account == "carol"
+	));
+
+	// Errors which aren't part of the API are returned as is.
+	let err = client.balance("alice".into()).await.unwrap_err();
+	assert!(matches!(err.client(), Some(Error::Call(err)) if err.code() == -32050));
+}
+
+#[test]
+#[should_panic(expected = "Error data `BadError::Invalid::keys` cannot be serialized")]
+fn typed_error_data_fails_to_serialize() {
+	use jsonrpsee::proc_macros::RpcError;
+	use jsonrpsee::types::ErrorObjectOwned;
+	use std::collections::HashMap;
+
+	#[derive(Debug, RpcError)]
+	pub enum BadError {
+		#[rpc_error(code = 1000)]
+		Invalid { keys: HashMap<(u8, u8), u8> },
+	}
+
+	// JSON object keys must be strings.
+	let _ = ErrorObjectOwned::from(BadError::Invalid { keys: HashMap::from([((1, 2), 3)]) });
+}
+
+#[tokio::test]
+async fn default_params_work() {
+	use jsonrpsee::MethodsError;
+	use jsonrpsee::core::{RpcResult, async_trait};
+	use jsonrpsee::proc_macros::rpc;
+
+	#[rpc(client, server)]
+	pub trait Defaults {
+		#[method(name = "array", deny_unknown_params)]
+		async fn array(
+			&self,
+			a: u64,
+			#[argument(default = 10)] b: u64,
+			#[argument(default)] c: String,
+		) -> RpcResult<String>;
+
+		#[method(name = "map", param_kind = map, deny_unknown_params)]
+		async fn map(
+			&self,
+			a: u64,
+			#[argument(default = 10)] b: u64,
+			#[argument(default, rename = "cValue")] c: String,
+		) -> RpcResult<String>;
+
+		#[method(name = "noParams", deny_unknown_params)]
+		async fn no_params(&self) -> RpcResult<u64>;
+	}
+
+	struct DefaultsImpl;
+
+	#[async_trait]
+	impl DefaultsServer for DefaultsImpl {
+		async fn array(&self, a: u64, b: u64, c: String) -> RpcResult<String> {
+			Ok(format!("{a} {b} {c:?}"))
+		}
+
+		async fn map(&self, a: u64, b: u64, c: String) -> RpcResult<String> {
+			Ok(format!("{a} {b} {c:?}"))
+		}
+
+		async fn no_params(&self) -> RpcResult<u64> {
+			Ok(1)
+		}
+	}
+
+	init_logger();
+
+	let module = DefaultsImpl.into_rpc();
+
+	// Array params.
+	let res: String = module.call("array", [1]).await.unwrap();
+	assert_eq!(res, r#"1 10 """#);
+	let res: String = module.call("array", [json!(1), json!(null), json!("c")]).await.unwrap();
+	assert_eq!(res, r#"1 10 "c""#);
+	let err = module.call::<_, String>("array", [json!(1), json!(2), json!("c"), json!(4)]).await.unwrap_err();
+	assert!(matches!(err, MethodsError::JsonRpc(err) if err.code() == ErrorCode::InvalidParams.code()));
+
+	// Map params.
+	let (resp, _) = module
+		.raw_json_request(r#"{"jsonrpc":"2.0","method":"map","params":{"a":1,"cValue":"c"},"id":0}"#, 1)
+		.await
+		.unwrap();
+	assert_eq!(resp.get(), r#"{"jsonrpc":"2.0","id":0,"result":"1 10 \"c\""}"#);
+	let (resp, _) =
+		module.raw_json_request(r#"{"jsonrpc":"2.0","method":"map","params":{"a":1,"d":4},"id":0}"#, 1).await.unwrap();
+	assert!(resp.get().contains(r#""code":-32602"#));
+
+	// Methods without params.
+	let res: u64 = module.call("noParams", rpc_params![]).await.unwrap();
+	assert_eq!(res, 1);
+	let err = module.call::<_, u64>("noParams", [1]).await.unwrap_err();
+	assert!(matches!(err, MethodsError::JsonRpc(err) if err.code() == ErrorCode::InvalidParams.code()));
+
+	// The client omits the arguments which are not set.
+	let server = ServerBuilder::default().build("127.0.0.1:0").await.unwrap();
+	let addr = server.local_addr().unwrap();
+	let _handle = server.start(module);
+	let client = HttpClientBuilder::default().build(format!("http://{addr}")).unwrap();
+
+	assert_eq!(client.array_builder(1).send().await.unwrap(), r#"1 10 """#);
+	assert_eq!(client.array_builder(1).c("c".into()).send().await.unwrap(), r#"1 10 "c""#);
+	assert_eq!(client.array(1, 2, "c".into()).await.unwrap(), r#"1 2 "c""#);
+	assert_eq!(client.map_builder(1).send().await.unwrap(), r#"1 10 """#);
+	assert_eq!(client.map_builder(1).b(2).c("c".into()).send().await.unwrap(), r#"1 2 "c""#);
+}
+
+#[tokio::test]
+async fn typed_batch_works() {
+	use jsonrpsee::core::{RpcResult, async_trait};
+	use jsonrpsee::proc_macros::rpc;
+	use jsonrpsee::types::ErrorObjectOwned;
+
+	#[rpc(client, server)]
+	pub trait Chain {
+		#[method(name = "blockNumber")]
+		async fn block_number(&self) -> RpcResult<u64>;
+
+		#[method(name = "blockHash")]
+		async fn block_hash(&self, number: u64) -> RpcResult<String>;
+
+		#[method(name = "header", param_kind = map)]
+		async fn header(&self, hash: String) -> RpcResult<Vec<u8>>;
+
+		// Doesn't clash with `ClientT::typed_batch`.
+		#[method(name = "batch")]
+		async fn batch(&self) -> RpcResult<u64>;
+	}
+
+	struct ChainImpl;
+
+	#[async_trait]
+	impl ChainServer for ChainImpl {
+		async fn block_number(&self) -> RpcResult<u64> {
+			Ok(7)
+		}
+
+		async fn block_hash(&self, number: u64) -> RpcResult<String> {
+			Ok(format!("0x{number}"))
+		}
+
+		async fn header(&self, hash: String) -> RpcResult<Vec<u8>> {
+			if hash == "0x7" { Ok(vec![7]) } else { Err(ErrorObjectOwned::owned(1, "Unknown block", None::<()>)) }
+		}
+
+		async fn batch(&self) -> RpcResult<u64> {
+			Ok(1)
+		}
+	}
+
+	init_logger();
+
+	let server = ServerBuilder::default().build("127.0.0.1:0").await.unwrap();
+	let addr = server.local_addr().unwrap();
+	let _handle = server.start(ChainImpl.into_rpc());
+	let client = HttpClientBuilder::default().build(format!("http://{addr}")).unwrap();
+
+	let (number, hash, header, unknown, batch) = client
+		.typed_batch()
+		.block_number()
+		.block_hash(7)
+		.header("0x7".to_string())
+		.header("0x8".to_string())
+		.batch()
+		.send()
+		.await
+		.unwrap();
+
+	assert_eq!(number.unwrap(), 7);
+	assert_eq!(hash.unwrap(), "0x7");
+	assert_eq!(header.unwrap(), vec![7]);
+	assert_eq!(unknown.unwrap_err().message(), "Unknown block");
+	assert_eq!(batch.unwrap(), 1);
+	assert_eq!(client.batch().await.unwrap(), 1);
+
+	// An empty batch is rejected by the client.
+	assert!(matches!(client.typed_batch().send().await, Err(Error::EmptyBatchRequest(_))));
+}
+
+#[tokio::test]
+#[allow(deprecated)]
+async fn versioned_and_deprecated_methods_work() {
+	use jsonrpsee::core::{RpcResult, async_trait};
+	use jsonrpsee::proc_macros::rpc;
+	use jsonrpsee::server::{Deprecation, ServerConfig};
+
+	#[rpc(client, server, namespace = "state")]
+	pub trait Versioned {
+		#[deprecated(since = "1.2.0", note = "use `get_v2`")]
+		#[method(name = "get", version = 1, aliases = ["state_getLegacy"])]
+		async fn get_v1(&self) -> RpcResult<u64>;
+
+		#[method(name = "get", version = 2)]
+		async fn get_v2(&self) -> RpcResult<String>;
+	}
+
+	#[rpc(server, namespace = "chain", namespace_separator = ".", version_style = namespace)]
+	pub trait Namespaced {
+		#[method(name = "head", version = 3)]
+		async fn head(&self) -> RpcResult<u64>;
+	}
+
+	struct VersionedImpl;
+
+	#[async_trait]
+	impl VersionedServer for VersionedImpl {
+		async fn get_v1(&self) -> RpcResult<u64> {
+			Ok(1)
+		}
+
+		async fn get_v2(&self) -> RpcResult<String> {
+			Ok("2".to_string())
+		}
+	}
+
+	#[async_trait]
+	impl NamespacedServer for VersionedImpl {
+		async fn head(&self) -> RpcResult<u64> {
+			Ok(3)
+		}
+	}
+
+	init_logger();
+
+	let mut module = VersionedServer::into_rpc(VersionedImpl);
+	module.merge(NamespacedServer::into_rpc(VersionedImpl)).unwrap();
+
+	let mut names: Vec<_> = module.method_names().collect();
+	names.sort();
+	assert_eq!(names, vec!["chain.v3.head", "state_getLegacy", "state_get_v1", "state_get_v2"]);
+
+	// Aliases of deprecated methods are deprecated too.
+	let mut deprecated: Vec<_> = module.deprecated_methods().map(|(name, _)| name).collect();
+	deprecated.sort();
+	assert_eq!(deprecated, vec!["state_getLegacy", "state_get_v1"]);
+	assert_eq!(
+		module.deprecated_method("state_getLegacy").unwrap().deprecation(),
+		module.deprecated_method("state_get_v1").unwrap().deprecation()
+	);
+	assert_eq!(
+		module.deprecated_method("state_get_v1").unwrap().deprecation(),
+		&Deprecation { since: Some("1.2.0".to_string()), note: Some("use `get_v2`".to_string()) }
+	);
+
+	// The calls of deprecated methods are counted by the server.
+	let server = ServerBuilder::default().build("127.0.0.1:0").await.unwrap();
+	let addr = server.local_addr().unwrap();
+	let _handle = server.start(module.clone());
+	let client = HttpClientBuilder::default().build(format!("http://{addr}")).unwrap();
+
+	assert_eq!(client.get_v1().await.unwrap(), 1);
+	assert_eq!(client.get_v1().await.unwrap(), 1);
+	assert_eq!(client.get_v2().await.unwrap(), "2");
+	assert_eq!(client.request::<u64, _>("state_getLegacy", rpc_params![]).await.unwrap(), 1);
+	assert_eq!(module.deprecated_method("state_get_v1").unwrap().calls(), 2);
+	assert_eq!(module.deprecated_method("state_getLegacy").unwrap().calls(), 1);
+
+	// Deprecated methods can be disabled.
+	let server = ServerBuilder::default()
+		.set_config(ServerConfig::builder().disable_deprecated_methods().build())
+		.build("127.0.0.1:0")
+		.await
+		.unwrap();
+	let addr = server.local_addr().unwrap();
+	let _handle = server.start(module.clone());
+	let client = HttpClientBuilder::default().build(format!("http://{addr}")).unwrap();
+
+	assert!(matches!(client.get_v1().await, Err(Error::Call(err)) if err.code() == ErrorCode::MethodNotFound.code()));
+	assert!(matches!(
+		client.request::<u64, _>("state_getLegacy", rpc_params![]).await,
+		Err(Error::Call(err)) if err.code() == ErrorCode::MethodNotFound.code()
+	));
+	assert_eq!(client.get_v2().await.unwrap(), "2");
+	assert_eq!(module.deprecated_method("state_get_v1").unwrap().calls(), 2);
+	assert_eq!(module.deprecated_method("state_getLegacy").unwrap().calls(), 1);
+}
+
+#[test]
+fn typescript_bindings_work() {
+	use jsonrpsee::core::{RpcResult, SubscriptionResult};
+	use jsonrpsee::proc_macros::rpc;
+
+	#[rpc(server, typescript, namespace = "chain")]
+	pub trait Chain<Hash> {
+		/// Returns the hash of the block.
+		#[method(name = "getBlockHash", aliases = ["chain_getHead"])]
+		async fn block_hash(&self, number: Option<u64>) -> RpcResult<Option<Hash>>;
+
+		#[method(name = "getHeader", param_kind = map)]
+		async fn header(&self, #[argument(rename = "blockHash")] block_hash: Hash, full: bool) -> RpcResult<Header>;
+
+		#[method(name = "transactions")]
+		fn transactions(&self, hashes: Vec<Option<Hash>>, #[argument(default)] limit: u32) -> RpcResult<Vec<String>>;
+
+		#[subscription(name = "subscribeHeads" => "newHead", unsubscribe = "unsubscribeHeads", item = Header)]
+		async fn subscribe_heads(&self) -> SubscriptionResult;
+
+		#[method(name = "odd\"name\u{0}1\u{301}", param_kind = map)]
+		fn odd_name(&self, package: String) -> RpcResult<()>;
+	}
+
+	#[allow(dead_code)]
+	#[derive(Clone, serde::Serialize, serde::Deserialize)]
+	pub struct Header;
+
+	let source = chain_typescript();
+
+	assert!(source.contains("The following types must be provided by the user: `Header`."));
+	assert!(source.contains("// 64-bit and larger integers are typed `number | bigint`."));
+	assert!(source.contains("  blockHash: { name: \"chain_getBlockHash\", aliases: [\"chain_getHead\"] },"));
+	assert!(source.contains("export interface ChainApi<Hash> {"));
+	assert!(source.contains("   * Returns the hash of the block.\n   *\n   * Aliases: `chain_getHead`.\n"));
+	assert!(source.contains(
+		"  blockHash(number?: number | bigint | null): Promise<Hash | null> {\n    return this.transport.request(\"chain_getBlockHash\", [number ?? null]);\n  }"
+	));
+	assert!(source.contains(
+		"  header(blockHash: Hash, full: boolean): Promise<Header> {\n    return this.transport.request(\"chain_getHeader\", { \"blockHash\": blockHash, \"full\": full });\n  }"
+	));
+	assert!(source.contains("  transactions(hashes: (Hash | null)[], limit?: number): Promise<string[]>;"));
+	assert!(source.contains(
+		"    return this.transport.subscribe(\"chain_subscribeHeads\", [], \"chain_unsubscribeHeads\", \"chain_newHead\");"
+	));
+	// Method names are escaped for JavaScript and reserved words of strict mode aren't used as parameter names.
+	assert!(source.contains(
+		"  oddName(package_: string): Promise<null> {\n    return this.transport.request(\"chain_odd\\\"name\\u00001\u{301}\", { \"package\": package_ });\n  }"
+	));
+}
+
+#[tokio::test]
+async fn mock_client_works() {
+	use jsonrpsee::core::{RpcResult, SubscriptionResult};
+	use jsonrpsee::proc_macros::rpc;
+	use jsonrpsee::types::ErrorObject;
+
+	#[rpc(client, server, mock, namespace = "state")]
+	pub trait Storage<Key> {
+		#[method(name = "get")]
+		async fn get(&self, key: Key, at: Option<u64>) -> RpcResult<Option<String>>;
+
+		#[method(name = "set", param_kind = map)]
+		async fn set(&self, key: Key, value: String, #[argument(default)] ttl: u64) -> RpcResult<bool>;
+
+		#[method(name = "touch")]
+		async fn touch(&self, key: Key);
+
+		#[subscription(name = "subscribeKey", unsubscribe = "unsubscribeKey", item = String)]
+		async fn subscribe_key(&self, key: Key) -> SubscriptionResult;
+	}
+
+	let mock = StorageMock::<u32>::new();
+	mock.expect_get(|key, at| Ok(at.map(|at| format!("{key}@{at}"))))
+		.expect_set(|key, _, _| if key == 0 { Err(ErrorObject::owned(1, "Invalid key", None::<()>)) } else { Ok(true) })
+		.expect_touch(|_| Ok(()))
+		.expect_subscribe_key(|key| Ok(vec![format!("{key}-1"), format!("{key}-2")]));
+
+	assert_eq!(mock.get(1, Some(10)).await.unwrap(), Some("1@10".to_string()));
+	assert_eq!(mock.get(2, None).await.unwrap(), None);
+	assert_eq!(mock.get_calls(), vec![(1, Some(10)), (2, None)]);
+
+	// Arguments with a default value may be omitted with the builder.
+	assert!(mock.set(1, "a".to_string(), 5).await.unwrap());
+	assert!(mock.set_builder(2, "b".to_string()).send().await.unwrap());
+	assert!(matches!(mock.set(0, "c".to_string(), 0).await, Err(Error::Call(err)) if err.message() == "Invalid key"));
+	assert_eq!(mock.set_calls(), vec![(1, "a".to_string(), 5), (2, "b".to_string(), 0), (0, "c".to_string(), 0)]);
+
+	mock.touch(3).await.unwrap();
+	assert_eq!(mock.touch_calls(), vec![(3,)]);
+
+	let mut sub = mock.subscribe_key(7).await.unwrap();
+	assert_eq!(sub.next().await.unwrap().unwrap(), "7-1");
+	assert_eq!(sub.next().await.unwrap().unwrap(), "7-2");
+	assert!(sub.next().await.is_none());
+
+	// Batches are answered by the mock as well.
+	let (value, set) = mock.typed_batch().get(4, Some(1)).set(5, "d".to_string(), 1).send().await.unwrap();
+	assert_eq!(value.unwrap(), Some("4@1".to_string()));
+	assert!(set.unwrap());
+
+	mock.checkpoint();
+
+	// Invalid params are rejected before the expectation is called.
+	let res = ClientT::request::<bool, _>(&mock, "state_set", rpc_params![]).await;
+	assert!(matches!(res, Err(Error::Call(err)) if err.code() == ErrorCode::InvalidParams.code()));
+	assert_eq!(mock.set_calls().len(), 4);
+}
+
+#[tokio::test]
+#[should_panic(expected = "Unexpected call of RPC method `state_get`: no expectation was set")]
+async fn mock_client_panics_on_unexpected_call() {
+	use jsonrpsee::proc_macros::rpc;
+
+	#[rpc(client, mock, namespace = "state")]
+	pub trait Storage {
+		#[method(name = "get")]
+		async fn get(&self, key: u32) -> jsonrpsee::core::RpcResult<Option<String>>;
+	}
+
+	let _ = StorageMock::new().get(1).await;
+}
+
+#[test]
+#[should_panic(expected = "Expected a call of RPC method `state_get` which was never made")]
+fn mock_client_checkpoint_panics_on_missing_call() {
+	use jsonrpsee::proc_macros::rpc;
+
+	#[rpc(client, mock, namespace = "state")]
+	pub trait Storage {
+		#[method(name = "get")]
+		async fn get(&self, key: u32) -> jsonrpsee::core::RpcResult<Option<String>>;
+	}
+
+	let mock = StorageMock::new();
+	mock.expect_get(|_| Ok(None));
+	mock.checkpoint();
+}
+
+#[tokio::test]
+async fn associated_types_work() {
+	use jsonrpsee::PendingSubscriptionSink;
+	use jsonrpsee::core::{RpcResult, SubscriptionResult, async_trait};
+	use jsonrpsee::proc_macros::rpc;
+
+	#[rpc(client, server, namespace = "chain")]
+	pub trait Chain {
+		type Hash;
+		type Block: std::fmt::Debug;
+
+		#[method(name = "getBlock")]
+		async fn block(&self, hash: Self::Hash) -> RpcResult<Option<Self::Block>>;
+
+		#[method(name = "getHashes")]
+		fn hashes(&self, #[argument(default)] limit: usize) -> RpcResult<Vec<Self::Hash>>;
+
+		#[subscription(name = "subscribeBlocks", unsubscribe = "unsubscribeBlocks", item = Self::Block)]
+		async fn subscribe_blocks(&self) -> SubscriptionResult;
+	}
+
+	#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+	pub struct Block {
+		number: u64,
+	}
+
+	struct ChainImpl;
+
+	#[async_trait]
+	impl ChainServer for ChainImpl {
+		type Hash = String;
+		type Block = Block;
+
+		async fn block(&self, hash: String) -> RpcResult<Option<Block>> {
+			Ok(hash.strip_prefix("0x").and_then(|n| n.parse().ok()).map(|number| Block { number }))
+		}
+
+		fn hashes(&self, limit: usize) -> RpcResult<Vec<String>> {
+			Ok((0..limit.max(1)).map(|n| format!("0x{n}")).collect())
+		}
+
+		async fn subscribe_blocks(&self, pending: PendingSubscriptionSink) -> SubscriptionResult {
+			let sink = pending.accept().await?;
+			sink.send(serde_json::value::to_raw_value(&Block { number: 1 })?).await?;
+			Ok(())
+		}
+	}
+
+	init_logger();
+
+	let server = ServerBuilder::default().build("127.0.0.1:0").await.unwrap();
+	let addr = server.local_addr().unwrap();
+	let _handle = server.start(ChainImpl.into_rpc());
+	let client = WsClientBuilder::default().build(format!("ws://{addr}")).await.unwrap();
+
+	// The associated types are type parameters of the client.
+	let block: Option<Block> = ChainClient::<String, Block>::block(&client, "0x7".to_string()).await.unwrap();
+	assert_eq!(block, Some(Block { number: 7 }));
+	assert_eq!(ChainClient::<String, Block>::hashes(&client, 2).await.unwrap(), vec!["0x0", "0x1"]);
+
+	let mut sub = ChainClient::<String, Block>::subscribe_blocks(&client).await.unwrap();
+	assert_eq!(sub.next().await.unwrap().unwrap(), Block { number: 1 });
+}
@@ -93,6 +93,27 @@ fn rpc_register_alias() {
 	assert!(module.method("hello_foobar").is_some());
 }
 
+#[test]
+fn rpc_method_meta() {
+	let mut module = RpcModule::new(());
+	module.register_method("hello_world", |_, _, _| RpcResult::Ok(())).unwrap();
+
+	let meta = MethodMeta::new().with("rate_limit", 10).with("auth", "admin");
+	assert!(module.set_method_meta("unknown", meta.clone()).is_err());
+	module.set_method_meta("hello_world", meta.clone()).unwrap();
+	assert_eq!(module.method_meta("hello_world"), Some(&meta));
+	assert_eq!(meta.get("auth").and_then(MetaValue::as_str), Some("admin"));
+
+	let mut other = RpcModule::new(());
+	other.register_method("hello_other", |_, _, _| RpcResult::Ok(())).unwrap();
+	other.set_method_meta("hello_other", MethodMeta::new().with("cached", true)).unwrap();
+	module.merge(other).unwrap();
+	assert_eq!(module.method_meta("hello_other").and_then(|m| m.get("cached")), Some(&MetaValue::Bool(true)));
+
+	module.remove_method("hello_world");
+	assert!(module.method_meta("hello_world").is_none());
+}
+
 #[tokio::test]
 async fn calling_method_without_server() {
 	// Call sync method with no params
@@ -499,6 +520,249 @@ async fn bounded_subscription_works() {
 	}
 }
 
+#[tokio::test]
+async fn pipe_from_stream_works() {
+	init_logger();
+
+	let mut module = RpcModule::new(());
+	module
+		.register_subscription("my_sub", "my_sub", "my_unsub", |_, pending, _, _| async move {
+			let config = PipeFromStreamConfig::new().close_response(SubscriptionCloseResponse::Notif(
+				SubscriptionMessage::from(RawValue::from_string("\"done\"".into()).unwrap()),
+			));
+			pending.pipe_from_stream(futures::stream::iter(0..3_usize), config).await
+		})
+		.unwrap();
+
+	let mut sub = module.subscribe("my_sub", EmptyServerParams::new(), 4).await.unwrap();
+	for exp in 0..3 {
+		let (item, _) = sub.next::<usize>().await.unwrap().unwrap();
+		assert_eq!(item, exp);
+	}
+
+	let (close, _) = sub.next::<String>().await.unwrap().unwrap();
+	assert_eq!(close, "done");
+}
+
+#[tokio::test]
+async fn pipe_from_stream_item_timeout_works() {
+	init_logger();
+
+	let mut module = RpcModule::new(());
+	module
+		.register_subscription("my_sub", "my_sub", "my_unsub", |_, pending, _, _| async move {
+			let config = PipeFromStreamConfig::new().item_timeout(Duration::from_millis(100));
+			pending.pipe_from_stream(futures::stream::iter(0..10_usize), config).await
+		})
+		.unwrap();
+
+	// the subscription isn't polled, so the buffer gets full and the item timeout elapses.
+	let mut sub = module.subscribe("my_sub", EmptyServerParams::new(), 1).await.unwrap();
+	tokio::time::sleep(Duration::from_millis(200)).await;
+
+	let (item, _) = sub.next::<usize>().await.unwrap().unwrap();
+	assert_eq!(item, 0);
+
+	// the subscription error notification terminates the subscription.
+	assert!(sub.next::<usize>().await.is_none());
+}
+
+#[tokio::test]
+async fn pubsub_hub_works() {
+	init_logger();
+
+	let hub = PubSubHub::new();
+	let mut module = RpcModule::new(hub.clone());
+	module
+		.register_subscription("my_sub", "my_sub", "my_unsub", |params, pending, hub, _| async move {
+			let topic: String = params.one()?;
+			let sink = pending.accept().await?;
+			hub.subscribe(topic, sink);
+			Ok(())
+		})
+		.unwrap();
+
+	let mut sub1 = module.subscribe("my_sub", ["a"], 4).await.unwrap();
+	let mut sub2 = module.subscribe("my_sub", ["a"], 4).await.unwrap();
+	let mut sub3 = module.subscribe("my_sub", ["b"], 4).await.unwrap();
+	assert_eq!(hub.subscriber_count("a"), 2);
+	assert_eq!(hub.subscriber_count("b"), 1);
+
+	assert_eq!(hub.publish("a", &"hello").await.unwrap(), 2);
+	assert_eq!(hub.publish("b", &1337).await.unwrap(), 1);
+	assert_eq!(hub.publish("c", &()).await.unwrap(), 0);
+
+	let (msg, id) = sub1.next::<String>().await.unwrap().unwrap();
+	assert_eq!(msg, "hello");
+	assert_eq!(&id, sub1.subscription_id());
+	let (msg, id) = sub2.next::<String>().await.unwrap().unwrap();
+	assert_eq!(msg, "hello");
+	assert_eq!(&id, sub2.subscription_id());
+	assert_eq!(sub3.next::<usize>().await.unwrap().unwrap().0, 1337);
+
+	// closed subscriptions are removed from the hub.
+	drop(sub1);
+	tokio::time::timeout(Duration::from_secs(5), async {
+		while hub.subscriber_count("a") != 1 {
+			tokio::time::sleep(Duration::from_millis(10)).await;
+		}
+	})
+	.await
+	.unwrap();
+	assert_eq!(hub.publish("a", &"bye").await.unwrap(), 1);
+}
+
+#[tokio::test]
+async fn pubsub_hubs_with_shared_backend_works() {
+	init_logger();
+
+	let backend = InProcessBackend::new();
+	let hub_a = PubSubHub::with_backend(backend.clone());
+	let hub_b = PubSubHub::with_backend(backend);
+
+	let mut module_a = RpcModule::new(hub_a.clone());
+	let mut module_b = RpcModule::new(hub_b.clone());
+
+	for module in [&mut module_a, &mut module_b] {
+		module
+			.register_subscription("my_sub", "my_sub", "my_unsub", |_, pending, hub, _| async move {
+				let sink = pending.accept().await?;
+				hub.subscribe("a", sink);
+				Ok(())
+			})
+			.unwrap();
+	}
+
+	let mut sub_a = module_a.subscribe("my_sub", EmptyServerParams::new(), 4).await.unwrap();
+	let mut sub_b = module_b.subscribe("my_sub", EmptyServerParams::new(), 4).await.unwrap();
+
+	// only the local subscribers are counted.
+	assert_eq!(hub_a.publish("a", &1).await.unwrap(), 1);
+	assert_eq!(hub_b.publish("a", &2).await.unwrap(), 1);
+
+	// each subscriber gets each message exactly once.
+	for sub in [&mut sub_a, &mut sub_b] {
+		let mut items =
+			vec![sub.next::<usize>().await.unwrap().unwrap().0, sub.next::<usize>().await.unwrap().unwrap().0];
+		items.sort();
+		assert_eq!(items, vec![1, 2]);
+	}
+
+	assert!(tokio::time::timeout(Duration::from_millis(100), sub_a.next::<usize>()).await.is_err());
+}
+
+#[tokio::test]
+async fn overflow_policy_drop_oldest_works() {
+	init_logger();
+
+	let (tx, mut rx) = mpsc::unbounded_channel::<u64>();
+	let mut module = RpcModule::new(tx);
+
+	module
+		.register_subscription("my_sub", "my_sub", "my_unsub", |_, pending, ctx, _| async move {
+			let sink = pending.accept_with_policy(OverflowPolicy::DropOldest(2)).await?;
+
+			for n in 0..10_usize {
+				sink.send(serde_json::value::to_raw_value(&n).unwrap()).await?;
+			}
+
+			ctx.send(sink.dropped_items()).unwrap();
+			Ok(())
+		})
+		.unwrap();
+
+	// the subscriber is slower than the producer, so only the latest items are kept.
+	let mut sub = module.subscribe("my_sub", EmptyServerParams::new(), 1).await.unwrap();
+	let dropped = rx.recv().await.unwrap();
+	assert!(dropped > 0);
+
+	let mut items = Vec::new();
+	for _ in 0..(10 - dropped) {
+		let (item, _) = sub.next::<usize>().await.unwrap().unwrap();
+		items.push(item);
+	}
+
+	assert!(items.windows(2).all(|w| w[0] < w[1]));
+	assert_eq!(items.last(), Some(&9));
+}
+
+#[tokio::test]
+async fn overflow_policy_conflate_latest_works() {
+	init_logger();
+
+	let (tx, mut rx) = mpsc::unbounded_channel::<u64>();
+	let mut module = RpcModule::new(tx);
+
+	module
+		.register_subscription("my_sub", "my_sub", "my_unsub", |_, pending, ctx, _| async move {
+			let sink = pending.accept_with_policy(OverflowPolicy::ConflateLatest).await.unwrap();
+
+			for n in 0..10_usize {
+				sink.send(serde_json::value::to_raw_value(&n).unwrap()).await.unwrap();
+			}
+
+			ctx.send(sink.dropped_items()).unwrap();
+			SubscriptionCloseResponse::Notif(SubscriptionMessage::from(
+				RawValue::from_string("\"done\"".into()).unwrap(),
+			))
+		})
+		.unwrap();
+
+	let mut sub = module.subscribe("my_sub", EmptyServerParams::new(), 1).await.unwrap();
+	let dropped = rx.recv().await.unwrap();
+	assert!(dropped > 0);
+
+	let mut last = None;
+	for _ in 0..(10 - dropped) {
+		let (item, _) = sub.next::<usize>().await.unwrap().unwrap();
+		last = Some(item);
+	}
+	assert_eq!(last, Some(9));
+
+	// the close notification is sent after the queued items.
+	let (close, _) = sub.next::<String>().await.unwrap().unwrap();
+	assert_eq!(close, "done");
+}
+
+#[tokio::test]
+async fn overflow_policy_close_with_lag_error_works() {
+	init_logger();
+
+	let (tx, mut rx) = mpsc::unbounded_channel::<(usize, u64)>();
+	let mut module = RpcModule::new(tx);
+
+	module
+		.register_subscription("my_sub", "my_sub", "my_unsub", |_, pending, ctx, _| async move {
+			let sink = pending.accept_with_policy(OverflowPolicy::CloseWithLagError).await?;
+
+			let mut sent = 0;
+			for n in 0..10_usize {
+				if sink.send(serde_json::value::to_raw_value(&n).unwrap()).await.is_err() {
+					break;
+				}
+				sent += 1;
+			}
+
+			assert!(sink.is_closed());
+			ctx.send((sent, sink.dropped_items())).unwrap();
+			Ok(())
+		})
+		.unwrap();
+
+	let mut sub = module.subscribe("my_sub", EmptyServerParams::new(), 1).await.unwrap();
+	let (sent, dropped) = rx.recv().await.unwrap();
+	assert!(sent < 10);
+	assert_eq!(dropped, 1);
+
+	for exp in 0..sent {
+		let (item, _) = sub.next::<usize>().await.unwrap().unwrap();
+		assert_eq!(item, exp);
+	}
+
+	// the subscription error notification terminates the subscription.
+	assert!(sub.next::<usize>().await.is_none());
+}
+
 #[tokio::test]
 async fn serialize_sub_error_json() {
 	#[derive(Serialize, Deserialize)]
@@ -677,6 +941,61 @@ async fn conn_id_in_rpc_module_without_server() {
 	);
 }
 
+#[tokio::test]
+async fn register_handler_with_extractors_works() {
+	use jsonrpsee::core::params::ObjectParams;
+	use jsonrpsee::types::error::INTERNAL_ERROR_CODE;
+
+	#[derive(Deserialize)]
+	struct Greet {
+		name: String,
+		#[serde(default)]
+		excited: bool,
+	}
+
+	let mut module = RpcModule::new(String::from("hello"));
+	module.register_handler("add", |Positional((a, b)): Positional<(u64, u64)>| async move { a + b }).unwrap();
+	module
+		.register_handler("greet", |Named(greet): Named<Greet>, Ctx(ctx): Ctx<String>| async move {
+			format!("{ctx} {}{}", greet.name, if greet.excited { "!" } else { "" })
+		})
+		.unwrap();
+	module
+		.register_handler("conn_id", |conn_id: ConnectionId, session: Option<SessionData<u32>>| async move {
+			[Some(conn_id.0 as u32), session.map(|s| s.0)]
+		})
+		.unwrap();
+	module.register_handler("extension", |Extension(data): Extension<u64>| async move { data }).unwrap();
+
+	let res: u64 = module.call("add", (1, 2)).await.unwrap();
+	assert_eq!(res, 3);
+
+	let mut params = ObjectParams::new();
+	params.insert("name", "world").unwrap();
+	let res: String = module.call("greet", params).await.unwrap();
+	assert_eq!(res, "hello world");
+
+	let mut params = ObjectParams::new();
+	params.insert("name", "world").unwrap();
+	params.insert("excited", true).unwrap();
+	let res: String = module.call("greet", params).await.unwrap();
+	assert_eq!(res, "hello world!");
+
+	let res: [Option<u32>; 2] = module.call("conn_id", EmptyServerParams::new()).await.unwrap();
+	assert_eq!(res, [Some(0), None]);
+
+	// Params that can't be extracted are rejected with invalid params.
+	let err = module.call::<_, u64>("add", (1, "two")).await.unwrap_err();
+	assert!(matches!(err, MethodsError::JsonRpc(err) if err.code() == ErrorCode::InvalidParams.code()));
+
+	let err = module.call::<_, String>("greet", ["world"]).await.unwrap_err();
+	assert!(matches!(err, MethodsError::JsonRpc(err) if err.code() == ErrorCode::InvalidParams.code()));
+
+	// Missing extensions are a server error.
+	let err = module.call::<_, u64>("extension", EmptyServerParams::new()).await.unwrap_err();
+	assert!(matches!(err, MethodsError::JsonRpc(err) if err.code() == INTERNAL_ERROR_CODE));
+}
+
 async fn run_subscription(req: &str, rpc: &RpcModule<()>) -> (u64, Box<RawValue>) {
 	let (rp, mut stream) = rpc.raw_json_request(req, 1).await.unwrap();
 	let resp = serde_json::from_str::<Response<u64>>(rp.get()).unwrap();
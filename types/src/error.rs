@@ -145,6 +145,10 @@ pub const SERVER_IS_BUSY_CODE: i32 = -32009;
 pub const TOO_BIG_BATCH_REQUEST_CODE: i32 = -32010;
 /// Batch response limit was exceed.
 pub const TOO_BIG_BATCH_RESPONSE_CODE: i32 = -32011;
+/// The connection was closed because the client didn't read the responses fast enough.
+pub const SLOW_CONSUMER_CODE: i32 = -32012;
+/// The call was rejected because the server is shutting down.
+pub const SHUTTING_DOWN_CODE: i32 = -32013;
 
 /// Parse error message
 pub const PARSE_ERROR_MSG: &str = "Parse error";
@@ -172,6 +176,10 @@ pub const TOO_MANY_SUBSCRIPTIONS_MSG: &str = "Too many subscriptions on the conn
 pub const TOO_BIG_BATCH_REQUEST_MSG: &str = "The batch request was too large";
 /// Batch request response limit was exceed.
 pub const TOO_BIG_BATCH_RESPONSE_MSG: &str = "The batch response was too large";
+/// The connection was closed because the client didn't read the responses fast enough.
+pub const SLOW_CONSUMER_MSG: &str = "The connection was closed because the client was too slow to read the responses";
+/// The call was rejected because the server is shutting down.
+pub const SHUTTING_DOWN_MSG: &str = "The server is shutting down";
 
 /// JSONRPC error code
 #[derive(Error, Debug, PartialEq, Eq, Copy, Clone)]
@@ -303,6 +311,20 @@ pub fn reject_too_big_batch_response(limit: usize) -> ErrorObjectOwned {
 	)
 }
 
+/// Helper to get a `JSON-RPC` error object when a connection is closed because its message buffer limit was exceeded.
+pub fn reject_slow_consumer(limit: usize) -> ErrorObjectOwned {
+	ErrorObjectOwned::owned(
+		SLOW_CONSUMER_CODE,
+		SLOW_CONSUMER_MSG,
+		Some(format!("Exceeded message buffer limit of {limit} bytes")),
+	)
+}
+
+/// Helper to get a `JSON-RPC` error object when a call is rejected because the server is shutting down.
+pub fn reject_shutting_down() -> ErrorObjectOwned {
+	ErrorObjectOwned::owned(SHUTTING_DOWN_CODE, SHUTTING_DOWN_MSG, None::<()>)
+}
+
 #[cfg(test)]
 mod tests {
 	use super::{ErrorCode, ErrorObject};
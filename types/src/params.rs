@@ -255,6 +255,25 @@ impl<'a> ParamsSequence<'a> {
 			None => Ok(None),
 		}
 	}
+
+	/// Assert that all parameters of the sequence have been parsed.
+	///
+	/// ```
+	/// # use jsonrpsee_types::params::Params;
+	/// let params = Params::new(Some(r#"[1, 2]"#));
+	/// let mut seq = params.sequence();
+	///
+	/// let _: u32 = seq.next().unwrap();
+	/// assert!(seq.finish().is_err());
+	///
+	/// let _: u32 = seq.next().unwrap();
+	/// assert!(seq.finish().is_ok());
+	/// ```
+	pub fn finish(&self) -> Result<(), ErrorObjectOwned> {
+		let json = self.0.strip_prefix('[').unwrap_or(self.0).trim_start();
+
+		if json.is_empty() || json.starts_with(']') { Ok(()) } else { Err(invalid_params("Too many params")) }
+	}
 }
 
 /// Id of a subscription, communicated by the server.